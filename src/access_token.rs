@@ -3,14 +3,32 @@
 //! The purpose for this module is providing a [AccessTokenProvider] trait with a method [get_access_token][AccessTokenProvider], return a struct [AccessToken].
 //!
 //! We also provide a default [TokenClient](by [reqwest](https://crates.io/crates/reqwest) crate) for the users didn't want to implement one themselves.
+//! [TokenClient] saves the token in a [TokenStore], use a shared store like [FileTokenStore] or [RedisTokenStore] when running multiple processes.
 use crate::{
     cache,
     error::{CommonResponse, SdkError, SdkResult},
+    utils::get_random_string,
+    wechat::{DomainPool, FailoverPolicy, API_BASE_URL},
 };
 use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
-use std::sync::{Arc, RwLock};
-use std::time::Duration;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+pub mod store;
+
+pub use store::{FileTokenStore, MemoryTokenStore, RedisCommands, RedisTokenStore, TokenStore};
+
+/// The lock is released automatically after it, in case the holder crashed.
+pub(crate) const REFRESH_LOCK_TTL: Duration = Duration::from_secs(10);
+/// The interval of checking the token refreshed by another process.
+pub(crate) const REFRESH_WAIT_INTERVAL: Duration = Duration::from_millis(100);
+/// The cached token expires earlier than WeChat's, so it's refreshed before it becomes invalid.
+pub const DEFAULT_REFRESH_BEFORE: Duration = Duration::from_secs(300);
+/// The interval of retrying in the background refresh task after a failure.
+const REFRESH_RETRY_INTERVAL: Duration = Duration::from_secs(10);
+/// The background refresh task renews the token this long before the cached one expires.
+const REFRESH_TASK_MARGIN: Duration = Duration::from_secs(60);
 
 /// [WxSdk][crate::wechat::WxSdk] take a struct which impl [AccessTokenProvider].
 /// You need to use [async_trait](https://crates.io/crates/async-trait) to implement [AccessTokenProvider].
@@ -18,6 +36,12 @@ use std::time::Duration;
 pub trait AccessTokenProvider: Sync + Send + Sized + Clone {
     /// This trait derive [async_trait](https://crates.io/crates/async-trait), it return a [std::future] of [AccessToken].
     async fn get_access_token(&self) -> SdkResult<AccessToken>;
+
+    /// Called when WeChat says `access_token` is invalid or expired, the cached one should be dropped if it's the same.
+    /// The default implementation does nothing.
+    async fn invalidate(&self, _access_token: &str) -> SdkResult<()> {
+        Ok(())
+    }
 }
 
 /// Access token with a expires time.
@@ -36,80 +60,274 @@ impl From<cache::Item<String>> for AccessToken {
     }
 }
 
-impl From<AccessToken> for cache::Item<String> {
-    fn from(t: AccessToken) -> Self {
-        let duration = Duration::from_secs((t.expires_in - 5) as u64);
-        cache::Item::new(t.access_token, Some(duration))
-    }
+/// The api used by [TokenClient] to get access token.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TokenMode {
+    /// [`/cgi-bin/token`](https://developers.weixin.qq.com/doc/offiaccount/Basic_Information/Get_access_token.html),
+    /// a new token invalidates the previous one.
+    #[default]
+    Normal,
+    /// [`/cgi-bin/stable_token`](https://developers.weixin.qq.com/doc/offiaccount/Basic_Information/getStableAccessToken.html),
+    /// returns the same token before it expires, so the services sharing one app don't invalidate each other.
+    Stable,
+}
+
+#[derive(Serialize)]
+struct StableTokenRequest<'a> {
+    grant_type: &'static str,
+    appid: &'a str,
+    secret: &'a str,
+    force_refresh: bool,
 }
 
 /// That's a default token client implement [AccessTokenProvider].
+///
+/// The token is saved in a [TokenStore], [MemoryTokenStore] by default.
+/// Before requesting a new token, it acquires the lock of the store, so only one process refreshes the token at a time.
+/// In the same process, the concurrent callers wait for a single in-flight refresh.
 #[derive(Clone)]
 pub struct TokenClient {
     app_id: String,
     app_secret: String,
-    cache_token: Arc<RwLock<Option<cache::Item<String>>>>,
+    store: Arc<dyn TokenStore>,
+    /// Identify this client as the owner of the refresh lock.
+    lock_owner: String,
+    refresh_before: Duration,
+    refreshing: Arc<tokio::sync::Mutex<()>>,
+    mode: TokenMode,
+    http_client: reqwest::Client,
+    domains: Arc<DomainPool>,
 }
 
 impl TokenClient {
     pub fn new(app_id: String, app_secret: String) -> Self {
+        Self::with_store(app_id, app_secret, MemoryTokenStore::new())
+    }
+
+    /// Create a token client with a custom [TokenStore].
+    pub fn with_store<S: TokenStore + 'static>(
+        app_id: String,
+        app_secret: String,
+        store: S,
+    ) -> Self {
         TokenClient {
             app_id,
             app_secret,
-            cache_token: Arc::new(RwLock::new(None)),
+            store: Arc::new(store),
+            lock_owner: get_random_string(16),
+            refresh_before: DEFAULT_REFRESH_BEFORE,
+            refreshing: Arc::new(tokio::sync::Mutex::new(())),
+            mode: TokenMode::Normal,
+            http_client: reqwest::Client::new(),
+            domains: Arc::new(DomainPool::new(FailoverPolicy::single(API_BASE_URL))),
         }
     }
 
-    fn get_cache_token(&self) -> Option<AccessToken> {
-        let locked = self.cache_token.read().unwrap();
-        match &*locked {
-            Some(i) if !i.expired() => Some(i.clone().into()),
-            _ => None,
+    /// Use a custom [reqwest::Client] to request the token.
+    pub fn with_http_client(mut self, http_client: reqwest::Client) -> Self {
+        self.http_client = http_client;
+        self
+    }
+
+    /// Replace the base url of the token api, [API_BASE_URL] by default.
+    pub fn with_base_url<S: AsRef<str>>(mut self, base_url: S) -> Self {
+        self.domains = Arc::new(DomainPool::new(FailoverPolicy::single(base_url)));
+        self
+    }
+
+    /// Share the api domains and their health status with [WxSdk][crate::WxSdk].
+    pub(crate) fn with_domains(mut self, domains: Arc<DomainPool>) -> Self {
+        self.domains = domains;
+        self
+    }
+
+    /// Set the api to get access token, [TokenMode::Normal] by default.
+    pub fn with_mode(mut self, mode: TokenMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// Request a new token ignoring the cached one.
+    ///
+    /// In [TokenMode::Stable] it calls `stable_token` with `force_refresh`, which invalidates the previous token
+    /// and is limited to 20 times a day.
+    pub async fn force_refresh(&self) -> SdkResult<AccessToken> {
+        let _refreshing = self.refreshing.lock().await;
+        self.refresh_access_token(&self.token_key(), true).await
+    }
+
+    /// Set how long before WeChat's expiry the token is refreshed, [DEFAULT_REFRESH_BEFORE] by default.
+    pub fn with_refresh_before(mut self, refresh_before: Duration) -> Self {
+        self.refresh_before = refresh_before;
+        self
+    }
+
+    /// Spawn a task renewing the token a minute before the cached one expires,
+    /// so the api callers don't wait for refreshing. The task runs until the returned handle is aborted.
+    ///
+    /// The task renews the token under the lock of the store, the token renewed by another process is reused.
+    /// In [TokenMode::Stable] `stable_token` returns the same token until WeChat renews it shortly before it expires,
+    /// so the task renews after the cached one lapses and never uses `force_refresh`.
+    pub fn spawn_refresh_task(&self) -> tokio::task::JoinHandle<()> {
+        let client = self.clone();
+        let margin = match self.mode {
+            TokenMode::Normal => REFRESH_TASK_MARGIN,
+            TokenMode::Stable => Duration::ZERO,
+        };
+        tokio::spawn(async move {
+            let key = client.token_key();
+            loop {
+                let renewed = {
+                    let _refreshing = client.refreshing.lock().await;
+                    client.get_or_refresh(&key, margin).await
+                };
+                let wait = match renewed {
+                    Ok(token) => Duration::from_secs(token.expires_in.max(0) as u64)
+                        .saturating_sub(margin)
+                        .max(Duration::from_secs(1)),
+                    Err(_) => REFRESH_RETRY_INTERVAL,
+                };
+                tokio::time::sleep(wait).await;
+            }
+        })
+    }
+
+    fn token_key(&self) -> String {
+        format!("wx_sdk:access_token:{}", self.app_id)
+    }
+
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(name = "wx_access_token", skip(self), fields(app_id = %self.app_id, mode = ?self.mode), err)
+    )]
+    async fn fetch_access_token(&self, force_refresh: bool) -> SdkResult<AccessToken> {
+        let msg = match self.mode {
+            TokenMode::Normal => {
+                let url = format!(
+                    "{}/cgi-bin/token?grant_type=client_credential&appid={}&secret={}",
+                    self.domains.primary(),
+                    self.app_id,
+                    self.app_secret
+                );
+                let request = self.http_client.get(&url).build()?;
+                // a new token is requested again if the response is lost
+                self.domains
+                    .execute(&self.http_client, request, true)
+                    .await?
+                    .json::<CommonResponse<AccessToken>>()
+                    .await?
+            }
+            TokenMode::Stable => {
+                let body = StableTokenRequest {
+                    grant_type: "client_credential",
+                    appid: &self.app_id,
+                    secret: &self.app_secret,
+                    force_refresh,
+                };
+                let request = self
+                    .http_client
+                    .post(format!("{}/cgi-bin/stable_token", self.domains.primary()))
+                    .json(&body)
+                    .build()?;
+                // forcing refresh twice invalidates the token got by the first one
+                self.domains
+                    .execute(&self.http_client, request, !force_refresh)
+                    .await?
+                    .json::<CommonResponse<AccessToken>>()
+                    .await?
+            }
+        };
+
+        match msg {
+            CommonResponse::Ok(at) => Ok(at),
+            CommonResponse::Err(e) => Err(SdkError::AccessTokenError(e)),
         }
     }
 
-    fn set_cache_token(&self, token: AccessToken) {
-        let mut locked = self.cache_token.write().unwrap();
-        *locked = Some(token.into())
+    /// Request a new token and save it, the returned and saved token expires `refresh_before` earlier.
+    ///
+    /// `stable_token` may return the previous token with its remaining lifetime, which is saved as is
+    /// if it's within `refresh_before`, otherwise it'd be requested again by every call.
+    async fn refresh_access_token(&self, key: &str, force_refresh: bool) -> SdkResult<AccessToken> {
+        let at = self.fetch_access_token(force_refresh).await?;
+        let refresh_before = self.refresh_before.as_secs() as i64;
+        let expires_in = match at.expires_in as i64 {
+            expires_in if expires_in > refresh_before => expires_in - refresh_before,
+            expires_in => expires_in.max(0),
+        };
+        let cached = AccessToken {
+            access_token: at.access_token,
+            expires_in: expires_in as i32,
+        };
+        self.store.set(key, &cached).await?;
+        Ok(cached)
+    }
+
+    /// Get the token from store if it has `min_ttl` left, or refresh it by the lock holder.
+    async fn get_or_refresh(&self, key: &str, min_ttl: Duration) -> SdkResult<AccessToken> {
+        let lock_key = format!("{}:lock", key);
+        let deadline = Instant::now() + REFRESH_LOCK_TTL;
+        let usable =
+            |token: &AccessToken| Duration::from_secs(token.expires_in.max(0) as u64) >= min_ttl;
+        loop {
+            if let Some(token) = self.store.get(key).await?.filter(usable) {
+                return Ok(token);
+            }
+            if self
+                .store
+                .try_lock(&lock_key, &self.lock_owner, REFRESH_LOCK_TTL)
+                .await?
+            {
+                // the token may be refreshed by the previous lock holder
+                let res = match self.store.get(key).await {
+                    Ok(Some(token)) if usable(&token) => Ok(token),
+                    Ok(_) => self.refresh_access_token(key, false).await,
+                    Err(e) => Err(e),
+                };
+                let unlocked = self.store.unlock(&lock_key, &self.lock_owner).await;
+                let token = res?;
+                unlocked?;
+                return Ok(token);
+            }
+            // another process is refreshing the token, wait for it
+            if Instant::now() >= deadline {
+                return self.refresh_access_token(key, false).await;
+            }
+            tokio::time::sleep(REFRESH_WAIT_INTERVAL).await;
+        }
     }
 }
 
 #[async_trait]
 impl AccessTokenProvider for TokenClient {
     async fn get_access_token(&self) -> SdkResult<AccessToken> {
-        let url = format!(
-            "https://api.weixin.qq.com/cgi-bin/token?grant_type=client_credential&appid={}&secret={}",
-            self.app_id.clone(),
-            self.app_secret.clone()
-        );
-        let cache_token = self.get_cache_token();
-        match cache_token {
-            Some(token) => Ok(token),
-            None => {
-                let msg = reqwest::get(&url)
-                    .await?
-                    .json::<CommonResponse<AccessToken>>()
-                    .await?;
-
-                match msg {
-                    CommonResponse::Ok(at) => {
-                        self.set_cache_token(at.clone());
-                        Ok(at)
-                    }
-                    CommonResponse::Err(e) => Err(SdkError::AccessTokenError(e)),
-                }
-            }
+        let key = self.token_key();
+        if let Some(token) = self.store.get(&key).await? {
+            return Ok(token);
+        }
+        // the tasks of this client wait here, only one of them refreshes the token
+        let _refreshing = self.refreshing.lock().await;
+        self.get_or_refresh(&key, Duration::ZERO).await
+    }
+
+    async fn invalidate(&self, access_token: &str) -> SdkResult<()> {
+        let key = self.token_key();
+        let _refreshing = self.refreshing.lock().await;
+        // keep the token refreshed by others
+        match self.store.get(&key).await? {
+            Some(token) if token.access_token == access_token => self.store.remove(&key).await,
+            _ => Ok(()),
         }
     }
 }
 #[cfg(test)]
 mod tests {
-    use std::time::SystemTime;
+    use std::time::Duration;
 
     use tokio::time::sleep;
 
     use crate::{
-        access_token::AccessTokenProvider, cache, error::CommonResponse, AccessToken, TokenClient,
+        access_token::AccessTokenProvider, error::CommonResponse, AccessToken, TokenClient,
     };
 
     #[test]
@@ -131,26 +349,312 @@ mod tests {
 
     #[tokio::test]
     async fn test_get_from_cache() {
-        use std::time::Duration;
-
-        let token_client = TokenClient {
-            app_id: "app_id".to_owned(),
-            app_secret: "secret".to_owned(),
-            cache_token: std::sync::Arc::new(std::sync::RwLock::new(Some(cache::Item::new(
-                "ACCESS_TOKEN".to_owned(),
-                Some(Duration::from_secs(2)),
-            )))),
-        };
+        use crate::access_token::{MemoryTokenStore, TokenStore};
+
+        let store = MemoryTokenStore::new();
+        store
+            .set(
+                "wx_sdk:access_token:app_id",
+                &AccessToken {
+                    access_token: "ACCESS_TOKEN".to_owned(),
+                    expires_in: 2,
+                },
+            )
+            .await
+            .unwrap();
+        let token_client = TokenClient::with_store("app_id".to_owned(), "secret".to_owned(), store);
         sleep(Duration::new(1, 0)).await;
         let res = token_client.get_access_token().await.unwrap();
         let token = res.access_token;
         let new_t = token_client.get_access_token().await.unwrap();
-        assert_eq!(
-            new_t,
-            AccessToken {
-                access_token: token,
-                expires_in: 0
+        assert_eq!(new_t.access_token, token);
+        assert!(new_t.expires_in <= 1);
+    }
+
+    #[tokio::test]
+    async fn test_wait_for_refreshing() {
+        use crate::access_token::{MemoryTokenStore, TokenStore};
+        use std::sync::Arc;
+
+        let store = Arc::new(MemoryTokenStore::new());
+        // another process is holding the lock
+        let lock_key = "wx_sdk:access_token:app_id:lock";
+        assert!(store
+            .try_lock(lock_key, "other", Duration::from_secs(5))
+            .await
+            .unwrap());
+        let token_client =
+            TokenClient::with_store("app_id".to_owned(), "secret".to_owned(), store.clone());
+        let waiting = tokio::spawn(async move { token_client.get_access_token().await });
+        sleep(Duration::from_millis(300)).await;
+        store
+            .set(
+                "wx_sdk:access_token:app_id",
+                &AccessToken {
+                    access_token: "REFRESHED".to_owned(),
+                    expires_in: 7200,
+                },
+            )
+            .await
+            .unwrap();
+        store.unlock(lock_key, "other").await.unwrap();
+        let token = waiting.await.unwrap().unwrap();
+        assert_eq!(token.access_token, "REFRESHED");
+    }
+
+    #[tokio::test]
+    async fn test_single_flight() {
+        use crate::{
+            access_token::{MemoryTokenStore, TokenStore},
+            SdkResult,
+        };
+        use std::sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        };
+
+        /// Count the lock attempts
+        #[derive(Default)]
+        struct Counting(MemoryTokenStore, AtomicUsize);
+
+        #[async_trait::async_trait]
+        impl TokenStore for Counting {
+            async fn get(&self, key: &str) -> SdkResult<Option<AccessToken>> {
+                self.0.get(key).await
+            }
+            async fn set(&self, key: &str, token: &AccessToken) -> SdkResult<()> {
+                self.0.set(key, token).await
+            }
+            async fn remove(&self, key: &str) -> SdkResult<()> {
+                self.0.remove(key).await
+            }
+            async fn try_lock(&self, key: &str, owner: &str, ttl: Duration) -> SdkResult<bool> {
+                self.1.fetch_add(1, Ordering::SeqCst);
+                self.0.try_lock(key, owner, ttl).await
             }
+            async fn unlock(&self, key: &str, owner: &str) -> SdkResult<()> {
+                self.0.unlock(key, owner).await
+            }
+        }
+
+        let store = Arc::new(Counting::default());
+        let lock_key = "wx_sdk:access_token:app_id:lock";
+        store
+            .0
+            .try_lock(lock_key, "other", Duration::from_secs(5))
+            .await
+            .unwrap();
+        let token_client =
+            TokenClient::with_store("app_id".to_owned(), "secret".to_owned(), store.clone());
+        let tasks: Vec<_> = (0..20)
+            .map(|_| {
+                let client = token_client.clone();
+                tokio::spawn(async move { client.get_access_token().await })
+            })
+            .collect();
+        sleep(Duration::from_millis(250)).await;
+        store
+            .set(
+                "wx_sdk:access_token:app_id",
+                &AccessToken {
+                    access_token: "REFRESHED".to_owned(),
+                    expires_in: 7200,
+                },
+            )
+            .await
+            .unwrap();
+        store.unlock(lock_key, "other").await.unwrap();
+        for task in tasks {
+            assert_eq!(task.await.unwrap().unwrap().access_token, "REFRESHED");
+        }
+        // only one task polls the lock, the others wait for it
+        assert!(store.1.load(Ordering::SeqCst) < 10);
+    }
+
+    #[tokio::test]
+    async fn test_invalidate() {
+        use crate::access_token::{MemoryTokenStore, TokenStore};
+        use std::sync::Arc;
+
+        let store = Arc::new(MemoryTokenStore::new());
+        let key = "wx_sdk:access_token:app_id";
+        let token = AccessToken {
+            access_token: "ACCESS_TOKEN".to_owned(),
+            expires_in: 7200,
+        };
+        store.set(key, &token).await.unwrap();
+        let token_client =
+            TokenClient::with_store("app_id".to_owned(), "secret".to_owned(), store.clone());
+        // the token has been refreshed by others
+        token_client.invalidate("OLD_TOKEN").await.unwrap();
+        assert!(store.get(key).await.unwrap().is_some());
+        token_client.invalidate("ACCESS_TOKEN").await.unwrap();
+        assert!(store.get(key).await.unwrap().is_none());
+    }
+
+    #[cfg(feature = "testing")]
+    #[tokio::test]
+    async fn test_refresh_task() {
+        use crate::access_token::{MemoryTokenStore, TokenStore};
+        use crate::testing::{MockServer, MOCK_ACCESS_TOKEN};
+        use std::sync::Arc;
+
+        let server = MockServer::start().await.unwrap();
+        let store = Arc::new(MemoryTokenStore::new());
+        let key = "wx_sdk:access_token:app_id";
+        // the cached token is about to expire
+        store
+            .set(
+                key,
+                &AccessToken {
+                    access_token: "EXPIRING".to_owned(),
+                    expires_in: 30,
+                },
+            )
+            .await
+            .unwrap();
+        let token_client =
+            TokenClient::with_store("app_id".to_owned(), "secret".to_owned(), store.clone())
+                .with_base_url(server.base_url());
+        let task = token_client.spawn_refresh_task();
+        sleep(Duration::from_millis(300)).await;
+        task.abort();
+        let token = store.get(key).await.unwrap().unwrap();
+        assert_eq!(token.access_token, MOCK_ACCESS_TOKEN);
+        // renewed with the default refresh window
+        assert!(token.expires_in <= 6900 && token.expires_in > 6890);
+        assert_eq!(server.requests_to("/cgi-bin/token").len(), 1);
+    }
+
+    #[cfg(feature = "testing")]
+    #[tokio::test]
+    async fn test_refresh_task_under_lock() {
+        use crate::access_token::{MemoryTokenStore, TokenStore};
+        use crate::testing::MockServer;
+        use std::sync::Arc;
+
+        let server = MockServer::start().await.unwrap();
+        let store = Arc::new(MemoryTokenStore::new());
+        let key = "wx_sdk:access_token:app_id";
+        let lock_key = "wx_sdk:access_token:app_id:lock";
+        // another process is renewing the token
+        assert!(store
+            .try_lock(lock_key, "other", Duration::from_secs(5))
+            .await
+            .unwrap());
+        let token_client =
+            TokenClient::with_store("app_id".to_owned(), "secret".to_owned(), store.clone())
+                .with_base_url(server.base_url());
+        let task = token_client.spawn_refresh_task();
+        sleep(Duration::from_millis(300)).await;
+        store
+            .set(
+                key,
+                &AccessToken {
+                    access_token: "RENEWED".to_owned(),
+                    expires_in: 6900,
+                },
+            )
+            .await
+            .unwrap();
+        store.unlock(lock_key, "other").await.unwrap();
+        sleep(Duration::from_millis(300)).await;
+        task.abort();
+        assert_eq!(
+            store.get(key).await.unwrap().unwrap().access_token,
+            "RENEWED"
+        );
+        assert!(server.requests_to("/cgi-bin/token").is_empty());
+    }
+
+    #[cfg(feature = "testing")]
+    #[tokio::test]
+    async fn test_stable_refresh_task() {
+        use crate::access_token::{MemoryTokenStore, TokenMode, TokenStore};
+        use crate::testing::{MockServer, MOCK_ACCESS_TOKEN};
+        use std::sync::Arc;
+
+        let server = MockServer::start().await.unwrap();
+        let store = Arc::new(MemoryTokenStore::new());
+        let key = "wx_sdk:access_token:app_id";
+        store
+            .set(
+                key,
+                &AccessToken {
+                    access_token: "EXPIRING".to_owned(),
+                    expires_in: 1,
+                },
+            )
+            .await
+            .unwrap();
+        let token_client =
+            TokenClient::with_store("app_id".to_owned(), "secret".to_owned(), store.clone())
+                .with_base_url(server.base_url())
+                .with_mode(TokenMode::Stable);
+        let task = token_client.spawn_refresh_task();
+        sleep(Duration::from_millis(300)).await;
+        // the cached token is reused until it lapses
+        assert!(server.requests_to("/cgi-bin/stable_token").is_empty());
+        sleep(Duration::from_millis(2000)).await;
+        task.abort();
+        let token = store.get(key).await.unwrap().unwrap();
+        assert_eq!(token.access_token, MOCK_ACCESS_TOKEN);
+        let requests = server.requests_to("/cgi-bin/stable_token");
+        assert_eq!(requests.len(), 1);
+        let body: serde_json::Value = requests[0].json().unwrap();
+        assert_eq!(body["force_refresh"], false);
+    }
+
+    #[cfg(feature = "testing")]
+    #[tokio::test]
+    async fn test_stable_token_in_refresh_window() {
+        use crate::access_token::{MemoryTokenStore, TokenMode, TokenStore};
+        use crate::testing::{MockResponse, MockServer};
+        use std::sync::Arc;
+
+        let server = MockServer::start().await.unwrap();
+        // the previous token is returned with its remaining lifetime
+        server.mock(
+            "/cgi-bin/stable_token",
+            MockResponse::json(&serde_json::json!({ "access_token": "STABLE", "expires_in": 120 })),
+        );
+        let store = Arc::new(MemoryTokenStore::new());
+        let token_client =
+            TokenClient::with_store("app_id".to_owned(), "secret".to_owned(), store.clone())
+                .with_base_url(server.base_url())
+                .with_mode(TokenMode::Stable);
+        let token = token_client.get_access_token().await.unwrap();
+        assert_eq!(token.access_token, "STABLE");
+        assert_eq!(token.expires_in, 120);
+        // cached for its real remaining lifetime
+        let cached = store
+            .get("wx_sdk:access_token:app_id")
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(cached.expires_in > 110 && cached.expires_in <= 120);
+        token_client.get_access_token().await.unwrap();
+        assert_eq!(server.requests_to("/cgi-bin/stable_token").len(), 1);
+    }
+
+    #[test]
+    fn test_stable_token_request() {
+        use crate::access_token::StableTokenRequest;
+
+        let body = StableTokenRequest {
+            grant_type: "client_credential",
+            appid: "APPID",
+            secret: "APPSECRET",
+            force_refresh: false,
+        };
+        assert_eq!(
+            serde_json::to_value(&body).unwrap(),
+            serde_json::json!({
+                "grant_type": "client_credential",
+                "appid": "APPID",
+                "secret": "APPSECRET",
+                "force_refresh": false
+            })
         );
     }
 }
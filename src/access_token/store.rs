@@ -0,0 +1,596 @@
+//! Access token storage shared by [TokenClient][crate::TokenClient].
+//!
+//! WeChat only keeps the newest access token valid, so all the processes using the same app should share one token.
+//! A [TokenStore] saves the token and provides a lock, only the lock holder requests a new token from WeChat.
+//!
+//! - [MemoryTokenStore]: in-process storage, the default one.
+//! - [FileTokenStore]: stores the token in a directory, shared by the processes on the same host.
+//! - [RedisTokenStore]: stores the token in Redis through [RedisCommands], enable the `redis` feature to use
+//!   `redis::aio::ConnectionManager` directly.
+use std::{
+    collections::HashMap,
+    io::ErrorKind,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+
+use crate::{AccessToken, SdkResult};
+
+/// Storage of access tokens, with a lock to make sure only one process refreshes the token at a time.
+#[async_trait]
+pub trait TokenStore: Send + Sync {
+    /// Get the unexpired token, its `expires_in` is the remaining seconds.
+    async fn get(&self, key: &str) -> SdkResult<Option<AccessToken>>;
+    /// Save the token, it expires after `expires_in` seconds.
+    async fn set(&self, key: &str, token: &AccessToken) -> SdkResult<()>;
+    /// Remove the token.
+    async fn remove(&self, key: &str) -> SdkResult<()>;
+    /// Try to acquire the lock for `ttl`, return `false` if the lock is held by another owner.
+    async fn try_lock(&self, key: &str, owner: &str, ttl: Duration) -> SdkResult<bool>;
+    /// Release the lock if it's held by `owner`.
+    async fn unlock(&self, key: &str, owner: &str) -> SdkResult<()>;
+}
+
+/// Share one store between multiple [TokenClient][crate::TokenClient]s.
+#[async_trait]
+impl<S: TokenStore + ?Sized> TokenStore for Arc<S> {
+    async fn get(&self, key: &str) -> SdkResult<Option<AccessToken>> {
+        (**self).get(key).await
+    }
+
+    async fn set(&self, key: &str, token: &AccessToken) -> SdkResult<()> {
+        (**self).set(key, token).await
+    }
+
+    async fn remove(&self, key: &str) -> SdkResult<()> {
+        (**self).remove(key).await
+    }
+
+    async fn try_lock(&self, key: &str, owner: &str, ttl: Duration) -> SdkResult<bool> {
+        (**self).try_lock(key, owner, ttl).await
+    }
+
+    async fn unlock(&self, key: &str, owner: &str) -> SdkResult<()> {
+        (**self).unlock(key, owner).await
+    }
+}
+
+/// The token saved in file or Redis, with a unix timestamp of expiry.
+#[derive(Serialize, Deserialize)]
+struct StoredToken {
+    access_token: String,
+    expires_at: u64,
+}
+
+impl StoredToken {
+    fn new(token: &AccessToken) -> Self {
+        StoredToken {
+            access_token: token.access_token.clone(),
+            expires_at: unix_now() + token.expires_in.max(0) as u64,
+        }
+    }
+
+    fn into_token(self) -> Option<AccessToken> {
+        let now = unix_now();
+        if self.expires_at <= now {
+            return None;
+        }
+        Some(AccessToken {
+            access_token: self.access_token,
+            expires_in: (self.expires_at - now) as i32,
+        })
+    }
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+#[cfg(feature = "redis")]
+fn store_error<E: ToString>(e: E) -> crate::error::SdkError {
+    crate::error::SdkError::TokenStoreError(e.to_string())
+}
+
+/// In-process token storage, tokens are not shared between processes.
+#[derive(Default)]
+pub struct MemoryTokenStore {
+    tokens: Mutex<HashMap<String, (String, Instant)>>,
+    locks: Mutex<HashMap<String, (String, Instant)>>,
+}
+
+impl MemoryTokenStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl TokenStore for MemoryTokenStore {
+    async fn get(&self, key: &str) -> SdkResult<Option<AccessToken>> {
+        let tokens = self.tokens.lock().unwrap();
+        let now = Instant::now();
+        Ok(tokens
+            .get(key)
+            .filter(|(_, expiry)| *expiry > now)
+            .map(|(token, expiry)| AccessToken {
+                access_token: token.clone(),
+                expires_in: (*expiry - now).as_secs() as i32,
+            }))
+    }
+
+    async fn set(&self, key: &str, token: &AccessToken) -> SdkResult<()> {
+        let expiry = Instant::now() + Duration::from_secs(token.expires_in.max(0) as u64);
+        let mut tokens = self.tokens.lock().unwrap();
+        tokens.insert(key.to_owned(), (token.access_token.clone(), expiry));
+        Ok(())
+    }
+
+    async fn remove(&self, key: &str) -> SdkResult<()> {
+        self.tokens.lock().unwrap().remove(key);
+        Ok(())
+    }
+
+    async fn try_lock(&self, key: &str, owner: &str, ttl: Duration) -> SdkResult<bool> {
+        let mut locks = self.locks.lock().unwrap();
+        let now = Instant::now();
+        match locks.get(key) {
+            Some((o, expiry)) if o != owner && *expiry > now => Ok(false),
+            _ => {
+                locks.insert(key.to_owned(), (owner.to_owned(), now + ttl));
+                Ok(true)
+            }
+        }
+    }
+
+    async fn unlock(&self, key: &str, owner: &str) -> SdkResult<()> {
+        let mut locks = self.locks.lock().unwrap();
+        if matches!(locks.get(key), Some((o, _)) if o == owner) {
+            locks.remove(key);
+        }
+        Ok(())
+    }
+}
+
+/// Token storage in a directory, the token is saved as `<key>.json` and the lock is a `<key>.lock` file.
+pub struct FileTokenStore {
+    dir: PathBuf,
+}
+
+impl FileTokenStore {
+    /// The directory will be created if it doesn't exist.
+    pub fn new<P: Into<PathBuf>>(dir: P) -> Self {
+        FileTokenStore { dir: dir.into() }
+    }
+
+    fn path(&self, key: &str, ext: &str) -> PathBuf {
+        let name: String = key
+            .chars()
+            .map(|c| {
+                if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                    c
+                } else {
+                    '_'
+                }
+            })
+            .collect();
+        self.dir.join(format!("{}.{}", name, ext))
+    }
+
+    async fn read(path: &Path) -> SdkResult<Option<String>> {
+        match tokio::fs::read_to_string(path).await {
+            Ok(s) => Ok(Some(s)),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    async fn remove_file(path: &Path) -> SdkResult<()> {
+        match tokio::fs::remove_file(path).await {
+            Err(e) if e.kind() != ErrorKind::NotFound => Err(e.into()),
+            _ => Ok(()),
+        }
+    }
+
+    /// Write `content` to a new temporary file beside the `key` files, the caller moves it into place.
+    async fn write_tmp(&self, key: &str, content: &str) -> SdkResult<PathBuf> {
+        let tmp = self.path(key, &format!("{}.tmp", crate::utils::get_random_string(8)));
+        tokio::fs::write(&tmp, content).await?;
+        Ok(tmp)
+    }
+
+    /// The lock file contains the owner and the unix timestamp in millis of expiry.
+    fn parse_lock(content: &str) -> Option<(&str, u128)> {
+        let mut lines = content.lines();
+        let owner = lines.next()?;
+        let expires_at = lines.next()?.parse().ok()?;
+        Some((owner, expires_at))
+    }
+
+    /// The expiry of a lock file that can't be parsed or the guard file, it's held until `ttl` after its last modification.
+    async fn broken_lock_expiry(path: &Path, ttl: Duration) -> SdkResult<u128> {
+        let modified = match tokio::fs::metadata(path).await {
+            Ok(meta) => meta.modified()?,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(0),
+            Err(e) => return Err(e.into()),
+        };
+        let modified = modified
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis();
+        Ok(modified + ttl.as_millis())
+    }
+
+    /// Remove the expired lock whose content is `stale`, return whether it's removed.
+    ///
+    /// The removal is guarded by a `.reap` file created with `create_new`, the holder re-checks the lock
+    /// is still the stale one before removing it, so a lock taken by another process is never removed.
+    async fn remove_stale_lock(
+        &self,
+        key: &str,
+        path: &Path,
+        stale: &str,
+        ttl: Duration,
+    ) -> SdkResult<bool> {
+        let guard = self.path(key, "reap");
+        let created = tokio::fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&guard)
+            .await;
+        match created {
+            Ok(_) => {}
+            Err(e) if e.kind() == ErrorKind::AlreadyExists => {
+                // the guard is left by a crashed process
+                if Self::broken_lock_expiry(&guard, ttl).await? <= unix_now_millis() {
+                    Self::remove_file(&guard).await?;
+                }
+                return Ok(false);
+            }
+            Err(e) => return Err(e.into()),
+        }
+        let removed = match Self::read(path).await {
+            Ok(Some(content)) if content == stale => Self::remove_file(path).await.map(|_| true),
+            Ok(_) => Ok(false),
+            Err(e) => Err(e),
+        };
+        Self::remove_file(&guard).await?;
+        removed
+    }
+
+    /// Link `tmp` as the lock of `key`, or extend the lock if it's held by `owner`.
+    async fn lock_with(
+        &self,
+        key: &str,
+        path: &Path,
+        tmp: &Path,
+        owner: &str,
+        ttl: Duration,
+    ) -> SdkResult<bool> {
+        // retry once after removing an expired lock
+        for _ in 0..2 {
+            match tokio::fs::hard_link(tmp, path).await {
+                Ok(()) => return Ok(true),
+                Err(e) if e.kind() == ErrorKind::AlreadyExists => {}
+                Err(e) => return Err(e.into()),
+            }
+            let current = match Self::read(path).await? {
+                Some(current) => current,
+                // released in between
+                None => continue,
+            };
+            let expires_at = match Self::parse_lock(&current) {
+                Some((o, _)) if o == owner => {
+                    // extend our own lock
+                    tokio::fs::rename(tmp, path).await?;
+                    return Ok(true);
+                }
+                Some((_, expires_at)) => expires_at,
+                // a lock that can't be parsed is held, not expired
+                None => Self::broken_lock_expiry(path, ttl).await?,
+            };
+            if expires_at > unix_now_millis()
+                || !self.remove_stale_lock(key, path, &current, ttl).await?
+            {
+                return Ok(false);
+            }
+        }
+        Ok(false)
+    }
+}
+
+fn unix_now_millis() -> u128 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis()
+}
+
+#[async_trait]
+impl TokenStore for FileTokenStore {
+    async fn get(&self, key: &str) -> SdkResult<Option<AccessToken>> {
+        match Self::read(&self.path(key, "json")).await? {
+            // a broken file is treated as no token
+            Some(content) => Ok(serde_json::from_str::<StoredToken>(&content)
+                .ok()
+                .and_then(StoredToken::into_token)),
+            None => Ok(None),
+        }
+    }
+
+    async fn set(&self, key: &str, token: &AccessToken) -> SdkResult<()> {
+        tokio::fs::create_dir_all(&self.dir).await?;
+        let path = self.path(key, "json");
+        // write to a temporary file then rename, so the readers never see a partial file
+        let tmp = self
+            .write_tmp(key, &serde_json::to_string(&StoredToken::new(token))?)
+            .await?;
+        tokio::fs::rename(&tmp, &path).await?;
+        Ok(())
+    }
+
+    async fn remove(&self, key: &str) -> SdkResult<()> {
+        Self::remove_file(&self.path(key, "json")).await
+    }
+
+    async fn try_lock(&self, key: &str, owner: &str, ttl: Duration) -> SdkResult<bool> {
+        tokio::fs::create_dir_all(&self.dir).await?;
+        let path = self.path(key, "lock");
+        let content = format!("{}\n{}", owner, unix_now_millis() + ttl.as_millis());
+        // the lock is written to a temporary file then linked into place, so it's created with its content
+        let tmp = self.write_tmp(key, &content).await?;
+        let locked = self.lock_with(key, &path, &tmp, owner, ttl).await;
+        Self::remove_file(&tmp).await?;
+        locked
+    }
+
+    async fn unlock(&self, key: &str, owner: &str) -> SdkResult<()> {
+        let path = self.path(key, "lock");
+        match Self::read(&path).await? {
+            Some(content) if matches!(Self::parse_lock(&content), Some((o, _)) if o == owner) => {
+                Self::remove_file(&path).await
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// The Redis commands used by [RedisTokenStore], implement it for your Redis client.
+///
+/// It's implemented for `redis::aio::ConnectionManager` with the `redis` feature.
+#[async_trait]
+pub trait RedisCommands: Send + Sync {
+    /// `GET key`
+    async fn get(&self, key: &str) -> SdkResult<Option<String>>;
+    /// `SET key value PX ttl [NX]`, return whether the value is set.
+    async fn set(&self, key: &str, value: &str, ttl: Duration, nx: bool) -> SdkResult<bool>;
+    /// `DEL key`
+    async fn del(&self, key: &str) -> SdkResult<()>;
+    /// Delete the key only if its value equals `value`, it should be atomic, e.g. by a Lua script.
+    async fn del_if_eq(&self, key: &str, value: &str) -> SdkResult<()>;
+}
+
+/// Token storage in Redis, the lock is a key set by `SET NX PX`.
+pub struct RedisTokenStore<C: RedisCommands> {
+    client: C,
+}
+
+impl<C: RedisCommands> RedisTokenStore<C> {
+    pub fn new(client: C) -> Self {
+        RedisTokenStore { client }
+    }
+}
+
+#[async_trait]
+impl<C: RedisCommands> TokenStore for RedisTokenStore<C> {
+    async fn get(&self, key: &str) -> SdkResult<Option<AccessToken>> {
+        match self.client.get(key).await? {
+            Some(value) => Ok(serde_json::from_str::<StoredToken>(&value)
+                .ok()
+                .and_then(StoredToken::into_token)),
+            None => Ok(None),
+        }
+    }
+
+    async fn set(&self, key: &str, token: &AccessToken) -> SdkResult<()> {
+        if token.expires_in <= 0 {
+            return self.client.del(key).await;
+        }
+        let value = serde_json::to_string(&StoredToken::new(token))?;
+        let ttl = Duration::from_secs(token.expires_in as u64);
+        self.client.set(key, &value, ttl, false).await?;
+        Ok(())
+    }
+
+    async fn remove(&self, key: &str) -> SdkResult<()> {
+        self.client.del(key).await
+    }
+
+    async fn try_lock(&self, key: &str, owner: &str, ttl: Duration) -> SdkResult<bool> {
+        self.client.set(key, owner, ttl, true).await
+    }
+
+    async fn unlock(&self, key: &str, owner: &str) -> SdkResult<()> {
+        self.client.del_if_eq(key, owner).await
+    }
+}
+
+#[cfg(feature = "redis")]
+const UNLOCK_SCRIPT: &str = r#"if redis.call("GET", KEYS[1]) == ARGV[1] then
+    return redis.call("DEL", KEYS[1])
+else
+    return 0
+end"#;
+
+#[cfg(feature = "redis")]
+#[async_trait]
+impl RedisCommands for redis::aio::ConnectionManager {
+    async fn get(&self, key: &str) -> SdkResult<Option<String>> {
+        let mut conn = self.clone();
+        redis::cmd("GET")
+            .arg(key)
+            .query_async(&mut conn)
+            .await
+            .map_err(store_error)
+    }
+
+    async fn set(&self, key: &str, value: &str, ttl: Duration, nx: bool) -> SdkResult<bool> {
+        let mut conn = self.clone();
+        let mut cmd = redis::cmd("SET");
+        cmd.arg(key)
+            .arg(value)
+            .arg("PX")
+            .arg(ttl.as_millis() as u64);
+        if nx {
+            cmd.arg("NX");
+        }
+        let res: Option<String> = cmd.query_async(&mut conn).await.map_err(store_error)?;
+        Ok(res.is_some())
+    }
+
+    async fn del(&self, key: &str) -> SdkResult<()> {
+        let mut conn = self.clone();
+        redis::cmd("DEL")
+            .arg(key)
+            .query_async(&mut conn)
+            .await
+            .map_err(store_error)
+    }
+
+    async fn del_if_eq(&self, key: &str, value: &str) -> SdkResult<()> {
+        let mut conn = self.clone();
+        redis::Script::new(UNLOCK_SCRIPT)
+            .key(key)
+            .arg(value)
+            .invoke_async(&mut conn)
+            .await
+            .map_err(store_error)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A local stand-in of Redis
+    #[derive(Default)]
+    struct FakeRedis(Mutex<HashMap<String, (String, Instant)>>);
+
+    #[async_trait]
+    impl RedisCommands for FakeRedis {
+        async fn get(&self, key: &str) -> SdkResult<Option<String>> {
+            let map = self.0.lock().unwrap();
+            Ok(map
+                .get(key)
+                .filter(|(_, expiry)| *expiry > Instant::now())
+                .map(|(v, _)| v.clone()))
+        }
+
+        async fn set(&self, key: &str, value: &str, ttl: Duration, nx: bool) -> SdkResult<bool> {
+            let mut map = self.0.lock().unwrap();
+            if nx && matches!(map.get(key), Some((_, expiry)) if *expiry > Instant::now()) {
+                return Ok(false);
+            }
+            map.insert(key.to_owned(), (value.to_owned(), Instant::now() + ttl));
+            Ok(true)
+        }
+
+        async fn del(&self, key: &str) -> SdkResult<()> {
+            self.0.lock().unwrap().remove(key);
+            Ok(())
+        }
+
+        async fn del_if_eq(&self, key: &str, value: &str) -> SdkResult<()> {
+            let mut map = self.0.lock().unwrap();
+            if matches!(map.get(key), Some((v, _)) if v == value) {
+                map.remove(key);
+            }
+            Ok(())
+        }
+    }
+
+    async fn check_store<S: TokenStore>(store: S) -> SdkResult<()> {
+        assert!(store.get("token").await?.is_none());
+        let token = AccessToken {
+            access_token: "ACCESS_TOKEN".to_owned(),
+            expires_in: 7200,
+        };
+        store.set("token", &token).await?;
+        let cached = store.get("token").await?.unwrap();
+        assert_eq!(cached.access_token, "ACCESS_TOKEN");
+        assert!(cached.expires_in > 7190 && cached.expires_in <= 7200);
+        store.remove("token").await?;
+        assert!(store.get("token").await?.is_none());
+
+        let ttl = Duration::from_millis(200);
+        assert!(store.try_lock("lock", "a", ttl).await?);
+        assert!(!store.try_lock("lock", "b", ttl).await?);
+        // only the owner can release the lock
+        store.unlock("lock", "b").await?;
+        assert!(!store.try_lock("lock", "b", ttl).await?);
+        store.unlock("lock", "a").await?;
+        assert!(store.try_lock("lock", "b", ttl).await?);
+        // the lock expires after ttl
+        tokio::time::sleep(Duration::from_millis(300)).await;
+        assert!(store.try_lock("lock", "a", ttl).await?);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_memory_store() -> SdkResult<()> {
+        check_store(MemoryTokenStore::new()).await
+    }
+
+    #[tokio::test]
+    async fn test_file_store() -> SdkResult<()> {
+        let dir = std::env::temp_dir().join(format!(
+            "wx-sdk-token-{}",
+            crate::utils::get_random_string(8)
+        ));
+        check_store(FileTokenStore::new(&dir)).await?;
+        let other = FileTokenStore::new(&dir);
+        other
+            .set(
+                "wx_sdk:access_token:app_id",
+                &AccessToken {
+                    access_token: "SHARED".to_owned(),
+                    expires_in: 60,
+                },
+            )
+            .await?;
+        let token = FileTokenStore::new(&dir)
+            .get("wx_sdk:access_token:app_id")
+            .await?;
+        assert_eq!(token.unwrap().access_token, "SHARED");
+
+        // a lock being written or broken is held, not expired
+        let ttl = Duration::from_millis(200);
+        tokio::fs::write(other.path("broken", "lock"), "").await?;
+        assert!(!other.try_lock("broken", "a", ttl).await?);
+        tokio::time::sleep(Duration::from_millis(300)).await;
+        assert!(other.try_lock("broken", "a", ttl).await?);
+
+        // only one of the concurrent lockers takes over an expired lock
+        assert!(other.try_lock("stale", "a", ttl).await?);
+        tokio::time::sleep(Duration::from_millis(300)).await;
+        let lockers = (0..8).map(|i| {
+            let store = FileTokenStore::new(&dir);
+            async move { store.try_lock("stale", &i.to_string(), ttl).await }
+        });
+        let locked = futures::future::try_join_all(lockers).await?;
+        assert_eq!(locked.iter().filter(|l| **l).count(), 1);
+        tokio::fs::remove_dir_all(&dir).await?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_redis_store() -> SdkResult<()> {
+        check_store(RedisTokenStore::new(FakeRedis::default())).await
+    }
+}
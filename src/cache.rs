@@ -36,6 +36,21 @@ impl<T, V> Cache<T, V> {
         }
     }
 
+    /// Creates a cache with initial items.
+    #[cfg(feature = "pay")]
+    pub fn with_items<I: IntoIterator<Item = (T, V, Option<Duration>)>>(items: I) -> Self
+    where
+        T: Eq + Hash,
+    {
+        let items = items
+            .into_iter()
+            .map(|(k, v, d)| (k, Item::new(v, d)))
+            .collect();
+        Cache {
+            items: RwLock::new(items),
+        }
+    }
+
     /// Get a cache item associated with a given key.
     pub async fn get(&self, key: &T) -> Option<V>
     where
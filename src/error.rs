@@ -15,6 +15,164 @@ pub struct CommonError {
     pub errmsg: String,
 }
 
+impl CommonError {
+    /// The request id at the end of `errmsg`, e.g. `invalid credential rid: 61725984-6126f6f9-040f19c4`,
+    /// it can be looked up by [OpenApiModule::get_rid_info][crate::openapi::OpenApiModule::get_rid_info].
+    pub fn rid(&self) -> Option<&str> {
+        let (_, rid) = self.errmsg.rsplit_once("rid:")?;
+        let rid = rid.split_whitespace().next()?;
+        Some(rid)
+    }
+
+    /// The typed [WxErrorCode] of `errcode`.
+    pub fn code(&self) -> WxErrorCode {
+        WxErrorCode::from(self.errcode)
+    }
+}
+
+/// The well-known global errcodes of WeChat api, see [全局返回码说明](https://developers.weixin.qq.com/doc/offiaccount/Getting_Started/Global_Return_Code.html).
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+pub enum WxErrorCode {
+    /// -1 系统繁忙，此时请开发者稍候再试
+    SystemBusy,
+    /// 0 请求成功
+    Ok,
+    /// 40001 获取 access_token 时 AppSecret 错误，或者 access_token 无效
+    InvalidCredential,
+    /// 40002 不合法的凭证类型
+    InvalidGrantType,
+    /// 40003 不合法的 OpenID
+    InvalidOpenid,
+    /// 40013 不合法的 AppID
+    InvalidAppid,
+    /// 40014 不合法的 access_token
+    InvalidAccessToken,
+    /// 40029 无效的 oauth_code
+    InvalidCode,
+    /// 40125 不合法的 AppSecret
+    InvalidAppSecret,
+    /// 40163 oauth_code 已使用
+    CodeBeenUsed,
+    /// 40164 调用接口的 IP 地址不在白名单中
+    InvalidIp,
+    /// 41001 缺少 access_token 参数
+    AccessTokenMissing,
+    /// 42001 access_token 超时
+    AccessTokenExpired,
+    /// 43004 需要接收者关注
+    RequireSubscribe,
+    /// 45009 接口调用超过限制
+    ApiQuotaExceeded,
+    /// 45011 API 调用太频繁，请稍候再试
+    ApiFrequencyLimited,
+    /// 45015 回复时间超过限制
+    ResponseOutOfTime,
+    /// 45047 客服接口下行条数超过上限
+    CustomerServiceMsgLimited,
+    /// 48001 api 功能未授权
+    ApiUnauthorized,
+    /// 50002 用户受限，可能是违规后接口被封禁
+    UserLimited,
+    /// 87014 内容含有违法违规内容
+    RiskyContent,
+    /// 其他返回码
+    Other(i32),
+}
+
+impl From<i32> for WxErrorCode {
+    fn from(code: i32) -> Self {
+        match code {
+            -1 => WxErrorCode::SystemBusy,
+            0 => WxErrorCode::Ok,
+            40001 => WxErrorCode::InvalidCredential,
+            40002 => WxErrorCode::InvalidGrantType,
+            40003 => WxErrorCode::InvalidOpenid,
+            40013 => WxErrorCode::InvalidAppid,
+            40014 => WxErrorCode::InvalidAccessToken,
+            40029 => WxErrorCode::InvalidCode,
+            40125 => WxErrorCode::InvalidAppSecret,
+            40163 => WxErrorCode::CodeBeenUsed,
+            40164 => WxErrorCode::InvalidIp,
+            41001 => WxErrorCode::AccessTokenMissing,
+            42001 => WxErrorCode::AccessTokenExpired,
+            43004 => WxErrorCode::RequireSubscribe,
+            45009 => WxErrorCode::ApiQuotaExceeded,
+            45011 => WxErrorCode::ApiFrequencyLimited,
+            45015 => WxErrorCode::ResponseOutOfTime,
+            45047 => WxErrorCode::CustomerServiceMsgLimited,
+            48001 => WxErrorCode::ApiUnauthorized,
+            50002 => WxErrorCode::UserLimited,
+            87014 => WxErrorCode::RiskyContent,
+            code => WxErrorCode::Other(code),
+        }
+    }
+}
+
+impl WxErrorCode {
+    /// The numeric errcode.
+    pub fn code(&self) -> i32 {
+        match self {
+            WxErrorCode::SystemBusy => -1,
+            WxErrorCode::Ok => 0,
+            WxErrorCode::InvalidCredential => 40001,
+            WxErrorCode::InvalidGrantType => 40002,
+            WxErrorCode::InvalidOpenid => 40003,
+            WxErrorCode::InvalidAppid => 40013,
+            WxErrorCode::InvalidAccessToken => 40014,
+            WxErrorCode::InvalidCode => 40029,
+            WxErrorCode::InvalidAppSecret => 40125,
+            WxErrorCode::CodeBeenUsed => 40163,
+            WxErrorCode::InvalidIp => 40164,
+            WxErrorCode::AccessTokenMissing => 41001,
+            WxErrorCode::AccessTokenExpired => 42001,
+            WxErrorCode::RequireSubscribe => 43004,
+            WxErrorCode::ApiQuotaExceeded => 45009,
+            WxErrorCode::ApiFrequencyLimited => 45011,
+            WxErrorCode::ResponseOutOfTime => 45015,
+            WxErrorCode::CustomerServiceMsgLimited => 45047,
+            WxErrorCode::ApiUnauthorized => 48001,
+            WxErrorCode::UserLimited => 50002,
+            WxErrorCode::RiskyContent => 87014,
+            WxErrorCode::Other(code) => *code,
+        }
+    }
+
+    /// The access token is invalid or expired, a new token is needed.
+    pub fn is_token_error(&self) -> bool {
+        matches!(
+            self,
+            WxErrorCode::InvalidCredential
+                | WxErrorCode::InvalidAccessToken
+                | WxErrorCode::AccessTokenExpired
+        )
+    }
+
+    /// The failure is transient, the same request may succeed later.
+    pub fn is_retryable(&self) -> bool {
+        matches!(self, WxErrorCode::SystemBusy)
+    }
+
+    /// The api quota or frequency limit is reached.
+    pub fn is_rate_limited(&self) -> bool {
+        matches!(
+            self,
+            WxErrorCode::ApiQuotaExceeded
+                | WxErrorCode::ApiFrequencyLimited
+                | WxErrorCode::CustomerServiceMsgLimited
+        )
+    }
+}
+
+/// WeChat Pay v3 api response a non-2xx status with a JSON body contains `code` and `message`, that is a struct for it.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Error)]
+#[error("Wechat Pay API response error: code {code}, message {message}")]
+pub struct PayError {
+    pub code: String,
+    pub message: String,
+    #[serde(default)]
+    pub detail: Option<serde_json::Value>,
+}
+
 /// Enum for the return result of http calling WeChat's api.
 #[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
 #[serde(untagged)]
@@ -58,6 +216,53 @@ pub enum SdkError {
 
     #[error("Exile write xml error")]
     XmlWriteError(#[from] exile::error::Error),
+
+    #[error(transparent)]
+    IoError(#[from] std::io::Error),
+
+    #[error("rate limited by the client: {0}")]
+    RateLimited(String),
+
+    #[error("token store error: {0}")]
+    TokenStoreError(String),
+
+    #[error("json parse error: {0}")]
+    JsonParseError(#[from] serde_json::Error),
+
+    #[error(transparent)]
+    PayApiError(#[from] PayError),
+
+    #[error("wechat pay sign error: {0}")]
+    PaySignError(String),
+}
+
+impl SdkError {
+    /// The errcode of the WeChat api error.
+    pub fn wx_error_code(&self) -> Option<WxErrorCode> {
+        match self {
+            SdkError::WxApiError(e) | SdkError::AccessTokenError(e) => Some(e.code()),
+            _ => None,
+        }
+    }
+
+    /// See [WxErrorCode::is_token_error].
+    pub fn is_token_error(&self) -> bool {
+        self.wx_error_code().is_some_and(|c| c.is_token_error())
+    }
+
+    /// See [WxErrorCode::is_retryable], the connection and timeout errors are retryable too.
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            SdkError::ReqwestError(e) => e.is_connect() || e.is_timeout(),
+            _ => self.wx_error_code().is_some_and(|c| c.is_retryable()),
+        }
+    }
+
+    /// See [WxErrorCode::is_rate_limited], the calls rejected by the client side rate limit too.
+    pub fn is_rate_limited(&self) -> bool {
+        matches!(self, SdkError::RateLimited(_))
+            || self.wx_error_code().is_some_and(|c| c.is_rate_limited())
+    }
 }
 
 /// A wrap of `std::result::Result<T, SdkError>`.
@@ -129,6 +334,44 @@ fn test_error_from() {
     assert!(into.is_err());
 }
 
+#[test]
+fn test_error_rid() {
+    let e = CommonError {
+        errcode: 40001,
+        errmsg: "invalid credential, access_token is invalid or not latest rid: 61725984-6126f6f9-040f19c4".to_string(),
+    };
+    assert_eq!(e.rid(), Some("61725984-6126f6f9-040f19c4"));
+    let e = CommonError {
+        errcode: 0,
+        errmsg: "ok".to_string(),
+    };
+    assert_eq!(e.rid(), None);
+}
+
+#[test]
+fn test_error_code() {
+    for code in [-1, 0, 40001, 40014, 42001, 45009, 45011, 87014, 12345] {
+        assert_eq!(WxErrorCode::from(code).code(), code);
+    }
+    assert_eq!(WxErrorCode::from(12345), WxErrorCode::Other(12345));
+
+    let e = SdkError::WxApiError(CommonError {
+        errcode: 42001,
+        errmsg: "access_token expired".to_string(),
+    });
+    assert_eq!(e.wx_error_code(), Some(WxErrorCode::AccessTokenExpired));
+    assert!(e.is_token_error());
+    assert!(!e.is_retryable());
+
+    let e = SdkError::WxApiError(CommonError {
+        errcode: 45009,
+        errmsg: "reach max api daily quota limit".to_string(),
+    });
+    assert!(e.is_rate_limited());
+    assert!(SdkError::RateLimited("/cgi-bin/user/info".to_string()).is_rate_limited());
+    assert!(!SdkError::InvalidSignature.is_rate_limited());
+}
+
 #[test]
 fn test_data_and_error() {
     #[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
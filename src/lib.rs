@@ -36,15 +36,25 @@ pub use access_token::AccessToken;
 pub mod error;
 pub use error::SdkResult;
 pub(crate) mod cache;
+pub mod openapi;
 pub(crate) mod utils;
 pub mod wechat;
 pub use access_token::TokenClient;
-pub use wechat::WxSdk;
+pub use wechat::{WxSdk, WxSdkBuilder};
 #[cfg(feature = "mp")]
 pub mod mp;
 
+#[cfg(feature = "mp")]
+pub mod registry;
+
 #[cfg(feature = "wxa")]
 pub mod wxa;
 
+#[cfg(feature = "open")]
+pub mod open;
+
 #[cfg(feature = "pay")]
 pub mod pay;
+
+#[cfg(any(test, feature = "testing"))]
+pub mod testing;
@@ -7,14 +7,10 @@ use std::collections::HashMap;
 use roxmltree::Document;
 
 use crate::{
-    access_token::AccessTokenProvider,
-    error::{CommonError, SdkError},
-    mp::event::signature::Signature,
-};
-use crate::{
-    wechat::{WxApiRequestBuilder, WxSdk},
-    SdkResult,
+    access_token::AccessTokenProvider, error::SdkError, mp::event::signature::Signature,
+    openapi::OpenApiModule,
 };
+use crate::{wechat::WxSdk, SdkResult};
 
 use self::{
     customservice::CustomServiceModule, datacube::DataCubeModule, draft::DraftModule,
@@ -34,6 +30,7 @@ pub mod menu;
 pub mod message;
 pub mod qrcode;
 pub mod reply;
+pub mod server;
 pub mod shorten;
 pub mod sns;
 pub mod tags;
@@ -76,24 +73,22 @@ pub struct MpSdk<T: AccessTokenProvider> {
 }
 
 impl<T: AccessTokenProvider> MpSdk<T> {
+    /// The app_id of the official account.
+    pub fn app_id(&self) -> &str {
+        &self.sdk.app_id
+    }
+
     /// 接口限额清零
     ///
     /// 公众号调用接口并不是无限制的。
     /// 每个帐号每月共10次清零操作机会，清零生效一次即用掉一次机会（10次包括了平台上的清零和调用接口API的清零）。
     pub async fn clear_quota(&self) -> SdkResult<()> {
-        let base_url = "https://api.weixin.qq.com/cgi-bin/clear_quota";
-        let sdk = &self.sdk;
-        let app_id = sdk.app_id.clone();
-        let res = sdk
-            .wx_post(base_url)
-            .await?
-            .json(&serde_json::json!({ "appid": app_id }))
-            .send()
-            .await?
-            .json::<CommonError>()
-            .await?;
+        self.openapi().clear_quota().await
+    }
 
-        res.into()
+    /// openApi module 接口额度及 rid 查询模块
+    pub fn openapi(&self) -> OpenApiModule<'_, T> {
+        OpenApiModule(&self.sdk)
     }
 
     /// Qrcode generator module 生成二维码模块
@@ -165,59 +160,105 @@ impl<T: AccessTokenProvider> MpSdk<T> {
         FreePublishModule(&self.sdk)
     }
 
+    /// 验证服务器地址，返回需要原样响应给微信服务器的 `echostr`。
+    ///
+    /// 校验 url 中的 `signature`；兼容模式和安全模式下如果带有 `msg_signature`，`echostr` 为密文，校验后解密返回。
+    pub fn verify_server_url(&self, url_params: &HashMap<String, String>) -> SdkResult<String> {
+        let server_config = &self.server_config;
+        let get_param = |key: &str| get_url_param(url_params, key).cloned();
+        let timestamp = get_param("timestamp")?;
+        let nonce = get_param("nonce")?;
+        let echostr = get_param("echostr")?;
+        let aes_key = match server_config.encoding_mode {
+            EncodingMode::Compat(ref aes_key) | EncodingMode::Security(ref aes_key)
+                if url_params.contains_key("msg_signature") =>
+            {
+                aes_key
+            }
+            _ => {
+                let check_sign = vec![server_config.token.clone(), timestamp, nonce];
+                if !Signature::new(get_param("signature")?, check_sign).is_ok() {
+                    return Err(SdkError::InvalidSignature);
+                }
+                return Ok(echostr);
+            }
+        };
+        self.decrypt_msg(&echostr, aes_key, url_params)
+    }
+
     /// 解析微信推送消息
+    ///
+    /// 兼容模式下校验 url 中的 `signature`，消息中带有 `Encrypt` 时校验 `msg_signature` 并以解密后的消息为准；
+    /// 安全模式下校验 `msg_signature` 并解密 `Encrypt`。
     pub fn parse_received_msg<S: AsRef<str>>(
         &self,
         msg: S,
         url_params: Option<HashMap<String, String>>,
     ) -> SdkResult<event::ReceivedEvent> {
         let server_config = &self.server_config;
-        let msg = match server_config.encoding_mode {
+        match server_config.encoding_mode {
             EncodingMode::Plain => event::ReceivedEvent::parse(msg.as_ref()),
-            EncodingMode::Compat(_) => event::ReceivedEvent::parse(msg.as_ref()),
-            EncodingMode::Security(ref aes_key) => {
+            EncodingMode::Compat(ref aes_key) => {
                 let url_params = url_params
                     .ok_or_else(|| SdkError::InvalidParams("needs url_params".to_owned()))?;
-                let signature = url_params
-                    .get("msg_signature")
-                    .ok_or_else(|| SdkError::InvalidParams("msg_signature".to_owned()))?;
-                let timestamp = url_params
-                    .get("timestamp")
-                    .ok_or_else(|| SdkError::InvalidParams("timestamp".to_owned()))?;
-                let nonce = url_params
-                    .get("nonce")
-                    .ok_or_else(|| SdkError::InvalidParams("nonce".to_owned()))?;
-                let token = server_config.token.clone();
-                let root = Document::parse(msg.as_ref())?;
-                let encrypt_msg = root
-                    .descendants()
-                    .find(|n| n.has_tag_name("Encrypt"))
-                    .map(|n| n.text())
-                    .flatten()
-                    .unwrap();
-
                 let check_sign = vec![
-                    token,
-                    timestamp.clone(),
-                    nonce.clone(),
-                    encrypt_msg.to_owned(),
+                    server_config.token.clone(),
+                    get_url_param(&url_params, "timestamp")?.clone(),
+                    get_url_param(&url_params, "nonce")?.clone(),
                 ];
-                let sign = event::signature::Signature::new(signature, check_sign);
-                if !sign.is_ok() {
+                let signature = get_url_param(&url_params, "signature")?;
+                if !Signature::new(signature, check_sign).is_ok() {
                     return Err(SdkError::InvalidSignature);
                 }
-                // decrpyted_text = [random(16) + content_len(4) + content + appid]
-                let (msg, app_id) = event::crypto::decrypt_message(encrypt_msg, aes_key)?;
-                if app_id != self.sdk.app_id {
-                    return Err(SdkError::InvalidAppid);
+                let root = Document::parse(msg.as_ref())?;
+                match get_encrypt_msg(&root) {
+                    Some(encrypt_msg) => {
+                        let msg = self.decrypt_msg(encrypt_msg, aes_key, &url_params)?;
+                        event::ReceivedEvent::parse(msg.as_ref())
+                    }
+                    None => event::ReceivedEvent::parse(msg.as_ref()),
                 }
+            }
+            EncodingMode::Security(ref aes_key) => {
+                let url_params = url_params
+                    .ok_or_else(|| SdkError::InvalidParams("needs url_params".to_owned()))?;
+                let root = Document::parse(msg.as_ref())?;
+                let encrypt_msg = get_encrypt_msg(&root)
+                    .ok_or_else(|| SdkError::InvalidParams("Encrypt".to_owned()))?;
+                let msg = self.decrypt_msg(encrypt_msg, aes_key, &url_params)?;
                 event::ReceivedEvent::parse(msg.as_ref())
             }
-        };
-        msg
+        }
+    }
+
+    /// 校验 `msg_signature` 并解密消息
+    fn decrypt_msg(
+        &self,
+        encrypt_msg: &str,
+        aes_key: &str,
+        url_params: &HashMap<String, String>,
+    ) -> SdkResult<String> {
+        let check_sign = vec![
+            self.server_config.token.clone(),
+            get_url_param(url_params, "timestamp")?.clone(),
+            get_url_param(url_params, "nonce")?.clone(),
+            encrypt_msg.to_owned(),
+        ];
+        let signature = get_url_param(url_params, "msg_signature")?;
+        if !Signature::new(signature, check_sign).is_ok() {
+            return Err(SdkError::InvalidSignature);
+        }
+        // decrpyted_text = [random(16) + content_len(4) + content + appid]
+        let (msg, app_id) = event::crypto::decrypt_message(encrypt_msg, aes_key)?;
+        if app_id != self.sdk.msg_app_id() {
+            return Err(SdkError::InvalidAppid);
+        }
+        Ok(msg)
     }
 
     /// 得到回复消息 XML
+    ///
+    /// 安全模式下只包含加密字段，兼容模式下同时包含明文字段和加密字段。
     pub fn reply_to_xml<S: Into<String>>(
         &self,
         reply: Reply,
@@ -226,37 +267,56 @@ impl<T: AccessTokenProvider> MpSdk<T> {
         url_params: Option<HashMap<String, String>>,
     ) -> SdkResult<String> {
         let server_config = &self.server_config;
-        let mut reply_xml = reply::reply_to_xml(reply, from, to)?;
-        if let EncodingMode::Security(ref aes_key) = server_config.encoding_mode {
-            let ref app_id = self.sdk.app_id;
-            let encrypt_msg = event::crypto::encrypt_message(&reply_xml, aes_key, app_id)?;
-            let url_params =
-                url_params.ok_or_else(|| SdkError::InvalidParams("needs url_params".to_owned()))?;
+        let reply_xml = reply::reply_to_xml(reply, from, to)?;
+        let (aes_key, is_compat) = match server_config.encoding_mode {
+            EncodingMode::Plain => return Ok(reply_xml),
+            EncodingMode::Compat(ref aes_key) => (aes_key, true),
+            EncodingMode::Security(ref aes_key) => (aes_key, false),
+        };
+        let app_id = &self.sdk.msg_app_id().to_owned();
+        let encrypt_msg = event::crypto::encrypt_message(&reply_xml, aes_key, app_id)?;
+        let url_params =
+            url_params.ok_or_else(|| SdkError::InvalidParams("needs url_params".to_owned()))?;
 
-            let timestamp = url_params
-                .get("timestamp")
-                .ok_or_else(|| SdkError::InvalidParams("timestamp".to_owned()))?;
-            let nonce = url_params
-                .get("nonce")
-                .ok_or_else(|| SdkError::InvalidParams("nonce".to_owned()))?;
-            let token = server_config.token.clone();
-            let check_sign = vec![
-                token,
-                timestamp.clone(),
-                nonce.clone(),
-                encrypt_msg.to_owned(),
-            ];
-            let msg_signaturet = Signature::generate_signature(check_sign);
-            reply_xml = format!(
-                "<xml>
-<Encrypt><![CDATA[{}]]></Encrypt>
+        let timestamp = get_url_param(&url_params, "timestamp")?;
+        let nonce = get_url_param(&url_params, "nonce")?;
+        let token = server_config.token.clone();
+        let check_sign = vec![
+            token,
+            timestamp.clone(),
+            nonce.clone(),
+            encrypt_msg.to_owned(),
+        ];
+        let msg_signaturet = Signature::generate_signature(check_sign);
+        let encrypt_xml = format!(
+            "<Encrypt><![CDATA[{}]]></Encrypt>
 <MsgSignature><![CDATA[{}]]></MsgSignature>
 <TimeStamp>{}</TimeStamp>
 <Nonce><![CDATA[{}]]></Nonce>
-</xml>",
-                encrypt_msg, msg_signaturet, timestamp, nonce
-            );
+",
+            encrypt_msg, msg_signaturet, timestamp, nonce
+        );
+        if is_compat {
+            let pos = reply_xml
+                .rfind("</xml>")
+                .ok_or_else(|| SdkError::InvalidParams("reply xml".to_owned()))?;
+            let mut compat_xml = reply_xml;
+            compat_xml.insert_str(pos, &encrypt_xml);
+            Ok(compat_xml)
+        } else {
+            Ok(format!("<xml>\n{}</xml>", encrypt_xml))
         }
-        Ok(reply_xml)
     }
 }
+
+fn get_url_param<'a>(url_params: &'a HashMap<String, String>, key: &str) -> SdkResult<&'a String> {
+    url_params
+        .get(key)
+        .ok_or_else(|| SdkError::InvalidParams(key.to_owned()))
+}
+
+fn get_encrypt_msg<'a>(root: &'a Document) -> Option<&'a str> {
+    root.descendants()
+        .find(|n| n.has_tag_name("Encrypt"))
+        .and_then(|n| n.text())
+}
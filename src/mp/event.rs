@@ -1,6 +1,8 @@
-use roxmltree::{Document, Node};
+use roxmltree::Node;
+use serde::{de, de::DeserializeOwned, ser, Deserialize, Deserializer, Serialize, Serializer};
+use serde_json::{Map, Value};
 
-use crate::{error::SdkError, SdkResult};
+use crate::{error::SdkError, utils::de::number_from_str, SdkResult};
 
 use msg_image::ImageMessage;
 use msg_text::TextMessage;
@@ -30,11 +32,69 @@ const MSG_LOCATION: &'static str = "location"; // 地理位置消息
 const MSG_LINK: &'static str = "link";
 const MSG_EVENT: &'static str = "event";
 
+/// 从 XML 解析消息
 pub trait ReceivedMessageParser {
     type ReceivedMessage;
     fn from_xml(node: &Node) -> SdkResult<Self::ReceivedMessage>;
 }
 
+/// 使用 serde 从 XML 节点解析实现了 [Deserialize] 的消息，不需要手写 [ReceivedMessageParser]
+pub fn from_xml_node<T: DeserializeOwned>(node: &Node) -> SdkResult<T> {
+    Ok(serde_json::from_value(xmlutil::node_to_json(node))?)
+}
+
+/// 为消息类型实现基于 serde 的 [ReceivedMessageParser]
+macro_rules! impl_received_message_parser {
+    ($($t:ty),* $(,)?) => {
+        $(
+            impl ReceivedMessageParser for $t {
+                type ReceivedMessage = $t;
+
+                fn from_xml(node: &Node) -> SdkResult<Self::ReceivedMessage> {
+                    from_xml_node(node)
+                }
+            }
+        )*
+    };
+}
+
+impl_received_message_parser!(
+    TextMessage,
+    ImageMessage,
+    VoiceMessage,
+    VideoMessage,
+    LocationMessage,
+    LinkMessage,
+    EventMessage,
+    msg_event::event_click::ClickEvent,
+    msg_event::event_guide::GuideInviteResultEvent,
+    msg_event::event_guide::GuideQrcodeScanEvent,
+    msg_event::event_location::LocationEvent,
+    msg_event::event_publishjob::PublishJobFinishEvent,
+    msg_event::event_scan::ScanEvent,
+    msg_event::event_scan::MenuScanEvent,
+    msg_event::event_send::SendPicsEvent,
+    msg_event::event_send::SendLocationEvent,
+    msg_event::event_sendjob::MassSendJobFinishEvent,
+    msg_event::event_sendjob::TemplateSendJobFinishEvent,
+    msg_event::event_view::ViewEvent,
+);
+
+/// 序列化为 JSON 对象，用于将消息头和消息体合并为与推送格式一致的 JSON
+pub(crate) fn to_json_object<T: Serialize>(value: &T) -> SdkResult<Map<String, Value>> {
+    match serde_json::to_value(value)? {
+        Value::Object(map) => Ok(map),
+        Value::Null => Ok(Map::new()),
+        _ => Err(SdkError::InvalidParams(
+            "message should be serialized as an object".to_owned(),
+        )),
+    }
+}
+
+/// 接收到的消息推送。
+///
+/// 序列化后的 JSON 与推送的 XML 字段一致，可以通过 [ReceivedEvent::from_json] 或 serde 重新解析。
+#[derive(Debug, Clone)]
 pub struct ReceivedEvent {
     pub from: String,
 
@@ -47,7 +107,9 @@ pub struct ReceivedEvent {
     pub body: ReceivedMessage,
 }
 
+#[derive(Debug, Clone)]
 pub enum ReceivedMessage {
+    /// 未处理的消息类型，保留转换为 JSON 后的消息原文
     UnhandledMessage(String),
     Text(TextMessage),
     Image(ImageMessage),
@@ -59,71 +121,157 @@ pub enum ReceivedMessage {
     Event(EventMessage),
 }
 
+impl ReceivedMessage {
+    fn to_json(&self) -> SdkResult<Map<String, Value>> {
+        match self {
+            ReceivedMessage::UnhandledMessage(json) => {
+                to_json_object(&serde_json::from_str::<Value>(json)?)
+            }
+            ReceivedMessage::Text(m) => to_json_object(m),
+            ReceivedMessage::Image(m) => to_json_object(m),
+            ReceivedMessage::Voice(m) => to_json_object(m),
+            ReceivedMessage::Video(m) | ReceivedMessage::ShortVideo(m) => to_json_object(m),
+            ReceivedMessage::Location(m) => to_json_object(m),
+            ReceivedMessage::Link(m) => to_json_object(m),
+            ReceivedMessage::Event(e) => to_json_object(&e.to_json()?),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct MessageHeader {
+    to_user_name: String,
+    from_user_name: String,
+    #[serde(deserialize_with = "number_from_str")]
+    create_time: u64,
+    msg_type: String,
+}
+
 impl ReceivedEvent {
+    /// 解析 XML 明文消息
     pub fn parse(input: &str) -> SdkResult<Self> {
-        let xml = Document::parse(input)?;
-        let root = xml.root();
-        let msg_type = root
-            .descendants()
-            .find(|n| n.has_tag_name("MsgType"))
-            .map(|n| n.text())
-            .flatten()
-            .ok_or_else(|| SdkError::InvalidParams("parse xml need `MsgType` params".to_owned()))?;
-        let from = root
-            .descendants()
-            .find(|n| n.has_tag_name("FromUserName"))
-            .map(|n| n.text())
-            .flatten()
-            .ok_or_else(|| {
-                SdkError::InvalidParams("parse xml need `FromUserName` params".to_owned())
-            })?;
-        let to = root
-            .descendants()
-            .find(|n| n.has_tag_name("ToUserName"))
-            .map(|n| n.text())
-            .flatten()
-            .ok_or_else(|| {
-                SdkError::InvalidParams("parse xml need `ToUserName` params".to_owned())
-            })?;
-        let create_time = root
-            .descendants()
-            .find(|n| n.has_tag_name("CreateTime"))
-            .map(|n| n.text())
-            .flatten()
-            .ok_or_else(|| {
-                SdkError::InvalidParams("parse xml need CreateTime params".to_owned())
-            })?;
-        let create_time = create_time.parse::<u64>().map_err(|_e| {
-            SdkError::InvalidParams(
-                "Parse XML msg from wechat error: tag `CreateTime` should be number".to_string(),
-            )
-        })?;
-
-        let body = match msg_type {
-            MSG_TEXT => ReceivedMessage::Text(TextMessage::from_xml(&root)?),
-            MSG_IMAGE => ReceivedMessage::Image(ImageMessage::from_xml(&root)?),
-            MSG_VOICE => ReceivedMessage::Voice(VoiceMessage::from_xml(&root)?),
-            MSG_VIDEO | MSG_SHORTVIDEO => {
-                if msg_type == MSG_VIDEO {
-                    ReceivedMessage::Video(VideoMessage::from_xml(&root)?)
-                } else {
-                    ReceivedMessage::ShortVideo(VideoMessage::from_xml(&root)?)
-                }
-            }
-            MSG_LOCATION => ReceivedMessage::Location(LocationMessage::from_xml(&root)?),
-            MSG_LINK => ReceivedMessage::Link(LinkMessage::from_xml(&root)?),
-            MSG_EVENT => ReceivedMessage::Event(EventMessage::from_xml(&root)?),
-            _ => ReceivedMessage::UnhandledMessage(format!(
-                "Havent' handle for this message type `{}`",
-                msg_type
-            )),
+        Self::from_json(xmlutil::xml_to_json(input)?)
+    }
+
+    /// 从 JSON 解析消息，字段与推送的 XML 一致
+    pub fn from_json(value: Value) -> SdkResult<Self> {
+        let header = MessageHeader::deserialize(&value)?;
+        let body = match header.msg_type.as_str() {
+            MSG_TEXT => ReceivedMessage::Text(serde_json::from_value(value)?),
+            MSG_IMAGE => ReceivedMessage::Image(serde_json::from_value(value)?),
+            MSG_VOICE => ReceivedMessage::Voice(serde_json::from_value(value)?),
+            MSG_VIDEO => ReceivedMessage::Video(serde_json::from_value(value)?),
+            MSG_SHORTVIDEO => ReceivedMessage::ShortVideo(serde_json::from_value(value)?),
+            MSG_LOCATION => ReceivedMessage::Location(serde_json::from_value(value)?),
+            MSG_LINK => ReceivedMessage::Link(serde_json::from_value(value)?),
+            MSG_EVENT => ReceivedMessage::Event(EventMessage::from_json(value)?),
+            _ => ReceivedMessage::UnhandledMessage(value.to_string()),
         };
         Ok(ReceivedEvent {
-            from: from.to_owned(),
-            to: to.to_owned(),
-            msg_type: msg_type.to_owned(),
-            create_time,
+            from: header.from_user_name,
+            to: header.to_user_name,
+            create_time: header.create_time,
+            msg_type: header.msg_type,
             body,
         })
     }
+
+    /// 转换为与推送的 XML 字段一致的 JSON
+    pub fn to_json(&self) -> SdkResult<Value> {
+        let mut map = self.body.to_json()?;
+        map.insert("ToUserName".to_owned(), Value::String(self.to.clone()));
+        map.insert("FromUserName".to_owned(), Value::String(self.from.clone()));
+        map.insert("CreateTime".to_owned(), Value::from(self.create_time));
+        map.insert("MsgType".to_owned(), Value::String(self.msg_type.clone()));
+        Ok(Value::Object(map))
+    }
+}
+
+impl Serialize for ReceivedEvent {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.to_json()
+            .map_err(ser::Error::custom)?
+            .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for ReceivedEvent {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let value = Value::deserialize(deserializer)?;
+        ReceivedEvent::from_json(value).map_err(de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_serde_round_trip() -> SdkResult<()> {
+        let s = "<xml>
+    <ToUserName><![CDATA[gh_e136c6e50636]]></ToUserName>
+    <FromUserName><![CDATA[oMgHVjngRipVsoxg6TuX3vz6glDg]]></FromUserName>
+    <CreateTime>1408090651</CreateTime>
+    <MsgType><![CDATA[event]]></MsgType>
+    <Event><![CDATA[pic_sysphoto]]></Event>
+    <EventKey><![CDATA[6]]></EventKey>
+    <SendPicsInfo><Count>1</Count>
+    <PicList><item><PicMd5Sum><![CDATA[1b5f7c23b5bf75682a53e7b6d163e185]]></PicMd5Sum>
+    </item>
+    </PicList>
+    </SendPicsInfo>
+  </xml>";
+        let event = ReceivedEvent::parse(s)?;
+        let json = serde_json::to_string(&event)?;
+        let replayed: ReceivedEvent = serde_json::from_str(&json)?;
+        assert_eq!(replayed.from, "oMgHVjngRipVsoxg6TuX3vz6glDg");
+        assert_eq!(replayed.create_time, 1408090651);
+        match &replayed.body {
+            ReceivedMessage::Event(EventMessage::PicSysPhoto(e)) => {
+                assert_eq!(e.event_key, "6");
+                assert_eq!(e.pic_md5_sum_list.len(), 1);
+            }
+            _ => panic!("should be a pic_sysphoto event"),
+        }
+        assert_eq!(
+            serde_json::to_value(&replayed)?,
+            serde_json::from_str::<Value>(&json)?
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_serde_unhandled() -> SdkResult<()> {
+        let json = serde_json::json!({
+            "ToUserName": "toUser",
+            "FromUserName": "fromUser",
+            "CreateTime": 1348831860,
+            "MsgType": "event",
+            "Event": "some_new_event",
+            "Foo": "bar"
+        });
+        let event: ReceivedEvent = serde_json::from_value(json.clone())?;
+        match &event.body {
+            ReceivedMessage::Event(EventMessage::UnhandledEvent(raw)) => {
+                assert_eq!(
+                    serde_json::from_str::<Value>(raw)?["Event"],
+                    "some_new_event"
+                )
+            }
+            _ => panic!("should be an unhandled event"),
+        }
+        assert_eq!(serde_json::to_value(&event)?, json);
+
+        let event = ReceivedEvent::parse(
+            "<xml>
+    <ToUserName><![CDATA[toUser]]></ToUserName>
+    <FromUserName><![CDATA[fromUser]]></FromUserName>
+    <CreateTime>1348831860</CreateTime>
+    <MsgType><![CDATA[subscribe]]></MsgType>
+  </xml>",
+        )?;
+        assert!(matches!(event.body, ReceivedMessage::UnhandledMessage(_)));
+        Ok(())
+    }
 }
@@ -1,4 +1,4 @@
-use aes::Aes256;
+use aes::{Aes128, Aes256};
 // use base64ct::{Base64, Encoding};
 use crate::utils::{base64, get_random_string};
 use block_modes::block_padding::Pkcs7;
@@ -8,39 +8,71 @@ use crate::error::SdkError;
 use crate::SdkResult;
 
 // create an alias for convenience
+type Aes128Cbc = Cbc<Aes128, Pkcs7>;
 type Aes256Cbc = Cbc<Aes256, Pkcs7>;
 
+/// AES-CBC 解密 base64 编码的密文，PKCS#7 填充，按 `key` 的长度使用 AES-128 或 AES-256
+pub fn aes_cbc_decrypt(key: &[u8], iv: &[u8], ciphertext: &str) -> SdkResult<Vec<u8>> {
+    let err = SdkError::MsgDecryptError;
+    let encrypt_buf = base64::decode(ciphertext).map_err(|e| err(e.to_string()))?;
+    match key.len() {
+        16 => Aes128Cbc::new_from_slices(key, iv)
+            .map_err(|e| err(e.to_string()))?
+            .decrypt_vec(&encrypt_buf),
+        32 => Aes256Cbc::new_from_slices(key, iv)
+            .map_err(|e| err(e.to_string()))?
+            .decrypt_vec(&encrypt_buf),
+        _ => return Err(err("invalid aes key".to_owned())),
+    }
+    .map_err(|e| err(e.to_string()))
+}
+
+/// AES-CBC 加密，PKCS#7 填充，返回 base64 编码的密文，按 `key` 的长度使用 AES-128 或 AES-256
+pub fn aes_cbc_encrypt(key: &[u8], iv: &[u8], plaintext: &[u8]) -> SdkResult<String> {
+    let err = SdkError::MsgEncryptError;
+    let ciphertext = match key.len() {
+        16 => Aes128Cbc::new_from_slices(key, iv)
+            .map_err(|e| err(e.to_string()))?
+            .encrypt_vec(plaintext),
+        32 => Aes256Cbc::new_from_slices(key, iv)
+            .map_err(|e| err(e.to_string()))?
+            .encrypt_vec(plaintext),
+        _ => return Err(err("invalid aes key".to_owned())),
+    };
+    Ok(base64::encode(ciphertext.as_slice()))
+}
+
 /// 解密消息，返回(decrypted_msg, app_id)
 pub fn decrypt_message<'a, S: AsRef<str>>(
     ciphertext: &'a str,
     key: S,
 ) -> SdkResult<(String, String)> {
-    // let mut key_buf = [0u8; 32];
-    let mut iv_buf = [0u8; 16];
     // first: base64 decode the key
     let key = base64::decode(format!("{}=", key.as_ref()))
         .map_err(|e| SdkError::MsgDecryptError(e.to_string()))?;
-    iv_buf.copy_from_slice(&key[0..16]);
-    // new the cipher
-    let cipher = Aes256Cbc::new_from_slices(&key[0..32], &iv_buf)
-        .map_err(|e| SdkError::MsgDecryptError(e.to_string()))?;
-
-    // second: base65 decode the raw message
-    let mut encrypt_buf =
-        base64::decode(ciphertext).map_err(|e| SdkError::MsgDecryptError(e.to_string()))?;
-
-    // last: decrypt the message
-    let decrypted_ciphertext = cipher
-        .decrypt_vec(&mut encrypt_buf)
-        .map_err(|e| SdkError::MsgDecryptError(e.to_string()))?;
+    if key.len() < 32 {
+        return Err(SdkError::MsgDecryptError("invalid aes key".to_owned()));
+    }
+    // then: decrypt the message, the iv is the first 16 bytes of the key
+    let decrypted_ciphertext = aes_cbc_decrypt(&key[0..32], &key[0..16], ciphertext)?;
 
     // decrpyted_text = [random(16) + content_len(4) + content + appid]
+    if decrypted_ciphertext.len() < 20 {
+        return Err(SdkError::MsgDecryptError(
+            "invalid message length".to_owned(),
+        ));
+    }
     let (_, text) = decrypted_ciphertext.split_at(16);
     let (xlen, text) = text.split_at(4);
     let mut len = [0; 4];
     len.copy_from_slice(&xlen[..]);
-    let len = u32::from_be_bytes(len);
-    let (text, appid) = text.split_at(len as usize);
+    let len = u32::from_be_bytes(len) as usize;
+    if len > text.len() {
+        return Err(SdkError::MsgDecryptError(
+            "invalid message length".to_owned(),
+        ));
+    }
+    let (text, appid) = text.split_at(len);
 
     Ok((
         String::from_utf8_lossy(text).to_string(),
@@ -54,15 +86,12 @@ pub fn encrypt_message<'a, S: AsRef<str>>(
     app_id: S,
 ) -> SdkResult<String> {
     let plaintext = plaintext.as_bytes();
-    // let mut key_buf = [0u8; 32];
-    let mut iv_buf = [0u8; 16];
     // first: base64 decode the key
     let key = base64::decode(format!("{}=", key.as_ref()))
         .map_err(|e| SdkError::MsgEncryptError(e.to_string()))?;
-    iv_buf.copy_from_slice(&key[0..16]);
-    // new the cipher
-    let cipher = Aes256Cbc::new_from_slices(&key[0..32], &iv_buf)
-        .map_err(|e| SdkError::MsgEncryptError(e.to_string()))?;
+    if key.len() < 32 {
+        return Err(SdkError::MsgEncryptError("invalid aes key".to_owned()));
+    }
 
     // encrpyted_text = [random(16) + content_len(4) + content + appid]
     let random_text = get_random_string(16);
@@ -76,10 +105,6 @@ pub fn encrypt_message<'a, S: AsRef<str>>(
     let app_id = app_id.as_ref().as_bytes();
     buf.extend_from_slice(app_id);
 
-    // encrypt the message
-    let ciphertext = cipher.encrypt_vec(buf.as_slice());
-
-    // last: base65 encode the raw message
-    let ciphertext = base64::encode(ciphertext.as_slice());
-    Ok(ciphertext)
+    // last: encrypt the message, the iv is the first 16 bytes of the key
+    aes_cbc_encrypt(&key[0..32], &key[0..16], buf.as_slice())
 }
@@ -1,27 +1,15 @@
-use crate::{
-    mp::event::{xmlutil::get_text_from_root, ReceivedMessageParser},
-    SdkResult,
-};
+use serde::{Deserialize, Serialize};
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
 pub struct ClickEvent {
     pub event_key: String,
 }
 
-impl ReceivedMessageParser for ClickEvent {
-    type ReceivedMessage = ClickEvent;
-
-    fn from_xml(node: &roxmltree::Node) -> SdkResult<Self::ReceivedMessage> {
-        let event_key = get_text_from_root(node, "EventKey")?;
-        Ok(ClickEvent {
-            event_key: event_key.to_string(),
-        })
-    }
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::SdkResult;
+    use crate::{mp::event::ReceivedMessageParser, SdkResult};
     #[test]
     pub fn parse() -> SdkResult<()> {
         use roxmltree::Document;
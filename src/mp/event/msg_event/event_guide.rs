@@ -1,51 +1,67 @@
+use serde::{Deserialize, Serialize};
+
 use crate::{
     error::SdkError,
-    mp::event::{
-        xmlutil::{get_number_from_root, get_text_from_root},
-        ReceivedMessageParser,
-    },
-    SdkResult,
+    utils::de::{empty_string_as_none, number_from_str},
 };
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(
+    try_from = "RawGuideInviteResultEvent",
+    into = "RawGuideInviteResultEvent"
+)]
 pub struct GuideInviteResultEvent {
     pub guide_account: Option<String>,
     pub guide_openid: Option<String>,
     pub invite_result: i32,
 }
 
-impl ReceivedMessageParser for GuideInviteResultEvent {
-    type ReceivedMessage = GuideInviteResultEvent;
+#[derive(Serialize, Deserialize)]
+struct GuideInviteEvent {
+    #[serde(default, deserialize_with = "empty_string_as_none")]
+    guide_account: Option<String>,
+    #[serde(default, deserialize_with = "empty_string_as_none")]
+    guide_openid: Option<String>,
+    #[serde(deserialize_with = "number_from_str")]
+    invite_result: i32,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct RawGuideInviteResultEvent {
+    guide_invite_event: GuideInviteEvent,
+}
+
+impl TryFrom<RawGuideInviteResultEvent> for GuideInviteResultEvent {
+    type Error = SdkError;
 
-    fn from_xml(node: &roxmltree::Node) -> SdkResult<Self::ReceivedMessage> {
-        let guide_invite_event = node
-            .descendants()
-            .find(|n| n.has_tag_name("GuideInviteEvent"))
-            .ok_or_else(|| {
-                SdkError::InvalidParams(format!(
-                    "Parse XML msg from wechat error: tag `{}` is none",
-                    "GuideInviteEvent"
-                ))
-            })?;
-        let guide_account = get_text_from_root(&guide_invite_event, "guide_account")
-            .ok()
-            .filter(|s| !s.trim().is_empty())
-            .map(|s| s.to_string());
-        let guide_openid = get_text_from_root(&guide_invite_event, "guide_openid")
-            .ok()
-            .filter(|s| !s.trim().is_empty())
-            .map(|s| s.to_string());
-        if guide_account.is_none() && guide_openid.is_none() {
-            return Err(SdkError::InvalidParams(format!("parse GuideInviteEvent error: guide_account and guide_openid should exist at least one of them.")));
+    fn try_from(raw: RawGuideInviteResultEvent) -> Result<Self, Self::Error> {
+        let event = raw.guide_invite_event;
+        if event.guide_account.is_none() && event.guide_openid.is_none() {
+            return Err(SdkError::InvalidParams("parse GuideInviteEvent error: guide_account and guide_openid should exist at least one of them.".to_owned()));
         }
-        let invite_result = get_number_from_root::<i32>(&guide_invite_event, "invite_result")?;
         Ok(GuideInviteResultEvent {
-            guide_account,
-            guide_openid,
-            invite_result,
+            guide_account: event.guide_account,
+            guide_openid: event.guide_openid,
+            invite_result: event.invite_result,
         })
     }
 }
 
+impl From<GuideInviteResultEvent> for RawGuideInviteResultEvent {
+    fn from(event: GuideInviteResultEvent) -> Self {
+        RawGuideInviteResultEvent {
+            guide_invite_event: GuideInviteEvent {
+                guide_account: event.guide_account,
+                guide_openid: event.guide_openid,
+                invite_result: event.invite_result,
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(try_from = "RawGuideQrcodeScanEvent", into = "RawGuideQrcodeScanEvent")]
 pub struct GuideQrcodeScanEvent {
     pub qrcode_guide_account: Option<String>,
     pub qrcode_guide_openid: Option<String>,
@@ -54,49 +70,60 @@ pub struct GuideQrcodeScanEvent {
     pub qrcode_info: String,
 }
 
-impl ReceivedMessageParser for GuideQrcodeScanEvent {
-    type ReceivedMessage = GuideQrcodeScanEvent;
+#[derive(Serialize, Deserialize)]
+struct GuideScanEvent {
+    #[serde(default, deserialize_with = "empty_string_as_none")]
+    qrcode_guide_account: Option<String>,
+    #[serde(default, deserialize_with = "empty_string_as_none")]
+    qrcode_guide_openid: Option<String>,
+    openid: String,
+    #[serde(deserialize_with = "number_from_str")]
+    action: u8,
+    qrcode_info: String,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct RawGuideQrcodeScanEvent {
+    guide_scan_event: GuideScanEvent,
+}
 
-    fn from_xml(node: &roxmltree::Node) -> SdkResult<Self::ReceivedMessage> {
-        let guide_scan_event = node
-            .descendants()
-            .find(|n| n.has_tag_name("GuideScanEvent"))
-            .ok_or_else(|| {
-                SdkError::InvalidParams(format!(
-                    "Parse XML msg from wechat error: tag `{}` is none",
-                    "GuideScanEvent"
-                ))
-            })?;
+impl TryFrom<RawGuideQrcodeScanEvent> for GuideQrcodeScanEvent {
+    type Error = SdkError;
 
-        let qrcode_guide_account = get_text_from_root(&guide_scan_event, "qrcode_guide_account")
-            .ok()
-            .filter(|s| !s.trim().is_empty())
-            .map(|s| s.to_string());
-        let qrcode_guide_openid = get_text_from_root(&guide_scan_event, "qrcode_guide_openid")
-            .ok()
-            .filter(|s| !s.trim().is_empty())
-            .map(|s| s.to_string());
-        if qrcode_guide_account.is_none() && qrcode_guide_openid.is_none() {
-            return Err(SdkError::InvalidParams(format!("parse GuideScanEvent error: qrcode_guide_account and qrcode_guide_openid should exist at least one of them.")));
+    fn try_from(raw: RawGuideQrcodeScanEvent) -> Result<Self, Self::Error> {
+        let event = raw.guide_scan_event;
+        if event.qrcode_guide_account.is_none() && event.qrcode_guide_openid.is_none() {
+            return Err(SdkError::InvalidParams("parse GuideScanEvent error: qrcode_guide_account and qrcode_guide_openid should exist at least one of them.".to_owned()));
         }
-
-        let action = get_number_from_root::<u8>(&guide_scan_event, "action")?;
-        let openid = get_text_from_root(&guide_scan_event, "openid")?;
-        let qrcode_info = get_text_from_root(&guide_scan_event, "qrcode_info")?;
         Ok(GuideQrcodeScanEvent {
-            qrcode_guide_account,
-            qrcode_guide_openid,
-            action,
-            openid: openid.to_string(),
-            qrcode_info: qrcode_info.to_string(),
+            qrcode_guide_account: event.qrcode_guide_account,
+            qrcode_guide_openid: event.qrcode_guide_openid,
+            openid: event.openid,
+            action: event.action,
+            qrcode_info: event.qrcode_info,
         })
     }
 }
 
+impl From<GuideQrcodeScanEvent> for RawGuideQrcodeScanEvent {
+    fn from(event: GuideQrcodeScanEvent) -> Self {
+        RawGuideQrcodeScanEvent {
+            guide_scan_event: GuideScanEvent {
+                qrcode_guide_account: event.qrcode_guide_account,
+                qrcode_guide_openid: event.qrcode_guide_openid,
+                openid: event.openid,
+                action: event.action,
+                qrcode_info: event.qrcode_info,
+            },
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::SdkResult;
+    use crate::{mp::event::ReceivedMessageParser, SdkResult};
     #[test]
     pub fn parse() -> SdkResult<()> {
         use roxmltree::Document;
@@ -1,33 +1,22 @@
-use crate::{
-    mp::event::{xmlutil::get_number_from_root, ReceivedMessageParser},
-    SdkResult,
-};
+use serde::{Deserialize, Serialize};
 
+use crate::utils::de::number_from_str;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
 pub struct LocationEvent {
+    #[serde(deserialize_with = "number_from_str")]
     pub latitude: f32,
+    #[serde(deserialize_with = "number_from_str")]
     pub longitude: f32,
+    #[serde(deserialize_with = "number_from_str")]
     pub precision: f32,
 }
 
-impl ReceivedMessageParser for LocationEvent {
-    type ReceivedMessage = LocationEvent;
-
-    fn from_xml(node: &roxmltree::Node) -> SdkResult<Self::ReceivedMessage> {
-        let latitude = get_number_from_root::<f32>(node, "Latitude")?;
-        let longitude = get_number_from_root::<f32>(node, "Longitude")?;
-        let precision = get_number_from_root::<f32>(node, "Precision")?;
-        Ok(LocationEvent {
-            latitude,
-            longitude,
-            precision,
-        })
-    }
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::SdkResult;
+    use crate::{mp::event::ReceivedMessageParser, SdkResult};
     #[test]
     pub fn parse() -> SdkResult<()> {
         use roxmltree::Document;
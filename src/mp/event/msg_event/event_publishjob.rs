@@ -1,16 +1,16 @@
+use serde::{Deserialize, Serialize};
+
 use crate::{
-    mp::{
-        event::{
-            xmlutil::{get_node_by_tag, get_number_from_root, get_text_from_root},
-            ReceivedMessageParser,
-        },
-        freepublish::{ArticleDetail, ArticleDetailItem},
-    },
-    SdkResult,
+    error::SdkError,
+    mp::freepublish::ArticleDetail,
+    utils::de::{number_from_str, numbers_from_str},
 };
-use roxmltree::Node;
-use serde::{Deserialize, Serialize};
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(
+    try_from = "RawPublishJobFinishEvent",
+    into = "RawPublishJobFinishEvent"
+)]
 pub enum PublishJobFinishEvent {
     Success {
         publish_id: String,
@@ -25,70 +25,92 @@ pub enum PublishJobFinishEvent {
     },
 }
 
-impl ReceivedMessageParser for PublishJobFinishEvent {
-    type ReceivedMessage = PublishJobFinishEvent;
+#[derive(Serialize, Deserialize)]
+struct PublishEventInfo {
+    publish_id: String,
+    #[serde(deserialize_with = "number_from_str")]
+    publish_status: i8,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    article_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    article_detail: Option<ArticleDetail>,
+    #[serde(
+        default,
+        deserialize_with = "numbers_from_str",
+        skip_serializing_if = "Vec::is_empty"
+    )]
+    fail_idx: Vec<i8>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct RawPublishJobFinishEvent {
+    publish_event_info: PublishEventInfo,
+}
 
-    fn from_xml(node: &roxmltree::Node) -> crate::SdkResult<Self::ReceivedMessage> {
-        let publish_event_info = get_node_by_tag(node, "PublishEventInfo")?;
-        let publish_id = get_text_from_root(&publish_event_info, "publish_id")?.to_string();
-        let publish_status = get_number_from_root::<i8>(&publish_event_info, "publish_status")?;
-        if publish_status == 0 {
-            let article_id = get_text_from_root(&publish_event_info, "article_id")?.to_string();
-            let article_detail = get_node_by_tag(&publish_event_info, "article_detail")?;
-            let count = get_number_from_root::<i8>(&article_detail, "count")?;
-            let article_list: Vec<Node> = article_detail
-                .descendants()
-                .filter(|n| n.has_tag_name("item"))
-                .collect();
-            let article_list: Vec<ArticleDetailItem> = article_list
-                .iter()
-                .map(|n| {
-                    let idx = n
-                        .first_child()
-                        .unwrap()
-                        .text()
-                        .unwrap()
-                        .parse::<i8>()
-                        .unwrap();
-                    let article_url = n
-                        .descendants()
-                        .find(|n| n.has_tag_name("article_url"))
-                        .unwrap()
-                        .text()
-                        .unwrap()
-                        .to_string();
-                    ArticleDetailItem { idx, article_url }
-                })
-                .collect();
-            let article_detail = ArticleDetail {
-                count,
-                item: article_list,
-            };
+impl TryFrom<RawPublishJobFinishEvent> for PublishJobFinishEvent {
+    type Error = SdkError;
+
+    fn try_from(raw: RawPublishJobFinishEvent) -> Result<Self, Self::Error> {
+        let info = raw.publish_event_info;
+        if info.publish_status == 0 {
+            let article_id = info
+                .article_id
+                .ok_or_else(|| SdkError::InvalidParams("article_id".to_owned()))?;
+            let article_detail = info
+                .article_detail
+                .ok_or_else(|| SdkError::InvalidParams("article_detail".to_owned()))?;
             Ok(PublishJobFinishEvent::Success {
-                publish_id,
-                publish_status,
+                publish_id: info.publish_id,
+                publish_status: info.publish_status,
                 article_id,
                 article_detail,
             })
         } else {
-            let fail_id: Vec<i8> = publish_event_info
-                .descendants()
-                .filter(|n| n.has_tag_name("fail_idx"))
-                .map(|n| n.text().unwrap().parse::<i8>().unwrap())
-                .collect();
             Ok(PublishJobFinishEvent::Fail {
+                publish_id: info.publish_id,
+                publish_status: info.publish_status,
+                fail_id: info.fail_idx,
+            })
+        }
+    }
+}
+
+impl From<PublishJobFinishEvent> for RawPublishJobFinishEvent {
+    fn from(event: PublishJobFinishEvent) -> Self {
+        let publish_event_info = match event {
+            PublishJobFinishEvent::Success {
+                publish_id,
+                publish_status,
+                article_id,
+                article_detail,
+            } => PublishEventInfo {
+                publish_id,
+                publish_status,
+                article_id: Some(article_id),
+                article_detail: Some(article_detail),
+                fail_idx: vec![],
+            },
+            PublishJobFinishEvent::Fail {
                 publish_id,
                 publish_status,
                 fail_id,
-            })
-        }
+            } => PublishEventInfo {
+                publish_id,
+                publish_status,
+                article_id: None,
+                article_detail: None,
+                fail_idx: fail_id,
+            },
+        };
+        RawPublishJobFinishEvent { publish_event_info }
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::SdkResult;
+    use crate::{mp::event::ReceivedMessageParser, SdkResult};
     #[test]
     pub fn parse() -> SdkResult<()> {
         use crate::mp::event::EventMessage;
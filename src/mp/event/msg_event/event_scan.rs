@@ -1,62 +1,60 @@
-use crate::{
-    error::SdkError,
-    mp::event::{xmlutil::get_text_from_root, ReceivedMessageParser},
-    SdkResult,
-};
+use serde::{Deserialize, Serialize};
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
 pub struct ScanEvent {
     pub event_key: String,
     pub ticket: String,
 }
 
-impl ReceivedMessageParser for ScanEvent {
-    type ReceivedMessage = ScanEvent;
-
-    fn from_xml(node: &roxmltree::Node) -> SdkResult<Self::ReceivedMessage> {
-        let event_key = get_text_from_root(node, "EventKey")?;
-        let ticket = get_text_from_root(node, "Ticket")?;
-        Ok(ScanEvent {
-            event_key: event_key.to_string(),
-            ticket: ticket.to_string(),
-        })
-    }
-}
-
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(from = "RawMenuScanEvent", into = "RawMenuScanEvent")]
 pub struct MenuScanEvent {
     pub event_key: String,
     pub scan_type: String,
     pub scan_result: String,
 }
 
-impl ReceivedMessageParser for MenuScanEvent {
-    type ReceivedMessage = MenuScanEvent;
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct ScanCodeInfo {
+    scan_type: String,
+    scan_result: String,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct RawMenuScanEvent {
+    event_key: String,
+    scan_code_info: ScanCodeInfo,
+}
+
+impl From<RawMenuScanEvent> for MenuScanEvent {
+    fn from(raw: RawMenuScanEvent) -> Self {
+        MenuScanEvent {
+            event_key: raw.event_key,
+            scan_type: raw.scan_code_info.scan_type,
+            scan_result: raw.scan_code_info.scan_result,
+        }
+    }
+}
 
-    fn from_xml(node: &roxmltree::Node) -> SdkResult<Self::ReceivedMessage> {
-        let event_key = get_text_from_root(node, "EventKey")?;
-        let scan_code_info = node
-            .descendants()
-            .find(|n| n.has_tag_name("ScanCodeInfo"))
-            .ok_or_else(|| {
-                SdkError::InvalidParams(format!(
-                    "Parse XML msg from wechat error: tag `{}` is none",
-                    "ScanCodeInfo"
-                ))
-            })?;
-        let scan_type = get_text_from_root(&scan_code_info, "ScanType")?;
-        let scan_result = get_text_from_root(&scan_code_info, "ScanResult")?;
-        let event = MenuScanEvent {
-            event_key: event_key.to_owned(),
-            scan_type: scan_type.to_owned(),
-            scan_result: scan_result.to_owned(),
-        };
-        Ok(event)
+impl From<MenuScanEvent> for RawMenuScanEvent {
+    fn from(event: MenuScanEvent) -> Self {
+        RawMenuScanEvent {
+            event_key: event.event_key,
+            scan_code_info: ScanCodeInfo {
+                scan_type: event.scan_type,
+                scan_result: event.scan_result,
+            },
+        }
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::SdkResult;
+    use crate::{mp::event::ReceivedMessageParser, SdkResult};
 
     #[test]
     pub fn parse() -> SdkResult<()> {
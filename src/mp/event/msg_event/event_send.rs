@@ -1,58 +1,79 @@
-use crate::{
-    error::SdkError,
-    mp::event::{
-        xmlutil::{get_number_from_root, get_text_from_root},
-        ReceivedMessageParser,
-    },
-    SdkResult,
-};
+use serde::{Deserialize, Serialize};
 
+use crate::utils::de::{empty_as_default, empty_string_as_none, number_from_str, one_or_many};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(from = "RawSendPicsEvent", into = "RawSendPicsEvent")]
 pub struct SendPicsEvent {
     pub event_key: String,
     pub count: u16,
     pub pic_md5_sum_list: Vec<String>,
 }
 
-impl ReceivedMessageParser for SendPicsEvent {
-    type ReceivedMessage = SendPicsEvent;
-
-    fn from_xml(node: &roxmltree::Node) -> SdkResult<Self::ReceivedMessage> {
-        let event_key = get_text_from_root(node, "EventKey")?;
-        let send_pics_info = node
-            .descendants()
-            .find(|n| n.has_tag_name("SendPicsInfo"))
-            .ok_or_else(|| {
-                SdkError::InvalidParams(format!(
-                    "Parse XML msg from wechat error: tag `{}` is none",
-                    "SendPicsInfo"
-                ))
-            })?;
-        let count = get_number_from_root::<u16>(&send_pics_info, "Count")?;
-
-        let pic_list = send_pics_info
-            .children()
-            .find(|n| n.has_tag_name("PicList"))
-            .ok_or_else(|| {
-                SdkError::InvalidParams(format!(
-                    "Parse XML msg from wechat error: tag `{}` is none",
-                    "PicList"
-                ))
-            })?;
-        let md5_vec: Vec<String> = pic_list
-            .descendants()
-            .filter(|n| n.has_tag_name("PicMd5Sum"))
-            .filter_map(|n| n.text())
-            .map(|s| s.to_owned())
-            .collect();
-        let event = SendPicsEvent {
-            event_key: event_key.to_owned(),
-            count,
-            pic_md5_sum_list: md5_vec,
-        };
-        Ok(event)
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct PicMd5Sum {
+    pic_md5_sum: String,
+}
+
+#[derive(Default, Serialize, Deserialize)]
+struct PicList {
+    #[serde(default, deserialize_with = "one_or_many")]
+    item: Vec<PicMd5Sum>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct SendPicsInfo {
+    #[serde(deserialize_with = "number_from_str")]
+    count: u16,
+    #[serde(default, deserialize_with = "empty_as_default")]
+    pic_list: PicList,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct RawSendPicsEvent {
+    event_key: String,
+    send_pics_info: SendPicsInfo,
+}
+
+impl From<RawSendPicsEvent> for SendPicsEvent {
+    fn from(raw: RawSendPicsEvent) -> Self {
+        SendPicsEvent {
+            event_key: raw.event_key,
+            count: raw.send_pics_info.count,
+            pic_md5_sum_list: raw
+                .send_pics_info
+                .pic_list
+                .item
+                .into_iter()
+                .map(|i| i.pic_md5_sum)
+                .collect(),
+        }
     }
 }
 
+impl From<SendPicsEvent> for RawSendPicsEvent {
+    fn from(event: SendPicsEvent) -> Self {
+        RawSendPicsEvent {
+            event_key: event.event_key,
+            send_pics_info: SendPicsInfo {
+                count: event.count,
+                pic_list: PicList {
+                    item: event
+                        .pic_md5_sum_list
+                        .into_iter()
+                        .map(|pic_md5_sum| PicMd5Sum { pic_md5_sum })
+                        .collect(),
+                },
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(from = "RawSendLocationEvent", into = "RawSendLocationEvent")]
 pub struct SendLocationEvent {
     pub event_key: String,
     pub location_x: f32,
@@ -62,44 +83,60 @@ pub struct SendLocationEvent {
     pub poiname: Option<String>,
 }
 
-impl ReceivedMessageParser for SendLocationEvent {
-    type ReceivedMessage = SendLocationEvent;
-
-    fn from_xml(node: &roxmltree::Node) -> SdkResult<Self::ReceivedMessage> {
-        let event_key = get_text_from_root(node, "EventKey")?;
-        let send_location_info = node
-            .descendants()
-            .find(|n| n.has_tag_name("SendLocationInfo"))
-            .ok_or_else(|| {
-                SdkError::InvalidParams(format!(
-                    "Parse XML msg from wechat error: tag `{}` is none",
-                    "SendLocationInfo"
-                ))
-            })?;
-
-        let location_x = get_number_from_root::<f32>(&send_location_info, "Location_X")?;
-        let location_y = get_number_from_root::<f32>(&send_location_info, "Location_Y")?;
-        let scale = get_number_from_root::<f32>(&send_location_info, "Scale")?;
-        let label = get_text_from_root(&send_location_info, "Label")?;
-        let poiname = get_text_from_root(&send_location_info, "Poiname")
-            .ok()
-            .filter(|s| !s.trim().is_empty())
-            .map(|s| s.to_string());
-        Ok(SendLocationEvent {
-            event_key: event_key.to_string(),
-            location_x,
-            location_y,
-            scale,
-            label: label.to_string(),
-            poiname,
-        })
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct SendLocationInfo {
+    #[serde(rename = "Location_X", deserialize_with = "number_from_str")]
+    location_x: f32,
+    #[serde(rename = "Location_Y", deserialize_with = "number_from_str")]
+    location_y: f32,
+    #[serde(deserialize_with = "number_from_str")]
+    scale: f32,
+    label: String,
+    #[serde(default, deserialize_with = "empty_string_as_none")]
+    poiname: Option<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct RawSendLocationEvent {
+    event_key: String,
+    send_location_info: SendLocationInfo,
+}
+
+impl From<RawSendLocationEvent> for SendLocationEvent {
+    fn from(raw: RawSendLocationEvent) -> Self {
+        let info = raw.send_location_info;
+        SendLocationEvent {
+            event_key: raw.event_key,
+            location_x: info.location_x,
+            location_y: info.location_y,
+            scale: info.scale,
+            label: info.label,
+            poiname: info.poiname,
+        }
+    }
+}
+
+impl From<SendLocationEvent> for RawSendLocationEvent {
+    fn from(event: SendLocationEvent) -> Self {
+        RawSendLocationEvent {
+            event_key: event.event_key,
+            send_location_info: SendLocationInfo {
+                location_x: event.location_x,
+                location_y: event.location_y,
+                scale: event.scale,
+                label: event.label,
+                poiname: event.poiname,
+            },
+        }
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::SdkResult;
+    use crate::{mp::event::ReceivedMessageParser, SdkResult};
     #[test]
     pub fn parse_send_pics() -> SdkResult<()> {
         use roxmltree::Document;
@@ -1,35 +1,36 @@
+use roxmltree::Node;
+use serde_json::Value;
+
 use crate::{
-    error::SdkError,
-    mp::event::{xmlutil::get_text_from_root, ReceivedMessageParser},
+    mp::event::{xmlutil::node_to_json, ReceivedMessageParser},
     SdkResult,
 };
 
-use super::{event_scan::ScanEvent, EventMessage};
+use super::EventMessage;
 
+/// 关注事件，扫描带参数二维码关注时带有 `EventKey` 和 `Ticket`，解析为 [EventMessage::SubscribeScan]
 pub struct SubScribeEvent;
 
+impl SubScribeEvent {
+    pub(crate) fn from_json(value: Value) -> SdkResult<EventMessage> {
+        let has_ticket = value
+            .get("Ticket")
+            .and_then(|t| t.as_str())
+            .map(|t| !t.trim().is_empty())
+            .unwrap_or(false);
+        if has_ticket {
+            Ok(EventMessage::SubscribeScan(serde_json::from_value(value)?))
+        } else {
+            Ok(EventMessage::Subscribe)
+        }
+    }
+}
+
 impl ReceivedMessageParser for SubScribeEvent {
     type ReceivedMessage = EventMessage;
 
-    fn from_xml(node: &roxmltree::Node) -> SdkResult<Self::ReceivedMessage> {
-        let ekn = node.descendants().find(|n| n.has_tag_name("EventKey"));
-        let event = match ekn {
-            Some(n) => {
-                let event_key = n.text().ok_or_else(|| {
-                    SdkError::InvalidParams(format!(
-                        "Parse XML msg from wechat error: tag `{}` text content is none",
-                        n.tag_name().name()
-                    ))
-                })?;
-                let ticket = get_text_from_root(node, "Ticket")?;
-                EventMessage::SubscribeScan(ScanEvent {
-                    event_key: event_key.to_string(),
-                    ticket: ticket.to_string(),
-                })
-            }
-            None => EventMessage::Subscribe,
-        };
-        Ok(event)
+    fn from_xml(node: &Node) -> SdkResult<Self::ReceivedMessage> {
+        Self::from_json(node_to_json(node))
     }
 }
 
@@ -1,34 +1,19 @@
-use crate::{
-    mp::event::{xmlutil::get_text_from_root, ReceivedMessageParser},
-    SdkResult,
-};
+use serde::{Deserialize, Serialize};
 
+use crate::utils::de::empty_string_as_none;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
 pub struct ViewEvent {
     pub event_key: String,
+    #[serde(default, deserialize_with = "empty_string_as_none")]
     pub menu_id: Option<String>,
 }
 
-impl ReceivedMessageParser for ViewEvent {
-    type ReceivedMessage = ViewEvent;
-
-    fn from_xml(node: &roxmltree::Node) -> SdkResult<Self::ReceivedMessage> {
-        let event_key = get_text_from_root(node, "EventKey")?;
-        let menu_id = node
-            .descendants()
-            .find(|n| n.has_tag_name("MenuId"))
-            .map(|n| n.text())
-            .flatten()
-            .map(|s| s.to_string());
-        Ok(ViewEvent {
-            event_key: event_key.to_string(),
-            menu_id,
-        })
-    }
-}
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::SdkResult;
+    use crate::{mp::event::ReceivedMessageParser, SdkResult};
     #[test]
     pub fn parse() -> SdkResult<()> {
         use roxmltree::Document;
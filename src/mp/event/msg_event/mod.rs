@@ -1,6 +1,7 @@
-use roxmltree::Node;
+use serde::{de, ser, Deserialize, Deserializer, Serialize, Serializer};
+use serde_json::Value;
 
-use crate::SdkResult;
+use crate::{error::SdkError, SdkResult};
 
 use self::{
     event_click::ClickEvent,
@@ -14,7 +15,7 @@ use self::{
     event_view::ViewEvent,
 };
 
-use super::{xmlutil::get_text_from_root, ReceivedMessageParser};
+use super::to_json_object;
 
 pub mod event_click;
 pub mod event_guide;
@@ -45,6 +46,7 @@ const EVENT_GUIDE_INVITE_RESULT: &'static str = "guide_invite_result_event";
 const EVENT_GUIDE_QRCODE_SCAN: &'static str = "guide_qrcode_scan_event";
 const EVENT_PUBLISHJOBFINISH: &'static str = "PUBLISHJOBFINISH";
 
+#[derive(Debug, Clone)]
 pub enum EventMessage {
     Subscribe,
     UnSubscribe,
@@ -65,54 +67,84 @@ pub enum EventMessage {
     GuideInviteResult(GuideInviteResultEvent),
     GuideQrcodeScan(GuideQrcodeScanEvent),
     PublishJobFinish(PublishJobFinishEvent),
+    /// 未处理的事件，保留转换为 JSON 后的消息原文
     UnhandledEvent(String),
 }
 
-impl ReceivedMessageParser for EventMessage {
-    type ReceivedMessage = Self;
-
-    fn from_xml(node: &Node) -> SdkResult<Self::ReceivedMessage> {
-        let event_type = get_text_from_root(node, "Event")?;
-        let event = match event_type {
-            EVENT_SUBSCRIBE => SubScribeEvent::from_xml(node)?,
+impl EventMessage {
+    /// 从转换为 JSON 的消息中按 `Event` 解析事件
+    pub fn from_json(value: Value) -> SdkResult<Self> {
+        let event_type = value
+            .get("Event")
+            .and_then(|e| e.as_str())
+            .ok_or_else(|| SdkError::InvalidParams("parse msg need `Event` params".to_owned()))?
+            .to_owned();
+        let event = match event_type.as_str() {
+            EVENT_SUBSCRIBE => SubScribeEvent::from_json(value)?,
             EVENT_UNSUBSCRIBE => EventMessage::UnSubscribe,
-            EVENT_SCAN => EventMessage::Scan(ScanEvent::from_xml(node)?),
-            EVENT_LOCATION => EventMessage::Location(LocationEvent::from_xml(node)?),
-            EVENT_CLICK => EventMessage::Click(ClickEvent::from_xml(node)?),
-            EVENT_VIEW => EventMessage::View(ViewEvent::from_xml(node)?),
-            EVENT_VIEW_MINIPROGRAM => EventMessage::ViewMiniProgram(ViewEvent::from_xml(node)?),
-            EVENT_SCANCODE_PUSH => EventMessage::ScanCodePush(MenuScanEvent::from_xml(node)?),
-            EVENT_SCANCODE_WAITMSG => EventMessage::ScanCodeWaitMsg(MenuScanEvent::from_xml(node)?),
-            EVENT_PIC_SYSPHOTO => EventMessage::PicSysPhoto(SendPicsEvent::from_xml(node)?),
+            EVENT_SCAN => EventMessage::Scan(serde_json::from_value(value)?),
+            EVENT_LOCATION => EventMessage::Location(serde_json::from_value(value)?),
+            EVENT_CLICK => EventMessage::Click(serde_json::from_value(value)?),
+            EVENT_VIEW => EventMessage::View(serde_json::from_value(value)?),
+            EVENT_VIEW_MINIPROGRAM => EventMessage::ViewMiniProgram(serde_json::from_value(value)?),
+            EVENT_SCANCODE_PUSH => EventMessage::ScanCodePush(serde_json::from_value(value)?),
+            EVENT_SCANCODE_WAITMSG => EventMessage::ScanCodeWaitMsg(serde_json::from_value(value)?),
+            EVENT_PIC_SYSPHOTO => EventMessage::PicSysPhoto(serde_json::from_value(value)?),
             EVENT_PIC_PHOTO_OR_ALBUM => {
-                EventMessage::PicPhotoOrAlbum(SendPicsEvent::from_xml(node)?)
-            }
-            EVENT_PIC_WEIXIN => EventMessage::PicWeixin(SendPicsEvent::from_xml(node)?),
-            EVENT_LOCATION_SELECT => {
-                EventMessage::LocationSelect(SendLocationEvent::from_xml(node)?)
+                EventMessage::PicPhotoOrAlbum(serde_json::from_value(value)?)
             }
+            EVENT_PIC_WEIXIN => EventMessage::PicWeixin(serde_json::from_value(value)?),
+            EVENT_LOCATION_SELECT => EventMessage::LocationSelect(serde_json::from_value(value)?),
             EVENT_MASSSENDJOBFINISH => {
-                EventMessage::MassSendJobFinish(MassSendJobFinishEvent::from_xml(node)?)
+                EventMessage::MassSendJobFinish(serde_json::from_value(value)?)
             }
             EVENT_TEMPLATESENDJOBFINISH => {
-                EventMessage::TemplateSendJobFinish(TemplateSendJobFinishEvent::from_xml(node)?)
+                EventMessage::TemplateSendJobFinish(serde_json::from_value(value)?)
             }
             EVENT_GUIDE_INVITE_RESULT => {
-                EventMessage::GuideInviteResult(GuideInviteResultEvent::from_xml(node)?)
+                EventMessage::GuideInviteResult(serde_json::from_value(value)?)
             }
             EVENT_GUIDE_QRCODE_SCAN => {
-                EventMessage::GuideQrcodeScan(GuideQrcodeScanEvent::from_xml(node)?)
+                EventMessage::GuideQrcodeScan(serde_json::from_value(value)?)
             }
             EVENT_PUBLISHJOBFINISH => {
-                EventMessage::PublishJobFinish(PublishJobFinishEvent::from_xml(node)?)
+                EventMessage::PublishJobFinish(serde_json::from_value(value)?)
             }
-            _ => EventMessage::UnhandledEvent(format!("unhandle this event type: {}", event_type)),
+            _ => EventMessage::UnhandledEvent(value.to_string()),
         };
         Ok(event)
     }
-}
 
-impl EventMessage {
+    /// 转换为与消息推送格式一致的 JSON
+    pub fn to_json(&self) -> SdkResult<Value> {
+        let mut map = match self {
+            EventMessage::Subscribe | EventMessage::UnSubscribe => serde_json::Map::new(),
+            EventMessage::SubscribeScan(e) | EventMessage::Scan(e) => to_json_object(e)?,
+            EventMessage::Location(e) => to_json_object(e)?,
+            EventMessage::Click(e) => to_json_object(e)?,
+            EventMessage::View(e) | EventMessage::ViewMiniProgram(e) => to_json_object(e)?,
+            EventMessage::ScanCodePush(e) | EventMessage::ScanCodeWaitMsg(e) => to_json_object(e)?,
+            EventMessage::PicSysPhoto(e)
+            | EventMessage::PicPhotoOrAlbum(e)
+            | EventMessage::PicWeixin(e) => to_json_object(e)?,
+            EventMessage::LocationSelect(e) => to_json_object(e)?,
+            EventMessage::TemplateSendJobFinish(e) => to_json_object(e)?,
+            EventMessage::MassSendJobFinish(e) => to_json_object(e)?,
+            EventMessage::GuideInviteResult(e) => to_json_object(e)?,
+            EventMessage::GuideQrcodeScan(e) => to_json_object(e)?,
+            EventMessage::PublishJobFinish(e) => to_json_object(e)?,
+            // the raw message has the original `Event`
+            EventMessage::UnhandledEvent(json) => {
+                return Ok(serde_json::from_str(json)?);
+            }
+        };
+        map.insert(
+            "Event".to_owned(),
+            Value::String(self.get_event_type().to_owned()),
+        );
+        Ok(Value::Object(map))
+    }
+
     pub fn get_event_type(&self) -> &'static str {
         match self {
             EventMessage::Subscribe => EVENT_SUBSCRIBE,
@@ -138,3 +170,18 @@ impl EventMessage {
         }
     }
 }
+
+impl Serialize for EventMessage {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.to_json()
+            .map_err(ser::Error::custom)?
+            .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for EventMessage {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let value = Value::deserialize(deserializer)?;
+        EventMessage::from_json(value).map_err(de::Error::custom)
+    }
+}
@@ -1,37 +1,20 @@
-use roxmltree::Node;
+use serde::{Deserialize, Serialize};
 
-use crate::SdkResult;
-
-use super::{
-    xmlutil::{get_number_from_root, get_text_from_root},
-    ReceivedMessageParser,
-};
+use crate::utils::de::number_from_str;
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
 pub struct ImageMessage {
+    #[serde(deserialize_with = "number_from_str")]
     pub msg_id: u64,
     pub pic_url: String,
     pub media_id: String,
 }
 
-impl ReceivedMessageParser for ImageMessage {
-    type ReceivedMessage = Self;
-
-    fn from_xml(node: &Node) -> SdkResult<Self::ReceivedMessage> {
-        let msg_id = get_number_from_root::<u64>(node, "MsgId")?;
-        let pic_url = get_text_from_root(node, "PicUrl")?;
-        let media_id = get_text_from_root(node, "MediaId")?;
-
-        Ok(ImageMessage {
-            msg_id,
-            pic_url: pic_url.to_owned(),
-            media_id: media_id.to_owned(),
-        })
-    }
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::mp::event::ReceivedMessageParser;
     use crate::SdkResult;
     #[test]
     pub fn parse() -> SdkResult<()> {
@@ -1,40 +1,21 @@
-use roxmltree::Node;
+use serde::{Deserialize, Serialize};
 
-use crate::SdkResult;
-
-use super::{
-    xmlutil::{get_number_from_root, get_text_from_root},
-    ReceivedMessageParser,
-};
+use crate::utils::de::number_from_str;
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
 pub struct LinkMessage {
+    #[serde(deserialize_with = "number_from_str")]
     pub msg_id: u64,
     pub title: String,
     pub description: String,
     pub url: String,
 }
 
-impl ReceivedMessageParser for LinkMessage {
-    type ReceivedMessage = LinkMessage;
-
-    fn from_xml(node: &Node) -> SdkResult<Self::ReceivedMessage> {
-        let msg_id = get_number_from_root::<u64>(node, "MsgId")?;
-        let title = get_text_from_root(node, "Title")?;
-        let description = get_text_from_root(node, "Description")?;
-        let url = get_text_from_root(node, "Url")?;
-
-        Ok(LinkMessage {
-            msg_id,
-            title: title.to_owned(),
-            description: description.to_owned(),
-            url: url.to_owned(),
-        })
-    }
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::mp::event::ReceivedMessageParser;
     use crate::SdkResult;
     #[test]
     pub fn parse() -> SdkResult<()> {
@@ -1,43 +1,25 @@
-use roxmltree::Node;
+use serde::{Deserialize, Serialize};
 
-use crate::SdkResult;
-
-use super::{
-    xmlutil::{get_number_from_root, get_text_from_root},
-    ReceivedMessageParser,
-};
+use crate::utils::de::number_from_str;
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
 pub struct LocationMessage {
+    #[serde(deserialize_with = "number_from_str")]
     pub msg_id: u64,
+    #[serde(rename = "Location_X", deserialize_with = "number_from_str")]
     pub location_x: f32,
+    #[serde(rename = "Location_Y", deserialize_with = "number_from_str")]
     pub location_y: f32,
+    #[serde(deserialize_with = "number_from_str")]
     pub scale: f32,
     pub label: String,
 }
 
-impl ReceivedMessageParser for LocationMessage {
-    type ReceivedMessage = LocationMessage;
-
-    fn from_xml(node: &Node) -> SdkResult<Self::ReceivedMessage> {
-        let msg_id = get_number_from_root::<u64>(node, "MsgId")?;
-        let location_x = get_number_from_root::<f32>(node, "Location_X")?;
-        let location_y = get_number_from_root::<f32>(node, "Location_Y")?;
-        let scale = get_number_from_root::<f32>(node, "Scale")?;
-        let label = get_text_from_root(node, "Label")?;
-
-        Ok(LocationMessage {
-            msg_id,
-            location_x,
-            location_y,
-            scale,
-            label: label.to_owned(),
-        })
-    }
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::mp::event::ReceivedMessageParser;
     use crate::SdkResult;
     #[test]
     pub fn parse() -> SdkResult<()> {
@@ -1,42 +1,17 @@
-use roxmltree::Node;
+use serde::{Deserialize, Serialize};
 
-use crate::{error::SdkError, SdkResult};
-
-use super::ReceivedMessageParser;
+use crate::utils::de::number_from_str;
+#[cfg(test)]
+use crate::{mp::event::ReceivedMessageParser, SdkResult};
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
 pub struct TextMessage {
+    #[serde(deserialize_with = "number_from_str")]
     pub msg_id: u64,
     pub content: String,
 }
 
-impl ReceivedMessageParser for TextMessage {
-    type ReceivedMessage = TextMessage;
-
-    fn from_xml(node: &Node) -> SdkResult<Self::ReceivedMessage> {
-        let content = node
-            .descendants()
-            .find(|n| n.has_tag_name("Content"))
-            .map(|n| n.text())
-            .flatten()
-            .ok_or_else(|| SdkError::InvalidParams("parse xml need Content params".to_owned()))?;
-        let msg_id = node
-            .descendants()
-            .find(|n| n.has_tag_name("MsgId"))
-            .map(|n| n.text())
-            .flatten()
-            .ok_or_else(|| SdkError::InvalidParams("parse xml need MsgId params".to_owned()))?;
-        let msg_id = msg_id.parse::<u64>().map_err(|_e| {
-            SdkError::InvalidParams(
-                "Parse XML msg from wechat error: tag `MsgId` should be number".to_string(),
-            )
-        })?;
-        Ok(TextMessage {
-            content: content.to_owned(),
-            msg_id,
-        })
-    }
-}
-
 #[test]
 pub fn parse() -> SdkResult<()> {
     use roxmltree::Document;
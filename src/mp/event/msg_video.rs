@@ -1,34 +1,18 @@
-use roxmltree::Node;
+use serde::{Deserialize, Serialize};
 
-use crate::SdkResult;
-
-use super::{
-    xmlutil::{get_number_from_root, get_text_from_root},
-    ReceivedMessageParser,
-};
+use crate::utils::de::number_from_str;
+#[cfg(test)]
+use crate::{mp::event::ReceivedMessageParser, SdkResult};
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
 pub struct VideoMessage {
+    #[serde(deserialize_with = "number_from_str")]
     pub msg_id: u64,
     pub thumb_media_id: String,
     pub media_id: String,
 }
 
-impl ReceivedMessageParser for VideoMessage {
-    type ReceivedMessage = VideoMessage;
-
-    fn from_xml(node: &Node) -> SdkResult<Self::ReceivedMessage> {
-        let msg_id = get_number_from_root::<u64>(node, "MsgId")?;
-        let media_id = get_text_from_root(node, "MediaId")?;
-        let thumb_media_id = get_text_from_root(node, "ThumbMediaId")?;
-
-        Ok(VideoMessage {
-            msg_id,
-            media_id: media_id.to_owned(),
-            thumb_media_id: thumb_media_id.to_owned(),
-        })
-    }
-}
-
 #[test]
 pub fn parse() -> SdkResult<()> {
     use roxmltree::Document;
@@ -1,40 +1,21 @@
-use roxmltree::Node;
+use serde::{Deserialize, Serialize};
 
-use crate::SdkResult;
-
-use super::{
-    xmlutil::{get_number_from_root, get_text_from_root},
-    ReceivedMessageParser,
-};
+use crate::utils::de::{empty_string_as_none, number_from_str};
+#[cfg(test)]
+use crate::{mp::event::ReceivedMessageParser, SdkResult};
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
 pub struct VoiceMessage {
+    #[serde(deserialize_with = "number_from_str")]
     pub msg_id: u64,
     pub format: String,
     pub media_id: String,
+    /// 开通语音识别后的识别结果
+    #[serde(default, deserialize_with = "empty_string_as_none")]
     pub recognition: Option<String>,
 }
 
-impl ReceivedMessageParser for VoiceMessage {
-    type ReceivedMessage = VoiceMessage;
-
-    fn from_xml(node: &Node) -> SdkResult<Self::ReceivedMessage> {
-        let msg_id = get_number_from_root::<u64>(node, "MsgId")?;
-        let media_id = get_text_from_root(node, "MediaId")?;
-        let format = get_text_from_root(node, "Format")?;
-        let recognition = node.descendants().find(|n| n.has_tag_name("Recognition"));
-        let recognition = recognition
-            .map(|n| n.text())
-            .map(|t| t.map(|s| s.to_string()))
-            .unwrap_or_else(|| None);
-        Ok(VoiceMessage {
-            msg_id,
-            format: format.to_owned(),
-            media_id: media_id.to_owned(),
-            recognition,
-        })
-    }
-}
-
 #[test]
 pub fn parse() -> SdkResult<()> {
     use roxmltree::Document;
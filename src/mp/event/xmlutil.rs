@@ -43,3 +43,69 @@ pub fn get_number_from_root<'a, T: std::str::FromStr>(
             ))
         })
 }
+
+/// Convert a XML node to a JSON value, the document root is converted from its root element.
+pub fn node_to_json(node: &Node) -> serde_json::Value {
+    if node.is_root() {
+        return node
+            .first_element_child()
+            .map(|n| node_to_json(&n))
+            .unwrap_or(serde_json::Value::Null);
+    }
+    let children: Vec<Node> = node.children().filter(|n| n.is_element()).collect();
+    if children.is_empty() {
+        let text = node.children().filter_map(|n| n.text()).collect::<String>();
+        return serde_json::Value::String(text);
+    }
+    let mut map = serde_json::Map::new();
+    for child in children.iter() {
+        let name = child.tag_name().name().to_owned();
+        let value = node_to_json(child);
+        match map.get_mut(&name) {
+            Some(serde_json::Value::Array(arr)) => arr.push(value),
+            Some(v) => {
+                let first = v.take();
+                *v = serde_json::Value::Array(vec![first, value]);
+            }
+            None => {
+                map.insert(name, value);
+            }
+        }
+    }
+    serde_json::Value::Object(map)
+}
+
+/// Convert the XML message to a JSON value, so that it can be deserialized by serde.
+///
+/// The text of leaf elements become strings, and the repeated elements become an array.
+pub fn xml_to_json(input: &str) -> SdkResult<serde_json::Value> {
+    let doc = roxmltree::Document::parse(input)?;
+    Ok(node_to_json(&doc.root()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_xml_to_json() -> SdkResult<()> {
+        let s = "<xml>
+    <ToUserName><![CDATA[toUser]]></ToUserName>
+    <CreateTime>1348831860</CreateTime>
+    <Empty></Empty>
+    <List><TemplateId><![CDATA[a]]></TemplateId></List>
+    <List><TemplateId><![CDATA[b]]></TemplateId></List>
+  </xml>";
+        let value = xml_to_json(s)?;
+        assert_eq!(
+            value,
+            serde_json::json!({
+                "ToUserName": "toUser",
+                "CreateTime": "1348831860",
+                "Empty": "",
+                "List": [{ "TemplateId": "a" }, { "TemplateId": "b" }]
+            })
+        );
+        Ok(())
+    }
+}
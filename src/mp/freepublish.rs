@@ -3,6 +3,7 @@ use serde::{Deserialize, Serialize};
 use crate::{
     access_token::AccessTokenProvider,
     error::{CommonError, CommonResponse},
+    utils::de::{number_from_str, one_or_many},
     wechat::WxApiRequestBuilder,
     SdkResult, WxSdk,
 };
@@ -26,14 +27,17 @@ pub struct PublishResult {
     pub fail_idx: Vec<i8>,
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ArticleDetail {
+    #[serde(deserialize_with = "number_from_str")]
     pub count: i8,
+    #[serde(deserialize_with = "one_or_many")]
     pub item: Vec<ArticleDetailItem>,
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ArticleDetailItem {
+    #[serde(deserialize_with = "number_from_str")]
     pub idx: i8,
     pub article_url: String,
 }
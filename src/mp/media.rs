@@ -102,7 +102,7 @@ impl<'a, T: WxApiRequestBuilder> MediaModule<'a, T> {
         let builder = sdk.wx_get(base_url).await?;
         let builder = builder.query(&[("media_id", media_id.as_ref())]);
         // 直接返回给前端处理了 https://developers.weixin.qq.com/doc/offiaccount/Asset_Management/Get_temporary_materials.html
-        Ok(builder.send().await?)
+        builder.send().await
     }
 
     /// 上传图文消息素材【订阅号与服务号认证后均可用】效
@@ -0,0 +1,566 @@
+//! 消息推送服务端处理
+//!
+//! [MpServer] 与 web 框架无关，只需要传入请求的 method、query string 和 body，
+//! 即可完成服务器地址验证、签名校验、消息解析、按消息类型分发到 [MessageHandler] 以及回复消息的加密。
+//! ```no_run
+//! use async_trait::async_trait;
+//! use wx_sdk::mp::{
+//!     event::{msg_text::TextMessage, ReceivedEvent},
+//!     reply::{Reply, Text},
+//!     server::{MessageHandler, MpServer},
+//!     EncodingMode, ServerConfig,
+//! };
+//! use wx_sdk::{SdkResult, WxSdk};
+//!
+//! struct Echo;
+//!
+//! #[async_trait]
+//! impl MessageHandler for Echo {
+//!     async fn on_text(&self, _event: &ReceivedEvent, msg: &TextMessage) -> SdkResult<Option<Reply>> {
+//!         Ok(Some(Reply::Text(Text { content: msg.content.clone() })))
+//!     }
+//! }
+//!
+//! let sdk = WxSdk::new_with_default_token_client("app_id", "app_secret");
+//! let server = MpServer::new(sdk.mp(ServerConfig::new("token", EncodingMode::Plain)), Echo);
+//! async {
+//!     let res = server.handle("POST", "signature=xxx&timestamp=xxx&nonce=xxx", "<xml>...</xml>").await;
+//!     println!("{} {}", res.status, res.body);
+//! };
+//! ```
+use std::collections::HashMap;
+
+use async_trait::async_trait;
+
+use crate::{access_token::AccessTokenProvider, error::SdkError, SdkResult};
+
+use super::{
+    event::{
+        msg_event::{
+            event_click::ClickEvent,
+            event_guide::{GuideInviteResultEvent, GuideQrcodeScanEvent},
+            event_location::LocationEvent,
+            event_publishjob::PublishJobFinishEvent,
+            event_scan::{MenuScanEvent, ScanEvent},
+            event_send::{SendLocationEvent, SendPicsEvent},
+            event_sendjob::{MassSendJobFinishEvent, TemplateSendJobFinishEvent},
+            event_view::ViewEvent,
+            EventMessage,
+        },
+        msg_image::ImageMessage,
+        msg_link::LinkMessage,
+        msg_location::LocationMessage,
+        msg_text::TextMessage,
+        msg_video::VideoMessage,
+        msg_voice::VoiceMessage,
+        signature::Signature,
+        ReceivedEvent, ReceivedMessage,
+    },
+    reply::Reply,
+    MpSdk,
+};
+
+#[cfg(feature = "actix")]
+pub mod actix;
+#[cfg(feature = "axum")]
+pub mod axum;
+
+/// 无需回复时返回给微信服务器的内容
+pub const REPLY_SUCCESS: &str = "success";
+
+/// 处理结果，由调用方转换为对应 web 框架的响应
+#[derive(Debug, Clone, PartialEq)]
+pub struct ServerResponse {
+    pub status: u16,
+    pub body: String,
+}
+
+impl ServerResponse {
+    pub fn ok<S: Into<String>>(body: S) -> Self {
+        ServerResponse {
+            status: 200,
+            body: body.into(),
+        }
+    }
+
+    pub fn error<S: Into<String>>(status: u16, body: S) -> Self {
+        ServerResponse {
+            status,
+            body: body.into(),
+        }
+    }
+}
+
+/// 解析 url 中的 query string
+pub fn parse_query(query: &str) -> HashMap<String, String> {
+    url::form_urlencoded::parse(query.trim_start_matches('?').as_bytes())
+        .into_owned()
+        .collect()
+}
+
+/// 推送消息处理，按消息类型和事件类型分发，默认不回复。
+///
+/// 返回 `Ok(None)` 时回复 `success`，返回 [Reply] 时回复对应的消息（安全模式下自动加密）。
+#[async_trait]
+pub trait MessageHandler: Send + Sync {
+    /// 处理所有推送消息，默认按 [ReceivedMessage] 和 [EventMessage] 的类型分发到下面的方法
+    async fn on_message(&self, event: &ReceivedEvent) -> SdkResult<Option<Reply>> {
+        match &event.body {
+            ReceivedMessage::Text(m) => self.on_text(event, m).await,
+            ReceivedMessage::Image(m) => self.on_image(event, m).await,
+            ReceivedMessage::Voice(m) => self.on_voice(event, m).await,
+            ReceivedMessage::Video(m) => self.on_video(event, m).await,
+            ReceivedMessage::ShortVideo(m) => self.on_short_video(event, m).await,
+            ReceivedMessage::Location(m) => self.on_location(event, m).await,
+            ReceivedMessage::Link(m) => self.on_link(event, m).await,
+            ReceivedMessage::UnhandledMessage(_) => self.on_unhandled_message(event).await,
+            ReceivedMessage::Event(e) => match e {
+                EventMessage::Subscribe => self.on_subscribe(event).await,
+                EventMessage::UnSubscribe => self.on_unsubscribe(event).await,
+                EventMessage::SubscribeScan(m) => self.on_subscribe_scan(event, m).await,
+                EventMessage::Scan(m) => self.on_scan(event, m).await,
+                EventMessage::Location(m) => self.on_location_event(event, m).await,
+                EventMessage::Click(m) => self.on_click(event, m).await,
+                EventMessage::View(m) => self.on_view(event, m).await,
+                EventMessage::ViewMiniProgram(m) => self.on_view_miniprogram(event, m).await,
+                EventMessage::ScanCodePush(m) => self.on_scancode_push(event, m).await,
+                EventMessage::ScanCodeWaitMsg(m) => self.on_scancode_waitmsg(event, m).await,
+                EventMessage::PicSysPhoto(m) => self.on_pic_sysphoto(event, m).await,
+                EventMessage::PicPhotoOrAlbum(m) => self.on_pic_photo_or_album(event, m).await,
+                EventMessage::PicWeixin(m) => self.on_pic_weixin(event, m).await,
+                EventMessage::LocationSelect(m) => self.on_location_select(event, m).await,
+                EventMessage::TemplateSendJobFinish(m) => {
+                    self.on_template_send_job_finish(event, m).await
+                }
+                EventMessage::MassSendJobFinish(m) => self.on_mass_send_job_finish(event, m).await,
+                EventMessage::GuideInviteResult(m) => self.on_guide_invite_result(event, m).await,
+                EventMessage::GuideQrcodeScan(m) => self.on_guide_qrcode_scan(event, m).await,
+                EventMessage::PublishJobFinish(m) => self.on_publish_job_finish(event, m).await,
+                EventMessage::UnhandledEvent(_) => self.on_unhandled_event(event).await,
+            },
+        }
+    }
+
+    /// 文本消息
+    async fn on_text(
+        &self,
+        _event: &ReceivedEvent,
+        _msg: &TextMessage,
+    ) -> SdkResult<Option<Reply>> {
+        Ok(None)
+    }
+    /// 图片消息
+    async fn on_image(
+        &self,
+        _event: &ReceivedEvent,
+        _msg: &ImageMessage,
+    ) -> SdkResult<Option<Reply>> {
+        Ok(None)
+    }
+    /// 语音消息
+    async fn on_voice(
+        &self,
+        _event: &ReceivedEvent,
+        _msg: &VoiceMessage,
+    ) -> SdkResult<Option<Reply>> {
+        Ok(None)
+    }
+    /// 视频消息
+    async fn on_video(
+        &self,
+        _event: &ReceivedEvent,
+        _msg: &VideoMessage,
+    ) -> SdkResult<Option<Reply>> {
+        Ok(None)
+    }
+    /// 小视频消息
+    async fn on_short_video(
+        &self,
+        _event: &ReceivedEvent,
+        _msg: &VideoMessage,
+    ) -> SdkResult<Option<Reply>> {
+        Ok(None)
+    }
+    /// 地理位置消息
+    async fn on_location(
+        &self,
+        _event: &ReceivedEvent,
+        _msg: &LocationMessage,
+    ) -> SdkResult<Option<Reply>> {
+        Ok(None)
+    }
+    /// 链接消息
+    async fn on_link(
+        &self,
+        _event: &ReceivedEvent,
+        _msg: &LinkMessage,
+    ) -> SdkResult<Option<Reply>> {
+        Ok(None)
+    }
+    /// 未处理的消息类型
+    async fn on_unhandled_message(&self, _event: &ReceivedEvent) -> SdkResult<Option<Reply>> {
+        Ok(None)
+    }
+
+    /// 关注事件
+    async fn on_subscribe(&self, _event: &ReceivedEvent) -> SdkResult<Option<Reply>> {
+        Ok(None)
+    }
+    /// 取消关注事件
+    async fn on_unsubscribe(&self, _event: &ReceivedEvent) -> SdkResult<Option<Reply>> {
+        Ok(None)
+    }
+    /// 用户未关注时扫描带参数二维码，关注后的事件推送
+    async fn on_subscribe_scan(
+        &self,
+        _event: &ReceivedEvent,
+        _msg: &ScanEvent,
+    ) -> SdkResult<Option<Reply>> {
+        Ok(None)
+    }
+    /// 用户已关注时扫描带参数二维码的事件推送
+    async fn on_scan(&self, _event: &ReceivedEvent, _msg: &ScanEvent) -> SdkResult<Option<Reply>> {
+        Ok(None)
+    }
+    /// 上报地理位置事件
+    async fn on_location_event(
+        &self,
+        _event: &ReceivedEvent,
+        _msg: &LocationEvent,
+    ) -> SdkResult<Option<Reply>> {
+        Ok(None)
+    }
+    /// 点击菜单拉取消息时的事件推送
+    async fn on_click(
+        &self,
+        _event: &ReceivedEvent,
+        _msg: &ClickEvent,
+    ) -> SdkResult<Option<Reply>> {
+        Ok(None)
+    }
+    /// 点击菜单跳转链接时的事件推送
+    async fn on_view(&self, _event: &ReceivedEvent, _msg: &ViewEvent) -> SdkResult<Option<Reply>> {
+        Ok(None)
+    }
+    /// 点击菜单跳转小程序的事件推送
+    async fn on_view_miniprogram(
+        &self,
+        _event: &ReceivedEvent,
+        _msg: &ViewEvent,
+    ) -> SdkResult<Option<Reply>> {
+        Ok(None)
+    }
+    /// 扫码推事件的事件推送
+    async fn on_scancode_push(
+        &self,
+        _event: &ReceivedEvent,
+        _msg: &MenuScanEvent,
+    ) -> SdkResult<Option<Reply>> {
+        Ok(None)
+    }
+    /// 扫码推事件且弹出“消息接收中”提示框的事件推送
+    async fn on_scancode_waitmsg(
+        &self,
+        _event: &ReceivedEvent,
+        _msg: &MenuScanEvent,
+    ) -> SdkResult<Option<Reply>> {
+        Ok(None)
+    }
+    /// 弹出系统拍照发图的事件推送
+    async fn on_pic_sysphoto(
+        &self,
+        _event: &ReceivedEvent,
+        _msg: &SendPicsEvent,
+    ) -> SdkResult<Option<Reply>> {
+        Ok(None)
+    }
+    /// 弹出拍照或者相册发图的事件推送
+    async fn on_pic_photo_or_album(
+        &self,
+        _event: &ReceivedEvent,
+        _msg: &SendPicsEvent,
+    ) -> SdkResult<Option<Reply>> {
+        Ok(None)
+    }
+    /// 弹出微信相册发图器的事件推送
+    async fn on_pic_weixin(
+        &self,
+        _event: &ReceivedEvent,
+        _msg: &SendPicsEvent,
+    ) -> SdkResult<Option<Reply>> {
+        Ok(None)
+    }
+    /// 弹出地理位置选择器的事件推送
+    async fn on_location_select(
+        &self,
+        _event: &ReceivedEvent,
+        _msg: &SendLocationEvent,
+    ) -> SdkResult<Option<Reply>> {
+        Ok(None)
+    }
+    /// 模板消息发送完成的事件推送
+    async fn on_template_send_job_finish(
+        &self,
+        _event: &ReceivedEvent,
+        _msg: &TemplateSendJobFinishEvent,
+    ) -> SdkResult<Option<Reply>> {
+        Ok(None)
+    }
+    /// 群发结果的事件推送
+    async fn on_mass_send_job_finish(
+        &self,
+        _event: &ReceivedEvent,
+        _msg: &MassSendJobFinishEvent,
+    ) -> SdkResult<Option<Reply>> {
+        Ok(None)
+    }
+    /// 顾问邀请结果的事件推送
+    async fn on_guide_invite_result(
+        &self,
+        _event: &ReceivedEvent,
+        _msg: &GuideInviteResultEvent,
+    ) -> SdkResult<Option<Reply>> {
+        Ok(None)
+    }
+    /// 顾问二维码扫码的事件推送
+    async fn on_guide_qrcode_scan(
+        &self,
+        _event: &ReceivedEvent,
+        _msg: &GuideQrcodeScanEvent,
+    ) -> SdkResult<Option<Reply>> {
+        Ok(None)
+    }
+    /// 发布任务完成的事件推送
+    async fn on_publish_job_finish(
+        &self,
+        _event: &ReceivedEvent,
+        _msg: &PublishJobFinishEvent,
+    ) -> SdkResult<Option<Reply>> {
+        Ok(None)
+    }
+    /// 未处理的事件类型
+    async fn on_unhandled_event(&self, _event: &ReceivedEvent) -> SdkResult<Option<Reply>> {
+        Ok(None)
+    }
+}
+
+/// 公众号消息推送服务端
+pub struct MpServer<T: AccessTokenProvider, H: MessageHandler> {
+    sdk: MpSdk<T>,
+    handler: H,
+}
+
+impl<T: AccessTokenProvider, H: MessageHandler> MpServer<T, H> {
+    pub fn new(sdk: MpSdk<T>, handler: H) -> Self {
+        MpServer { sdk, handler }
+    }
+
+    pub fn sdk(&self) -> &MpSdk<T> {
+        &self.sdk
+    }
+
+    /// 处理微信服务器的请求，`GET` 为服务器地址验证，`POST` 为消息推送
+    pub async fn handle(&self, method: &str, query: &str, body: &str) -> ServerResponse {
+        let params = parse_query(query);
+        if method.eq_ignore_ascii_case("GET") {
+            self.handle_verify(&params)
+        } else if method.eq_ignore_ascii_case("POST") {
+            self.handle_message(params, body).await
+        } else {
+            ServerResponse::error(405, "method not allowed")
+        }
+    }
+
+    fn handle_verify(&self, params: &HashMap<String, String>) -> ServerResponse {
+        match self.sdk.verify_server_url(params) {
+            Ok(echostr) => ServerResponse::ok(echostr),
+            Err(e) => error_response(e),
+        }
+    }
+
+    async fn handle_message(&self, params: HashMap<String, String>, body: &str) -> ServerResponse {
+        if let Err(e) = check_signature(&self.sdk.server_config.token, &params) {
+            return error_response(e);
+        }
+        let event = match self.sdk.parse_received_msg(body, Some(params.clone())) {
+            Ok(event) => event,
+            Err(e) => return error_response(e),
+        };
+        let reply = match self.handler.on_message(&event).await {
+            Ok(Some(reply)) => reply,
+            Ok(None) => return ServerResponse::ok(REPLY_SUCCESS),
+            Err(e) => return ServerResponse::error(500, e.to_string()),
+        };
+        match self
+            .sdk
+            .reply_to_xml(reply, event.to.as_str(), event.from.as_str(), Some(params))
+        {
+            Ok(xml) => ServerResponse::ok(xml),
+            Err(e) => ServerResponse::error(500, e.to_string()),
+        }
+    }
+}
+
+/// 校验 url 中的 `signature`，微信服务器的每次请求都会带上该签名
+pub(crate) fn check_signature(token: &str, params: &HashMap<String, String>) -> SdkResult<()> {
+    let get_param = |key: &str| {
+        params
+            .get(key)
+            .cloned()
+            .ok_or_else(|| SdkError::InvalidParams(key.to_owned()))
+    };
+    let check_sign = vec![
+        token.to_owned(),
+        get_param("timestamp")?,
+        get_param("nonce")?,
+    ];
+    if !Signature::new(get_param("signature")?, check_sign).is_ok() {
+        return Err(SdkError::InvalidSignature);
+    }
+    Ok(())
+}
+
+pub(crate) fn error_response(e: SdkError) -> ServerResponse {
+    match e {
+        SdkError::InvalidSignature | SdkError::InvalidAppid => {
+            ServerResponse::error(403, e.to_string())
+        }
+        _ => ServerResponse::error(400, e.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        mp::{
+            reply::{Reply, Text},
+            EncodingMode, ServerConfig,
+        },
+        WxSdk,
+    };
+
+    struct Echo;
+
+    #[async_trait]
+    impl MessageHandler for Echo {
+        async fn on_text(
+            &self,
+            _event: &ReceivedEvent,
+            msg: &TextMessage,
+        ) -> SdkResult<Option<Reply>> {
+            Ok(Some(Reply::Text(Text {
+                content: msg.content.clone(),
+            })))
+        }
+    }
+
+    fn get_server() -> MpServer<crate::TokenClient, Echo> {
+        let sdk = WxSdk::new_with_default_token_client("wx5823bf96d3bd56c7", "app_secret");
+        MpServer::new(
+            sdk.mp(ServerConfig::new("token", EncodingMode::Plain)),
+            Echo,
+        )
+    }
+
+    fn signed_query() -> String {
+        let signature = Signature::generate_signature(vec![
+            "token".to_owned(),
+            "1409659813".to_owned(),
+            "1372623149".to_owned(),
+        ]);
+        format!(
+            "signature={}&timestamp=1409659813&nonce=1372623149",
+            signature
+        )
+    }
+
+    #[tokio::test]
+    async fn test_verify() {
+        let server = get_server();
+        let res = server
+            .handle("GET", &format!("{}&echostr=hello", signed_query()), "")
+            .await;
+        assert_eq!(res, ServerResponse::ok("hello"));
+
+        let res = server
+            .handle(
+                "GET",
+                "signature=wrong&timestamp=1409659813&nonce=1372623149&echostr=hello",
+                "",
+            )
+            .await;
+        assert_eq!(res.status, 403);
+        assert_eq!(server.handle("PUT", "", "").await.status, 405);
+    }
+
+    #[tokio::test]
+    async fn test_verify_encrypted_echostr() -> SdkResult<()> {
+        let app_id = "wx5823bf96d3bd56c7";
+        let aes_key = "jWmYm7qr5nMoAUwZRjGtBxmz3KA1tkAj3ykkR6q2B2C";
+        let sdk = WxSdk::new_with_default_token_client(app_id, "app_secret").mp(ServerConfig::new(
+            "token",
+            EncodingMode::Security(aes_key.to_owned()),
+        ));
+        let server = MpServer::new(sdk, Echo);
+        let echostr = crate::mp::event::crypto::encrypt_message("hello", aes_key, app_id)?;
+        let msg_signature = Signature::generate_signature(vec![
+            "token".to_owned(),
+            "1409659813".to_owned(),
+            "1372623149".to_owned(),
+            echostr.clone(),
+        ]);
+        let mut params = parse_query("timestamp=1409659813&nonce=1372623149");
+        params.insert("echostr".to_owned(), echostr.clone());
+        params.insert("msg_signature".to_owned(), msg_signature);
+        assert_eq!(server.sdk().verify_server_url(&params)?, "hello");
+
+        params.insert("msg_signature".to_owned(), "wrong".to_owned());
+        assert!(matches!(
+            server.sdk().verify_server_url(&params),
+            Err(SdkError::InvalidSignature)
+        ));
+
+        // 不带 msg_signature 时按明文校验
+        let res = server
+            .handle("GET", &format!("{}&echostr=hello", signed_query()), "")
+            .await;
+        assert_eq!(res, ServerResponse::ok("hello"));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_handle_message() {
+        let server = get_server();
+        let text = "<xml>
+    <ToUserName><![CDATA[toUser]]></ToUserName>
+    <FromUserName><![CDATA[fromUser]]></FromUserName>
+    <CreateTime>1348831860</CreateTime>
+    <MsgType><![CDATA[text]]></MsgType>
+    <Content><![CDATA[this is a test]]></Content>
+    <MsgId>1234567890123456</MsgId>
+  </xml>";
+        let res = server.handle("POST", &signed_query(), text).await;
+        assert_eq!(res.status, 200);
+        assert!(res
+            .body
+            .contains("<ToUserName><![CDATA[fromUser]]></ToUserName>"));
+        assert!(res.body.contains("this is a test"));
+
+        let click = "<xml>
+    <ToUserName><![CDATA[toUser]]></ToUserName>
+    <FromUserName><![CDATA[FromUser]]></FromUserName>
+    <CreateTime>123456789</CreateTime>
+    <MsgType><![CDATA[event]]></MsgType>
+    <Event><![CDATA[CLICK]]></Event>
+    <EventKey><![CDATA[EVENTKEY]]></EventKey>
+  </xml>";
+        let res = server.handle("POST", &signed_query(), click).await;
+        assert_eq!(res, ServerResponse::ok(REPLY_SUCCESS));
+
+        let res = server.handle("POST", &signed_query(), "not xml").await;
+        assert_eq!(res.status, 400);
+        let res = server.handle("POST", "timestamp=1&nonce=1", text).await;
+        assert_eq!(res.status, 400);
+    }
+}
@@ -0,0 +1,104 @@
+//! actix-web 集成，提供挂载公众号消息推送地址的 [Resource]
+//! ```no_run
+//! # use std::sync::Arc;
+//! # use wx_sdk::mp::{server::{actix::mp_service, MessageHandler, MpServer}, EncodingMode, ServerConfig};
+//! # use wx_sdk::WxSdk;
+//! struct Handler;
+//! impl MessageHandler for Handler {}
+//!
+//! let sdk = WxSdk::new_with_default_token_client("app_id", "app_secret");
+//! let server = Arc::new(MpServer::new(sdk.mp(ServerConfig::new("token", EncodingMode::Plain)), Handler));
+//! let app = actix_web::App::new().service(mp_service("/wechat", server));
+//! ```
+use std::sync::Arc;
+
+use actix_web::{
+    body::BoxBody,
+    http::StatusCode,
+    web::{self, Data},
+    HttpRequest, HttpResponse, Resource, Responder,
+};
+
+use crate::access_token::AccessTokenProvider;
+
+use super::{MessageHandler, MpServer, ServerResponse};
+
+impl Responder for ServerResponse {
+    type Body = BoxBody;
+
+    fn respond_to(self, _req: &HttpRequest) -> HttpResponse<Self::Body> {
+        let status = StatusCode::from_u16(self.status).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
+        HttpResponse::build(status).body(self.body)
+    }
+}
+
+/// 在 `path` 上挂载 `GET`（服务器地址验证）和 `POST`（消息推送）路由
+pub fn mp_service<T, H>(path: &str, server: Arc<MpServer<T, H>>) -> Resource
+where
+    T: AccessTokenProvider + 'static,
+    H: MessageHandler + 'static,
+{
+    web::resource(path)
+        .app_data(Data::from(server))
+        .route(web::get().to(handle::<T, H>))
+        .route(web::post().to(handle::<T, H>))
+}
+
+async fn handle<T, H>(
+    req: HttpRequest,
+    body: String,
+    server: Data<MpServer<T, H>>,
+) -> ServerResponse
+where
+    T: AccessTokenProvider + 'static,
+    H: MessageHandler + 'static,
+{
+    server
+        .handle(req.method().as_str(), req.query_string(), &body)
+        .await
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::{test, App};
+
+    use super::*;
+    use crate::{
+        mp::{event::signature::Signature, EncodingMode, ServerConfig},
+        WxSdk,
+    };
+
+    struct Handler;
+    impl MessageHandler for Handler {}
+
+    #[actix_web::test]
+    async fn test_mp_service() {
+        let sdk = WxSdk::new_with_default_token_client("app_id", "app_secret");
+        let server = MpServer::new(
+            sdk.mp(ServerConfig::new("token", EncodingMode::Plain)),
+            Handler,
+        );
+        let app =
+            test::init_service(App::new().service(mp_service("/wechat", Arc::new(server)))).await;
+
+        let signature = Signature::generate_signature(vec![
+            "token".to_owned(),
+            "1409659813".to_owned(),
+            "1372623149".to_owned(),
+        ]);
+        let uri = format!(
+            "/wechat?signature={}&timestamp=1409659813&nonce=1372623149&echostr=hello",
+            signature
+        );
+        let req = test::TestRequest::get().uri(&uri).to_request();
+        let body = test::call_and_read_body(&app, req).await;
+        assert_eq!(&body[..], b"hello");
+
+        let req = test::TestRequest::post()
+            .uri("/wechat?signature=wrong&timestamp=1&nonce=1")
+            .set_payload("<xml></xml>")
+            .to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::FORBIDDEN);
+    }
+}
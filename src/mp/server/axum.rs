@@ -0,0 +1,114 @@
+//! axum 集成，提供挂载公众号消息推送地址的 [Router]
+//! ```no_run
+//! # use std::sync::Arc;
+//! # use wx_sdk::mp::{server::{axum::mp_router, MessageHandler, MpServer}, EncodingMode, ServerConfig};
+//! # use wx_sdk::WxSdk;
+//! struct Handler;
+//! impl MessageHandler for Handler {}
+//!
+//! let sdk = WxSdk::new_with_default_token_client("app_id", "app_secret");
+//! let server = MpServer::new(sdk.mp(ServerConfig::new("token", EncodingMode::Plain)), Handler);
+//! let app = axum::Router::new().merge(mp_router("/wechat", Arc::new(server)));
+//! ```
+use std::sync::Arc;
+
+use axum::{
+    extract::{RawQuery, State},
+    http::{Method, StatusCode},
+    response::{IntoResponse, Response},
+    routing::get,
+    Router,
+};
+
+use crate::access_token::AccessTokenProvider;
+
+use super::{MessageHandler, MpServer, ServerResponse};
+
+impl IntoResponse for ServerResponse {
+    fn into_response(self) -> Response {
+        let status = StatusCode::from_u16(self.status).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
+        (status, self.body).into_response()
+    }
+}
+
+/// 在 `path` 上挂载 `GET`（服务器地址验证）和 `POST`（消息推送）路由
+pub fn mp_router<T, H>(path: &str, server: Arc<MpServer<T, H>>) -> Router
+where
+    T: AccessTokenProvider + 'static,
+    H: MessageHandler + 'static,
+{
+    Router::new()
+        .route(path, get(handle::<T, H>).post(handle::<T, H>))
+        .with_state(server)
+}
+
+async fn handle<T, H>(
+    State(server): State<Arc<MpServer<T, H>>>,
+    method: Method,
+    RawQuery(query): RawQuery,
+    body: String,
+) -> ServerResponse
+where
+    T: AccessTokenProvider + 'static,
+    H: MessageHandler + 'static,
+{
+    server
+        .handle(method.as_str(), query.as_deref().unwrap_or_default(), &body)
+        .await
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::body::Body;
+    use axum::http::Request;
+    use tower::ServiceExt;
+
+    use super::*;
+    use crate::{
+        mp::{event::signature::Signature, EncodingMode, ServerConfig},
+        WxSdk,
+    };
+
+    struct Handler;
+    impl MessageHandler for Handler {}
+
+    #[tokio::test]
+    async fn test_mp_router() {
+        let sdk = WxSdk::new_with_default_token_client("app_id", "app_secret");
+        let server = MpServer::new(
+            sdk.mp(ServerConfig::new("token", EncodingMode::Plain)),
+            Handler,
+        );
+        let app = mp_router("/wechat", Arc::new(server));
+
+        let signature = Signature::generate_signature(vec![
+            "token".to_owned(),
+            "1409659813".to_owned(),
+            "1372623149".to_owned(),
+        ]);
+        let uri = format!(
+            "/wechat?signature={}&timestamp=1409659813&nonce=1372623149&echostr=hello",
+            signature
+        );
+        let res = app
+            .clone()
+            .oneshot(Request::get(uri).body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(res.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert_eq!(&body[..], b"hello");
+
+        let res = app
+            .oneshot(
+                Request::post("/wechat?signature=wrong&timestamp=1&nonce=1")
+                    .body(Body::from("<xml></xml>"))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::FORBIDDEN);
+    }
+}
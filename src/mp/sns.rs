@@ -32,7 +32,9 @@ impl<'a, T: AccessTokenProvider> SnsModule<'a, T> {
     /// 第二步，通过code换取access token
     pub async fn oauth_access_token(&self, code: String) -> SdkResult<GrantAccessToken> {
         let base_url = "https://api.weixin.qq.com/sns/oauth2/access_token";
-        let client = self.0.http_client.get(base_url);
+        let client = self
+            .0
+            .wx_request_without_token(reqwest::Method::GET, base_url)?;
 
         let app_id = self.0.app_id.clone();
         let app_secret = self.0.app_secret.clone();
@@ -49,7 +51,9 @@ impl<'a, T: AccessTokenProvider> SnsModule<'a, T> {
     /// 第三步：刷新access_token（如果需要）
     pub async fn oauth_refresh_token(&self, refresh_token: String) -> SdkResult<GrantAccessToken> {
         let base_url = "https://api.weixin.qq.com/sns/oauth2/refresh_token";
-        let client = self.0.http_client.get(base_url);
+        let client = self
+            .0
+            .wx_request_without_token(reqwest::Method::GET, base_url)?;
 
         let app_id = self.0.app_id.clone();
         let client = client.query(&[
@@ -70,7 +74,9 @@ impl<'a, T: AccessTokenProvider> SnsModule<'a, T> {
         lang: String,
     ) -> SdkResult<UserInfo> {
         let base_url = "https://api.weixin.qq.com/sns/userinfo";
-        let client = self.0.http_client.get(base_url);
+        let client = self
+            .0
+            .wx_request_without_token(reqwest::Method::GET, base_url)?;
 
         let client = client.query(&[
             ("access_token", access_token),
@@ -0,0 +1,963 @@
+//! 微信开放平台第三方平台（component），代公众号、小程序调用接口。
+//!
+//! 1. 在授权事件接收URL上用 [ComponentSdk::handle_event] 处理推送，保存每10分钟推送一次的 `component_verify_ticket`；
+//! 2. 用 [ComponentSdk::create_preauthcode] 和 [ComponentSdk::auth_url] 引导公众号或小程序管理员授权；
+//! 3. 收到授权成功事件后用 [ComponentSdk::query_auth] 换取并保存授权方的 `authorizer_access_token` 和 `authorizer_refresh_token`；
+//! 4. 用 [ComponentSdk::authorizer_sdk] 得到授权方的 [WxSdk]，它的 [AuthorizerTokenProvider] 会自动刷新令牌，
+//!    再通过 [WxSdk::mp] 或 [WxSdk::wxa] 代调用接口。
+//!
+//! 票据和令牌都保存在 [TokenStore] 中，多进程部署时请使用共享的存储，例如 [RedisTokenStore][crate::access_token::RedisTokenStore]。
+//! 详情请参考[微信官方文档](https://developers.weixin.qq.com/doc/oplatform/Third-party_Platforms/2.0/api/Before_Develop/creation.html)。
+//!
+//! Example
+//! ```no_run
+//! use std::collections::HashMap;
+//! use wx_sdk::open::{ComponentConfig, ComponentEvent, ComponentSdk};
+//! # async fn run(url_params: HashMap<String, String>, body: String) -> wx_sdk::SdkResult<()> {
+//! let component = ComponentSdk::new(ComponentConfig::new(
+//!     "component_appid",
+//!     "component_appsecret",
+//!     "token",
+//!     "encoding_aes_key",
+//! ));
+//! // the authorization event url, reply `success`
+//! if let ComponentEvent::Authorized(event) = component.handle_event(&url_params, &body).await? {
+//!     component.query_auth(&event.authorization_code).await?;
+//! }
+//! // call apis on behalf of the authorized official account
+//! let mp = component.authorizer_sdk("authorizer_appid")?.mp(component.server_config());
+//! let menu = mp.menu().get_current_selfmenu_info().await?;
+//! # Ok(())
+//! # }
+//! ```
+use std::{
+    collections::HashMap,
+    future::Future,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use async_trait::async_trait;
+use reqwest::Client;
+use roxmltree::Document;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use crate::{
+    access_token::{
+        AccessTokenProvider, MemoryTokenStore, TokenStore, DEFAULT_REFRESH_BEFORE,
+        REFRESH_LOCK_TTL, REFRESH_WAIT_INTERVAL,
+    },
+    error::{CommonResponse, SdkError},
+    mp::{
+        event::{
+            crypto::decrypt_message,
+            signature::Signature,
+            xmlutil::{get_number_from_root, get_text_from_root},
+        },
+        EncodingMode, ServerConfig,
+    },
+    utils::get_random_string,
+    wechat::{ApiCall, RetryPolicy},
+    wechat::{DomainPool, FailoverPolicy, API_BASE_URL},
+    AccessToken, SdkResult, TokenClient, WxSdk,
+};
+
+/// `component_verify_ticket` 有效期为12小时
+const VERIFY_TICKET_TTL: i32 = 12 * 3600;
+/// `authorizer_refresh_token` 不会过期，除非授权方取消授权或重新授权
+const REFRESH_TOKEN_TTL: i32 = 10 * 365 * 86400;
+
+/// 第三方平台配置
+#[derive(Debug, Clone)]
+pub struct ComponentConfig {
+    pub component_appid: String,
+    pub component_appsecret: String,
+    /// 消息校验Token
+    pub token: String,
+    /// 消息加解密Key
+    pub encoding_aes_key: String,
+}
+
+impl ComponentConfig {
+    pub fn new<S: AsRef<str>>(
+        component_appid: S,
+        component_appsecret: S,
+        token: S,
+        encoding_aes_key: S,
+    ) -> Self {
+        ComponentConfig {
+            component_appid: component_appid.as_ref().to_owned(),
+            component_appsecret: component_appsecret.as_ref().to_owned(),
+            token: token.as_ref().to_owned(),
+            encoding_aes_key: encoding_aes_key.as_ref().to_owned(),
+        }
+    }
+}
+
+/// 授权事件接收URL收到的推送
+#[derive(Debug, Clone, PartialEq)]
+pub enum ComponentEvent {
+    /// 验证票据，已由 [ComponentSdk::handle_event] 保存
+    VerifyTicket { ticket: String, create_time: i64 },
+    /// 授权成功
+    Authorized(AuthorizationEvent),
+    /// 授权更新
+    UpdateAuthorized(AuthorizationEvent),
+    /// 取消授权，授权方的令牌已由 [ComponentSdk::handle_event] 删除
+    Unauthorized {
+        authorizer_appid: String,
+        create_time: i64,
+    },
+    /// 其他 `InfoType` 的推送，例如快速注册小程序的结果
+    Unknown { info_type: String, xml: String },
+}
+
+/// 授权成功或授权更新通知
+#[derive(Debug, Clone, PartialEq)]
+pub struct AuthorizationEvent {
+    /// 公众号或小程序的 appid
+    pub authorizer_appid: String,
+    /// 授权码，可用于 [ComponentSdk::query_auth]
+    pub authorization_code: String,
+    /// 授权码过期时间，时间戳
+    pub authorization_code_expired_time: i64,
+    pub pre_auth_code: String,
+    pub create_time: i64,
+}
+
+/// 授权页面展示的授权方类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum AuthType {
+    /// 仅展示公众号
+    Mp = 1,
+    /// 仅展示小程序
+    Wxa = 2,
+    /// 公众号和小程序都展示
+    #[default]
+    All = 3,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct ComponentAccessToken {
+    component_access_token: String,
+    expires_in: i32,
+}
+
+/// 预授权码
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PreAuthCode {
+    pub pre_auth_code: String,
+    /// 有效期，单位：秒
+    pub expires_in: i32,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct QueryAuthResult {
+    authorization_info: AuthorizationInfo,
+}
+
+/// 授权信息
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuthorizationInfo {
+    /// 授权方 appid
+    pub authorizer_appid: String,
+    /// 授权方接口调用令牌
+    pub authorizer_access_token: String,
+    /// 有效期，单位：秒
+    pub expires_in: i32,
+    /// 刷新令牌，请妥善保存
+    pub authorizer_refresh_token: String,
+    /// 授权给第三方平台的权限集
+    #[serde(default)]
+    pub func_info: Vec<FuncInfo>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FuncInfo {
+    pub funcscope_category: FuncscopeCategory,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FuncscopeCategory {
+    /// 权限集 id
+    pub id: i32,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct AuthorizerToken {
+    authorizer_access_token: String,
+    expires_in: i32,
+    authorizer_refresh_token: String,
+}
+
+/// 第三方平台SDK
+///
+/// The tokens are saved in a [TokenStore], only one process refreshes a token at a time, like [TokenClient].
+/// The apis are called like [WxSdk], with its retry, failover, tracing and [ApiCall] hook.
+#[derive(Clone)]
+pub struct ComponentSdk {
+    config: Arc<ComponentConfig>,
+    /// Call the component apis, its token client isn't used.
+    api: WxSdk<TokenClient>,
+    store: Arc<dyn TokenStore>,
+    refresh_before: Duration,
+    lock_owner: String,
+    /// A lock for each token key, the authorizer token is refreshed with the component token.
+    refreshing: Arc<Mutex<HashMap<String, Arc<tokio::sync::Mutex<()>>>>>,
+}
+
+impl ComponentSdk {
+    /// Use a default http client and a [MemoryTokenStore].
+    pub fn new(config: ComponentConfig) -> Self {
+        let api = WxSdk::new_with_default_token_client(
+            &config.component_appid,
+            &config.component_appsecret,
+        );
+        ComponentSdk {
+            config: Arc::new(config),
+            api,
+            store: Arc::new(MemoryTokenStore::new()),
+            refresh_before: DEFAULT_REFRESH_BEFORE,
+            lock_owner: get_random_string(16),
+            refreshing: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Use a custom [reqwest::Client], it's shared by the authorizer sdks.
+    pub fn with_http_client(mut self, http_client: Client) -> Self {
+        self.api.http_client = http_client;
+        self
+    }
+
+    /// See [WxSdkBuilder::retry][crate::WxSdkBuilder::retry], it's used by the authorizer sdks too.
+    pub fn with_retry(mut self, policy: RetryPolicy) -> Self {
+        self.api.retry = Arc::new(policy);
+        self
+    }
+
+    /// See [WxSdkBuilder::on_api_call][crate::WxSdkBuilder::on_api_call], it receives the calls of the authorizer sdks too.
+    pub fn on_api_call<F: Fn(&ApiCall) + Send + Sync + 'static>(mut self, hook: F) -> Self {
+        self.api.api_hook = Some(Arc::new(hook));
+        self
+    }
+
+    /// Set how long before WeChat's expiry the tokens are refreshed, [DEFAULT_REFRESH_BEFORE] by default.
+    pub fn with_refresh_before(mut self, refresh_before: Duration) -> Self {
+        self.refresh_before = refresh_before;
+        self
+    }
+
+    /// Save the ticket and tokens in a custom [TokenStore].
+    pub fn with_token_store<S: TokenStore + 'static>(mut self, store: S) -> Self {
+        self.store = Arc::new(store);
+        self
+    }
+
+    /// Replace [API_BASE_URL], see [WxSdkBuilder::base_url][crate::WxSdkBuilder::base_url].
+    pub fn with_base_url<S: AsRef<str>>(self, base_url: S) -> Self {
+        self.with_failover(FailoverPolicy::single(base_url))
+    }
+
+    /// See [WxSdkBuilder::failover][crate::WxSdkBuilder::failover], it's used by the authorizer sdks too.
+    pub fn with_failover(mut self, policy: FailoverPolicy) -> Self {
+        self.api.domains = Arc::new(DomainPool::new(policy));
+        self
+    }
+
+    pub fn component_appid(&self) -> &str {
+        &self.config.component_appid
+    }
+
+    /// 代授权方接收消息的服务器配置，使用第三方平台的 Token 和加解密 Key
+    pub fn server_config(&self) -> ServerConfig {
+        ServerConfig::new(
+            self.config.token.clone(),
+            EncodingMode::Security(self.config.encoding_aes_key.clone()),
+        )
+    }
+
+    fn key(&self, name: &str) -> String {
+        format!("wx_sdk:{}:{}", name, self.config.component_appid)
+    }
+
+    fn authorizer_key(&self, name: &str, authorizer_appid: &str) -> String {
+        format!(
+            "wx_sdk:{}:{}:{}",
+            name, self.config.component_appid, authorizer_appid
+        )
+    }
+
+    /// 校验签名并解析授权事件接收URL收到的推送
+    ///
+    /// `url_params` 为推送 url 中的 `timestamp`、`nonce` 和 `msg_signature` 参数。
+    pub fn parse_event<S: AsRef<str>>(
+        &self,
+        url_params: &HashMap<String, String>,
+        body: S,
+    ) -> SdkResult<ComponentEvent> {
+        let get_param = |key: &str| {
+            url_params
+                .get(key)
+                .cloned()
+                .ok_or_else(|| SdkError::InvalidParams(key.to_owned()))
+        };
+        let doc = Document::parse(body.as_ref())?;
+        let encrypt_msg = get_text_from_root(&doc.root_element(), "Encrypt")?;
+        let check_sign = vec![
+            self.config.token.clone(),
+            get_param("timestamp")?,
+            get_param("nonce")?,
+            encrypt_msg.to_owned(),
+        ];
+        if !Signature::new(get_param("msg_signature")?, check_sign).is_ok() {
+            return Err(SdkError::InvalidSignature);
+        }
+        let (xml, app_id) = decrypt_message(encrypt_msg, &self.config.encoding_aes_key)?;
+        if app_id != self.config.component_appid {
+            return Err(SdkError::InvalidAppid);
+        }
+        parse_event_xml(&xml)
+    }
+
+    /// 解析推送，并保存验证票据，或删除取消授权的授权方的令牌
+    pub async fn handle_event<S: AsRef<str>>(
+        &self,
+        url_params: &HashMap<String, String>,
+        body: S,
+    ) -> SdkResult<ComponentEvent> {
+        let event = self.parse_event(url_params, body)?;
+        match event {
+            ComponentEvent::VerifyTicket { ref ticket, .. } => {
+                self.set_verify_ticket(ticket).await?;
+            }
+            ComponentEvent::Unauthorized {
+                ref authorizer_appid,
+                ..
+            } => {
+                let access_key = self.authorizer_key("authorizer_access_token", authorizer_appid);
+                let refresh_key = self.authorizer_key("authorizer_refresh_token", authorizer_appid);
+                self.store.remove(&access_key).await?;
+                self.store.remove(&refresh_key).await?;
+            }
+            _ => {}
+        }
+        Ok(event)
+    }
+
+    /// 保存验证票据
+    pub async fn set_verify_ticket<S: AsRef<str>>(&self, ticket: S) -> SdkResult<()> {
+        let ticket = AccessToken {
+            access_token: ticket.as_ref().to_owned(),
+            expires_in: VERIFY_TICKET_TTL,
+        };
+        self.store
+            .set(&self.key("component_verify_ticket"), &ticket)
+            .await
+    }
+
+    /// 最近一次推送的验证票据
+    pub async fn verify_ticket(&self) -> SdkResult<String> {
+        self.store
+            .get(&self.key("component_verify_ticket"))
+            .await?
+            .map(|t| t.access_token)
+            .ok_or_else(|| {
+                SdkError::InvalidParams("component_verify_ticket is not received".to_owned())
+            })
+    }
+
+    /// 获取令牌 `component_access_token`，过期前会自动刷新
+    pub async fn component_access_token(&self) -> SdkResult<AccessToken> {
+        let key = self.key("component_access_token");
+        self.get_or_refresh(&key, || self.refresh_component_access_token(&key))
+            .await
+    }
+
+    async fn refresh_component_access_token(&self, key: &str) -> SdkResult<AccessToken> {
+        let body = serde_json::json!({
+            "component_appid": &self.config.component_appid,
+            "component_appsecret": &self.config.component_appsecret,
+            "component_verify_ticket": self.verify_ticket().await?,
+        });
+        let res: CommonResponse<ComponentAccessToken> = self
+            .send("/cgi-bin/component/api_component_token", None, &body)
+            .await?;
+        let token = match res {
+            CommonResponse::Ok(token) => token,
+            CommonResponse::Err(e) => return Err(SdkError::AccessTokenError(e)),
+        };
+        let token = self.cached_token(token.component_access_token, token.expires_in);
+        self.store.set(key, &token).await?;
+        Ok(token)
+    }
+
+    /// 获取预授权码
+    pub async fn create_preauthcode(&self) -> SdkResult<PreAuthCode> {
+        let body = serde_json::json!({ "component_appid": &self.config.component_appid });
+        self.post("/cgi-bin/component/api_create_preauthcode", &body)
+            .await
+    }
+
+    /// 授权链接，需要在第三方平台登记的授权发起页域名下的网页中打开，授权后跳转到 `redirect_uri`
+    pub fn auth_url<S: AsRef<str>>(
+        &self,
+        pre_auth_code: S,
+        redirect_uri: S,
+        auth_type: AuthType,
+    ) -> String {
+        let auth_type = (auth_type as u8).to_string();
+        let query = [
+            ("component_appid", self.config.component_appid.as_str()),
+            ("pre_auth_code", pre_auth_code.as_ref()),
+            ("redirect_uri", redirect_uri.as_ref()),
+            ("auth_type", &auth_type),
+        ];
+        let query = url::form_urlencoded::Serializer::new(String::new())
+            .extend_pairs(query)
+            .finish();
+        format!(
+            "https://mp.weixin.qq.com/cgi-bin/componentloginpage?{}",
+            query
+        )
+    }
+
+    /// 使用授权码获取授权信息，并保存授权方的令牌
+    pub async fn query_auth<S: AsRef<str>>(
+        &self,
+        authorization_code: S,
+    ) -> SdkResult<AuthorizationInfo> {
+        let body = serde_json::json!({
+            "component_appid": &self.config.component_appid,
+            "authorization_code": authorization_code.as_ref(),
+        });
+        let res: QueryAuthResult = self
+            .post("/cgi-bin/component/api_query_auth", &body)
+            .await?;
+        let info = res.authorization_info;
+        self.save_authorizer_token(
+            &info.authorizer_appid,
+            &info.authorizer_access_token,
+            info.expires_in,
+            &info.authorizer_refresh_token,
+        )
+        .await?;
+        Ok(info)
+    }
+
+    /// 保存授权方的刷新令牌，例如从数据库中恢复
+    pub async fn set_authorizer_refresh_token<S: AsRef<str>>(
+        &self,
+        authorizer_appid: S,
+        refresh_token: S,
+    ) -> SdkResult<()> {
+        let key = self.authorizer_key("authorizer_refresh_token", authorizer_appid.as_ref());
+        let token = AccessToken {
+            access_token: refresh_token.as_ref().to_owned(),
+            expires_in: REFRESH_TOKEN_TTL,
+        };
+        self.store.set(&key, &token).await
+    }
+
+    /// 授权方的刷新令牌
+    pub async fn authorizer_refresh_token<S: AsRef<str>>(
+        &self,
+        authorizer_appid: S,
+    ) -> SdkResult<String> {
+        let authorizer_appid = authorizer_appid.as_ref();
+        let key = self.authorizer_key("authorizer_refresh_token", authorizer_appid);
+        self.store
+            .get(&key)
+            .await?
+            .map(|t| t.access_token)
+            .ok_or_else(|| {
+                SdkError::InvalidParams(format!(
+                    "authorizer_refresh_token of {} is not found",
+                    authorizer_appid
+                ))
+            })
+    }
+
+    /// 使用刷新令牌获取新的授权方令牌，并保存
+    pub async fn refresh_authorizer_token<S: AsRef<str>>(
+        &self,
+        authorizer_appid: S,
+    ) -> SdkResult<AccessToken> {
+        let authorizer_appid = authorizer_appid.as_ref();
+        let body = serde_json::json!({
+            "component_appid": &self.config.component_appid,
+            "authorizer_appid": authorizer_appid,
+            "authorizer_refresh_token": self.authorizer_refresh_token(authorizer_appid).await?,
+        });
+        let res: AuthorizerToken = self
+            .post("/cgi-bin/component/api_authorizer_token", &body)
+            .await
+            .map_err(|e| match e {
+                SdkError::WxApiError(e) => SdkError::AccessTokenError(e),
+                e => e,
+            })?;
+        self.save_authorizer_token(
+            authorizer_appid,
+            &res.authorizer_access_token,
+            res.expires_in,
+            &res.authorizer_refresh_token,
+        )
+        .await
+    }
+
+    async fn save_authorizer_token(
+        &self,
+        authorizer_appid: &str,
+        access_token: &str,
+        expires_in: i32,
+        refresh_token: &str,
+    ) -> SdkResult<AccessToken> {
+        self.set_authorizer_refresh_token(authorizer_appid, refresh_token)
+            .await?;
+        let token = self.cached_token(access_token.to_owned(), expires_in);
+        let key = self.authorizer_key("authorizer_access_token", authorizer_appid);
+        self.store.set(&key, &token).await?;
+        Ok(token)
+    }
+
+    /// 授权方的令牌提供者
+    pub fn authorizer<S: AsRef<str>>(&self, authorizer_appid: S) -> AuthorizerTokenProvider {
+        AuthorizerTokenProvider {
+            component: self.clone(),
+            authorizer_appid: authorizer_appid.as_ref().to_owned(),
+        }
+    }
+
+    /// 代授权方调用接口的 [WxSdk]，与第三方平台共用 http client、api 域名、重试和调用回调配置
+    ///
+    /// 授权方没有 AppSecret，不能调用需要 AppSecret 的接口，例如 `code_to_session`。
+    pub fn authorizer_sdk<S: AsRef<str>>(
+        &self,
+        authorizer_appid: S,
+    ) -> SdkResult<WxSdk<AuthorizerTokenProvider>> {
+        let authorizer_appid = authorizer_appid.as_ref();
+        let mut sdk = self
+            .api
+            .with_token_client(authorizer_appid, self.authorizer(authorizer_appid));
+        sdk.component_app_id = Some(self.config.component_appid.clone());
+        Ok(sdk)
+    }
+
+    /// 调用带 `component_access_token` 的接口，令牌失效时刷新后重试一次
+    async fn post<D: Serialize, R: DeserializeOwned>(&self, path: &str, body: &D) -> SdkResult<R> {
+        let token = self.component_access_token().await?;
+        let res: CommonResponse<R> = self.send(path, Some(&token.access_token), body).await?;
+        match res {
+            CommonResponse::Err(e) if e.code().is_token_error() => {
+                let key = self.key("component_access_token");
+                self.invalidate(&key, &token.access_token).await?;
+                let token = self.component_access_token().await?;
+                let res: CommonResponse<R> =
+                    self.send(path, Some(&token.access_token), body).await?;
+                res.into()
+            }
+            res => res.into(),
+        }
+    }
+
+    async fn send<D: Serialize, R: DeserializeOwned>(
+        &self,
+        path: &str,
+        component_access_token: Option<&str>,
+        body: &D,
+    ) -> SdkResult<R> {
+        let url = format!("{}{}", API_BASE_URL, path);
+        // the authorization code is single use, the apis are only retried when the connection failed
+        let mut request = self
+            .api
+            .wx_request_without_token(reqwest::Method::POST, &url)?
+            .json(body)
+            .idempotent(false);
+        if let Some(token) = component_access_token {
+            request = request.query(&[("component_access_token", token)]);
+        }
+        let res = request.send().await?.json().await?;
+        Ok(res)
+    }
+
+    fn refresh_lock(&self, key: &str) -> Arc<tokio::sync::Mutex<()>> {
+        let mut locks = self.refreshing.lock().unwrap();
+        locks.entry(key.to_owned()).or_default().clone()
+    }
+
+    /// The cached token expires `refresh_before` earlier than WeChat's.
+    fn cached_token(&self, access_token: String, expires_in: i32) -> AccessToken {
+        let refresh_before = self.refresh_before.as_secs() as i32;
+        AccessToken {
+            access_token,
+            expires_in: (expires_in - refresh_before).max(0),
+        }
+    }
+
+    /// Remove the cached token if it's the same.
+    async fn invalidate(&self, key: &str, access_token: &str) -> SdkResult<()> {
+        let lock = self.refresh_lock(key);
+        let _refreshing = lock.lock().await;
+        match self.store.get(key).await? {
+            Some(token) if token.access_token == access_token => self.store.remove(key).await,
+            _ => Ok(()),
+        }
+    }
+
+    /// Get the token from store, or refresh it by the lock holder.
+    async fn get_or_refresh<F, Fut>(&self, key: &str, refresh: F) -> SdkResult<AccessToken>
+    where
+        F: Fn() -> Fut,
+        Fut: Future<Output = SdkResult<AccessToken>>,
+    {
+        if let Some(token) = self.store.get(key).await? {
+            return Ok(token);
+        }
+        let lock = self.refresh_lock(key);
+        let _refreshing = lock.lock().await;
+        let lock_key = format!("{}:lock", key);
+        let deadline = Instant::now() + REFRESH_LOCK_TTL;
+        loop {
+            if let Some(token) = self.store.get(key).await? {
+                return Ok(token);
+            }
+            if self
+                .store
+                .try_lock(&lock_key, &self.lock_owner, REFRESH_LOCK_TTL)
+                .await?
+            {
+                let res = match self.store.get(key).await {
+                    Ok(Some(token)) => Ok(token),
+                    Ok(None) => refresh().await,
+                    Err(e) => Err(e),
+                };
+                let unlocked = self.store.unlock(&lock_key, &self.lock_owner).await;
+                let token = res?;
+                unlocked?;
+                return Ok(token);
+            }
+            // another process is refreshing the token, wait for it
+            if Instant::now() >= deadline {
+                return refresh().await;
+            }
+            tokio::time::sleep(REFRESH_WAIT_INTERVAL).await;
+        }
+    }
+}
+
+fn parse_event_xml(xml: &str) -> SdkResult<ComponentEvent> {
+    let doc = Document::parse(xml)?;
+    let root = doc.root_element();
+    let text = |tag: &str| get_text_from_root(&root, tag).map(str::to_owned);
+    let create_time = get_number_from_root(&root, "CreateTime")?;
+    let info_type = text("InfoType")?;
+    let authorization = || -> SdkResult<AuthorizationEvent> {
+        Ok(AuthorizationEvent {
+            authorizer_appid: text("AuthorizerAppid")?,
+            authorization_code: text("AuthorizationCode")?,
+            authorization_code_expired_time: get_number_from_root(
+                &root,
+                "AuthorizationCodeExpiredTime",
+            )?,
+            pre_auth_code: text("PreAuthCode")?,
+            create_time,
+        })
+    };
+    Ok(match info_type.as_str() {
+        "component_verify_ticket" => ComponentEvent::VerifyTicket {
+            ticket: text("ComponentVerifyTicket")?,
+            create_time,
+        },
+        "authorized" => ComponentEvent::Authorized(authorization()?),
+        "updateauthorized" => ComponentEvent::UpdateAuthorized(authorization()?),
+        "unauthorized" => ComponentEvent::Unauthorized {
+            authorizer_appid: text("AuthorizerAppid")?,
+            create_time,
+        },
+        _ => ComponentEvent::Unknown {
+            info_type,
+            xml: xml.to_owned(),
+        },
+    })
+}
+
+/// 授权方令牌，实现 [AccessTokenProvider]，使用保存的刷新令牌自动刷新
+#[derive(Clone)]
+pub struct AuthorizerTokenProvider {
+    component: ComponentSdk,
+    authorizer_appid: String,
+}
+
+impl AuthorizerTokenProvider {
+    pub fn authorizer_appid(&self) -> &str {
+        &self.authorizer_appid
+    }
+
+    fn token_key(&self) -> String {
+        self.component
+            .authorizer_key("authorizer_access_token", &self.authorizer_appid)
+    }
+}
+
+#[async_trait]
+impl AccessTokenProvider for AuthorizerTokenProvider {
+    async fn get_access_token(&self) -> SdkResult<AccessToken> {
+        let key = self.token_key();
+        self.component
+            .get_or_refresh(&key, || {
+                self.component
+                    .refresh_authorizer_token(&self.authorizer_appid)
+            })
+            .await
+    }
+
+    async fn invalidate(&self, access_token: &str) -> SdkResult<()> {
+        self.component
+            .invalidate(&self.token_key(), access_token)
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mp::event::crypto::encrypt_message;
+
+    const AES_KEY: &str = "jWmYm7qr5nMoAUwZRjGtBxmz3KA1tkAj3ykkR6q2B2C";
+
+    fn component() -> ComponentSdk {
+        ComponentSdk::new(ComponentConfig::new(
+            "wx_component",
+            "component_secret",
+            "token",
+            AES_KEY,
+        ))
+    }
+
+    /// Encrypt a push like WeChat does.
+    fn push(xml: &str, app_id: &str) -> (HashMap<String, String>, String) {
+        let encrypt = encrypt_message(xml, AES_KEY, app_id).unwrap();
+        let signature = Signature::generate_signature(vec![
+            "token".to_owned(),
+            "1413192605".to_owned(),
+            "nonce".to_owned(),
+            encrypt.clone(),
+        ]);
+        let url_params = [
+            ("timestamp", "1413192605"),
+            ("nonce", "nonce"),
+            ("msg_signature", signature.as_str()),
+        ]
+        .iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect();
+        let body = format!(
+            "<xml><AppId><![CDATA[{}]]></AppId><Encrypt><![CDATA[{}]]></Encrypt></xml>",
+            app_id, encrypt
+        );
+        (url_params, body)
+    }
+
+    #[tokio::test]
+    async fn test_handle_event() -> SdkResult<()> {
+        let component = component();
+        assert!(component.verify_ticket().await.is_err());
+
+        let xml = "<xml><AppId>wx_component</AppId><CreateTime>1413192605</CreateTime><InfoType>component_verify_ticket</InfoType><ComponentVerifyTicket>TICKET</ComponentVerifyTicket></xml>";
+        let (url_params, body) = push(xml, "wx_component");
+        let event = component.handle_event(&url_params, &body).await?;
+        assert_eq!(
+            event,
+            ComponentEvent::VerifyTicket {
+                ticket: "TICKET".to_owned(),
+                create_time: 1413192605
+            }
+        );
+        assert_eq!(component.verify_ticket().await?, "TICKET");
+
+        let xml = "<xml><AppId>wx_component</AppId><CreateTime>1413192760</CreateTime><InfoType>authorized</InfoType><AuthorizerAppid>wx_authorizer</AuthorizerAppid><AuthorizationCode>CODE</AuthorizationCode><AuthorizationCodeExpiredTime>1413196360</AuthorizationCodeExpiredTime><PreAuthCode>PRE</PreAuthCode></xml>";
+        let (url_params, body) = push(xml, "wx_component");
+        let event = component.parse_event(&url_params, &body)?;
+        assert!(matches!(event, ComponentEvent::Authorized(e) if e.authorization_code == "CODE"));
+
+        component
+            .set_authorizer_refresh_token("wx_authorizer", "REFRESH")
+            .await?;
+        let xml = "<xml><AppId>wx_component</AppId><CreateTime>1413192760</CreateTime><InfoType>unauthorized</InfoType><AuthorizerAppid>wx_authorizer</AuthorizerAppid></xml>";
+        let (url_params, body) = push(xml, "wx_component");
+        component.handle_event(&url_params, &body).await?;
+        assert!(component
+            .authorizer_refresh_token("wx_authorizer")
+            .await
+            .is_err());
+
+        let (url_params, body) = push(xml, "wx_other");
+        assert!(matches!(
+            component.parse_event(&url_params, &body),
+            Err(SdkError::InvalidAppid)
+        ));
+        let (mut url_params, body) = push(xml, "wx_component");
+        url_params.insert("nonce".to_owned(), "other".to_owned());
+        assert!(matches!(
+            component.parse_event(&url_params, &body),
+            Err(SdkError::InvalidSignature)
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_auth_url() {
+        let url = component().auth_url("PRE", "https://example.com/auth?a=1", AuthType::Mp);
+        assert_eq!(url, "https://mp.weixin.qq.com/cgi-bin/componentloginpage?component_appid=wx_component&pre_auth_code=PRE&redirect_uri=https%3A%2F%2Fexample.com%2Fauth%3Fa%3D1&auth_type=1");
+    }
+
+    #[test]
+    fn test_authorizer_msg() -> SdkResult<()> {
+        let component = component();
+        let mp = component
+            .authorizer_sdk("wx_authorizer")?
+            .mp(component.server_config());
+        let xml = "<xml><ToUserName><![CDATA[gh_authorizer]]></ToUserName><FromUserName><![CDATA[openid]]></FromUserName><CreateTime>1348831860</CreateTime><MsgType><![CDATA[text]]></MsgType><Content><![CDATA[hi]]></Content><MsgId>1234567890123456</MsgId></xml>";
+        // the messages of the authorizer are encrypted with the component appid
+        let (url_params, body) = push(xml, "wx_component");
+        mp.parse_received_msg(&body, Some(url_params))?;
+        Ok(())
+    }
+
+    #[cfg(feature = "testing")]
+    #[tokio::test]
+    async fn test_authorizer_token() -> SdkResult<()> {
+        use crate::testing::{MockResponse, MockServer};
+
+        let server = MockServer::start().await?;
+        server.mock(
+            "/cgi-bin/component/api_component_token",
+            MockResponse::json(&serde_json::json!({
+                "component_access_token": "COMPONENT_TOKEN",
+                "expires_in": 7200
+            })),
+        );
+        server.mock(
+            "/cgi-bin/component/api_query_auth",
+            MockResponse::json(&serde_json::json!({
+                "authorization_info": {
+                    "authorizer_appid": "wx_authorizer",
+                    "authorizer_access_token": "AUTHORIZER_TOKEN",
+                    "expires_in": 7200,
+                    "authorizer_refresh_token": "REFRESH",
+                    "func_info": [{ "funcscope_category": { "id": 1 } }]
+                }
+            })),
+        );
+        server.mock(
+            "/cgi-bin/component/api_authorizer_token",
+            MockResponse::json(&serde_json::json!({
+                "authorizer_access_token": "AUTHORIZER_TOKEN_2",
+                "expires_in": 7200,
+                "authorizer_refresh_token": "REFRESH_2"
+            })),
+        );
+        server.mock(
+            "/cgi-bin/component/api_create_preauthcode",
+            MockResponse::error(42001, "component_access_token expired"),
+        );
+        let component = component().with_base_url(server.base_url());
+        component.set_verify_ticket("TICKET").await?;
+
+        let info = component.query_auth("CODE").await?;
+        assert_eq!(info.func_info[0].funcscope_category.id, 1);
+        let requests = server.requests_to("/cgi-bin/component/api_component_token");
+        assert_eq!(requests.len(), 1);
+        let body: serde_json::Value = requests[0].json()?;
+        assert_eq!(body["component_verify_ticket"], "TICKET");
+        let requests = server.requests_to("/cgi-bin/component/api_query_auth");
+        assert_eq!(
+            requests[0]
+                .query
+                .get("component_access_token")
+                .map(String::as_str),
+            Some("COMPONENT_TOKEN")
+        );
+
+        // the expired component_access_token is refreshed once
+        assert!(component.create_preauthcode().await.is_err());
+        assert_eq!(
+            server
+                .requests_to("/cgi-bin/component/api_component_token")
+                .len(),
+            2
+        );
+
+        let provider = component.authorizer("wx_authorizer");
+        assert_eq!(
+            provider.get_access_token().await?.access_token,
+            "AUTHORIZER_TOKEN"
+        );
+        provider.invalidate("AUTHORIZER_TOKEN").await?;
+        assert_eq!(
+            provider.get_access_token().await?.access_token,
+            "AUTHORIZER_TOKEN_2"
+        );
+        assert_eq!(
+            component.authorizer_refresh_token("wx_authorizer").await?,
+            "REFRESH_2"
+        );
+        let body: serde_json::Value =
+            server.requests_to("/cgi-bin/component/api_authorizer_token")[0].json()?;
+        assert_eq!(body["authorizer_refresh_token"], "REFRESH");
+        Ok(())
+    }
+
+    #[cfg(feature = "testing")]
+    #[tokio::test]
+    async fn test_authorizer_cold_start() -> SdkResult<()> {
+        use crate::testing::{MockResponse, MockServer};
+
+        let server = MockServer::start().await?;
+        server.mock(
+            "/cgi-bin/component/api_component_token",
+            MockResponse::json(&serde_json::json!({
+                "component_access_token": "COMPONENT_TOKEN",
+                "expires_in": 7200
+            })),
+        );
+        server.mock(
+            "/cgi-bin/component/api_authorizer_token",
+            MockResponse::json(&serde_json::json!({
+                "authorizer_access_token": "AUTHORIZER_TOKEN",
+                "expires_in": 7200,
+                "authorizer_refresh_token": "REFRESH_2"
+            })),
+        );
+        let calls = Arc::new(Mutex::new(Vec::new()));
+        let recorded = calls.clone();
+        let component = component()
+            .with_base_url(server.base_url())
+            .with_refresh_before(Duration::from_secs(60))
+            .on_api_call(move |call| recorded.lock().unwrap().push(call.path.clone()));
+        // only the ticket and the refresh token are saved, e.g. after a restart
+        component.set_verify_ticket("TICKET").await?;
+        component
+            .set_authorizer_refresh_token("wx_authorizer", "REFRESH")
+            .await?;
+
+        // the component token is refreshed while refreshing the authorizer token
+        let token = tokio::time::timeout(
+            Duration::from_secs(5),
+            component.authorizer("wx_authorizer").get_access_token(),
+        )
+        .await
+        .expect("refreshing the authorizer token deadlocked")?;
+        assert_eq!(token.access_token, "AUTHORIZER_TOKEN");
+        assert_eq!(token.expires_in, 7140);
+        let component_token = component.component_access_token().await?;
+        assert!(component_token.expires_in <= 7140 && component_token.expires_in > 7130);
+        assert_eq!(
+            *calls.lock().unwrap(),
+            vec![
+                "/cgi-bin/component/api_component_token",
+                "/cgi-bin/component/api_authorizer_token"
+            ]
+        );
+        Ok(())
+    }
+}
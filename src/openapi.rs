@@ -0,0 +1,150 @@
+//! openApi 管理，公众号和小程序通用。
+//!
+//! 查询接口调用额度、根据 rid 查询请求信息、清空接口调用次数，
+//! 详情请参考[微信官方文档](https://developers.weixin.qq.com/doc/offiaccount/openApi/get_api_quota.html)。
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    access_token::AccessTokenProvider,
+    error::{CommonError, CommonResponse},
+    wechat::WxApiRequestBuilder,
+    SdkResult, WxSdk,
+};
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ApiQuota {
+    /// quota详情
+    pub quota: Quota,
+    /// 普通调用频率限制
+    pub rate_limit: Option<QuotaRateLimit>,
+    /// 代调用频率限制
+    pub component_rate_limit: Option<QuotaRateLimit>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Quota {
+    /// 当天该账号可调用该接口的次数
+    pub daily_limit: i64,
+    /// 当天已经调用的次数
+    pub used: i64,
+    /// 当天剩余调用次数
+    pub remain: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct QuotaRateLimit {
+    /// 周期内可调用数量，单位 次
+    pub call_count: i64,
+    /// 更新周期，单位 秒
+    pub refresh_second: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RidInfo {
+    /// 该 rid 对应的请求详情
+    pub request: RidRequest,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RidRequest {
+    /// 发起请求的时间戳
+    pub invoke_time: i64,
+    /// 请求毫秒级耗时
+    pub cost_in_ms: i64,
+    /// 请求的URL参数
+    pub request_url: String,
+    /// post请求的请求参数
+    pub request_body: String,
+    /// 接口请求返回参数
+    pub response_body: String,
+    /// 接口请求的客户端ip
+    pub client_ip: String,
+}
+
+pub struct OpenApiModule<'a, T: AccessTokenProvider>(pub(crate) &'a WxSdk<T>);
+
+impl<'a, T: AccessTokenProvider> OpenApiModule<'a, T> {
+    /// 接口限额清零
+    ///
+    /// 每个帐号每月共10次清零操作机会，清零生效一次即用掉一次机会（10次包括了平台上的清零和调用接口API的清零）。
+    pub async fn clear_quota(&self) -> SdkResult<()> {
+        let url = "https://api.weixin.qq.com/cgi-bin/clear_quota";
+        let res: CommonError = self
+            .0
+            .wx_post(url)
+            .await?
+            .json(&serde_json::json!({ "appid": self.0.app_id }))
+            .send()
+            .await?
+            .json()
+            .await?;
+        res.into()
+    }
+
+    /// 使用AppSecret重置 API 调用次数
+    ///
+    /// 不需要 access_token，在 access_token 的获取次数也用完时可以使用。
+    pub async fn clear_quota_by_app_secret(&self) -> SdkResult<()> {
+        let url = "https://api.weixin.qq.com/cgi-bin/clear_quota/v2";
+        let res: CommonError = self
+            .0
+            .wx_request_without_token(reqwest::Method::POST, url)?
+            .query(&[("appid", &self.0.app_id), ("appsecret", &self.0.app_secret)])
+            .send()
+            .await?
+            .json()
+            .await?;
+        res.into()
+    }
+
+    /// 查询API调用额度
+    ///
+    /// `cgi_path` 为 api 的请求地址，例如 `/cgi-bin/message/custom/send`，不要带 url 参数。
+    pub async fn get_api_quota<S: AsRef<str>>(&self, cgi_path: S) -> SdkResult<ApiQuota> {
+        let url = "https://api.weixin.qq.com/cgi-bin/openapi/quota/get";
+        let res: CommonResponse<ApiQuota> = self
+            .0
+            .wx_post(url)
+            .await?
+            .json(&serde_json::json!({ "cgi_path": cgi_path.as_ref() }))
+            .send()
+            .await?
+            .json()
+            .await?;
+        res.into()
+    }
+
+    /// 查询rid信息
+    ///
+    /// 调用接口报错返回的 errmsg 中带有 rid，可以通过 [CommonError::rid] 获取，rid 的有效期只有7天。
+    pub async fn get_rid_info<S: AsRef<str>>(&self, rid: S) -> SdkResult<RidInfo> {
+        let url = "https://api.weixin.qq.com/cgi-bin/openapi/rid/get";
+        let res: CommonResponse<RidInfo> = self
+            .0
+            .wx_post(url)
+            .await?
+            .json(&serde_json::json!({ "rid": rid.as_ref() }))
+            .send()
+            .await?
+            .json()
+            .await?;
+        res.into()
+    }
+}
+
+#[test]
+fn test_parse_api_quota() {
+    let input = r#"{
+        "errcode": 0,
+        "errmsg": "ok",
+        "quota": { "daily_limit": 0, "used": 0, "remain": 0 },
+        "rate_limit": { "call_count": 0, "refresh_second": 0 },
+        "component_rate_limit": { "call_count": 0, "refresh_second": 0 }
+    }"#;
+    let res: CommonResponse<ApiQuota> = serde_json::from_str(input).unwrap();
+    assert!(matches!(res, CommonResponse::Ok(q) if q.rate_limit.is_some()));
+
+    let input = r#"{"errcode":76022,"errmsg":"could not find this cgi_path rid: 61725984-6126f6f9-040f19c4"}"#;
+    let res: CommonResponse<ApiQuota> = serde_json::from_str(input).unwrap();
+    assert!(matches!(res, CommonResponse::Err(e) if e.rid() == Some("61725984-6126f6f9-040f19c4")));
+}
@@ -1,27 +1,428 @@
-use std::path::Path;
+//! This module define the api set of [wechat pay v3](https://pay.weixin.qq.com/wiki/doc/apiv3/index.shtml).
+//!
+//! Every request is signed with the merchant private key by the `WECHATPAY2-SHA256-RSA2048` schema.
+//!
+//! Example
+//! ```ignore
+//! use wx_sdk::pay::PaySdkConfig;
+//!
+//! let config = PaySdkConfig::new("mch_id", "serial_number", "apiv3_key", "apiclient_key.pem")?;
+//! let paysdk = sdk.pay(config);
+//! let prepay = paysdk.transactions().jsapi(&order).await?;
+//! ```
 
-#[derive(Clone)]
-pub struct PaySdk {
-    pub(crate) config: PaySdkConfig,
-}
+use std::{collections::HashMap, time::SystemTime};
+
+use reqwest::{header, Method};
+use rsa::RsaPrivateKey;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use crate::{
+    access_token::AccessTokenProvider,
+    error::{PayError, SdkError},
+    utils::get_random_string,
+    SdkResult, WxSdk,
+};
 
+use self::{
+    certificate::{CertificateList, CertificateManager, PlatformCertificate},
+    notify::Notification,
+    refund::RefundModule,
+    transactions::TransactionsModule,
+};
+
+pub mod certificate;
+pub mod crypto;
+pub mod notify;
+pub mod refund;
+pub mod sign;
+pub mod transactions;
+
+const BASE_URL: &str = "https://api.mch.weixin.qq.com";
+
+/// The merchant configuration of wechat pay.
 #[derive(Clone)]
 pub struct PaySdkConfig {
-    mch_id: String,
-    mch_certificate_serial_number: String,
-    mch_apiv3_key: String,
-    mch_private_key: String,
+    pub mch_id: String,
+    pub mch_certificate_serial_number: String,
+    pub(crate) mch_apiv3_key: String,
+    pub(crate) mch_private_key: RsaPrivateKey,
+    pub(crate) platform_certificates: HashMap<String, PlatformCertificate>,
 }
 
 impl PaySdkConfig {
+    /// Construct the config with the merchant private key file path, eg: `apiclient_key.pem`.
     pub fn new<S: Into<String>>(
         mch_id: S,
         mch_certificate_serial_number: S,
         mch_apiv3_key: S,
         mch_private_path: S,
-    ) {
-        todo!()
+    ) -> SdkResult<Self> {
+        let pem = std::fs::read_to_string(mch_private_path.into())?;
+        Self::from_pem(
+            mch_id.into(),
+            mch_certificate_serial_number.into(),
+            mch_apiv3_key.into(),
+            pem,
+        )
+    }
+
+    /// Construct the config with the content of merchant private key file.
+    pub fn from_pem<S: Into<String>>(
+        mch_id: S,
+        mch_certificate_serial_number: S,
+        mch_apiv3_key: S,
+        mch_private_pem: S,
+    ) -> SdkResult<Self> {
+        let mch_private_key = sign::load_private_key(mch_private_pem.into())?;
+        Ok(PaySdkConfig {
+            mch_id: mch_id.into(),
+            mch_certificate_serial_number: mch_certificate_serial_number.into(),
+            mch_apiv3_key: mch_apiv3_key.into(),
+            mch_private_key,
+            platform_certificates: HashMap::new(),
+        })
+    }
+
+    /// Add a wechat pay platform certificate(PEM) for verifying the notifications.
+    pub fn add_platform_certificate<S: AsRef<str>>(&mut self, pem: S) -> SdkResult<()> {
+        let certificate = PlatformCertificate::from_pem(pem)?;
+        self.platform_certificates
+            .insert(certificate.serial_no.clone(), certificate);
+        Ok(())
+    }
+
+    /// Compute the `Authorization` header value, `url` is the absolute path with query.
+    pub fn authorization(&self, method: &str, url: &str, body: &str) -> SdkResult<String> {
+        let timestamp = get_timestamp().to_string();
+        let nonce_str = get_random_string(32);
+        let message = sign::build_message(&[method, url, &timestamp, &nonce_str, body]);
+        sign::authorization(
+            &self.mch_private_key,
+            &self.mch_id,
+            &self.mch_certificate_serial_number,
+            &timestamp,
+            &nonce_str,
+            &message,
+        )
+    }
+}
+
+/// The params for `wx.requestPayment` of JSAPI or mini program.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JsapiPayParams {
+    pub app_id: String,
+    pub time_stamp: String,
+    pub nonce_str: String,
+    pub package: String,
+    pub sign_type: String,
+    pub pay_sign: String,
+}
+
+/// The params for calling wechat pay in APP.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AppPayParams {
+    pub appid: String,
+    pub partnerid: String,
+    pub prepayid: String,
+    pub package: String,
+    pub noncestr: String,
+    pub timestamp: String,
+    pub sign: String,
+}
+
+/// 微信支付接口SDK
+#[derive(Clone)]
+pub struct PaySdk<T: AccessTokenProvider> {
+    pub(crate) sdk: WxSdk<T>,
+    pub(crate) config: PaySdkConfig,
+    pub(crate) certificates: CertificateManager,
+}
+
+impl<T: AccessTokenProvider> PaySdk<T> {
+    pub(crate) fn new(sdk: WxSdk<T>, config: PaySdkConfig) -> Self {
+        let certificates = CertificateManager::new(config.platform_certificates.values().cloned());
+        PaySdk {
+            sdk,
+            config,
+            certificates,
+        }
+    }
+
+    /// Transactions module 下单、查单、关单
+    pub fn transactions(&self) -> TransactionsModule<'_, T> {
+        TransactionsModule(self)
+    }
+
+    /// Refund module 退款
+    pub fn refund(&self) -> RefundModule<'_, T> {
+        RefundModule(self)
+    }
+
+    /// 生成 JSAPI 或小程序调起支付的参数
+    pub fn jsapi_pay_params<S: AsRef<str>>(&self, prepay_id: S) -> SdkResult<JsapiPayParams> {
+        let app_id = self.sdk.app_id.clone();
+        let time_stamp = get_timestamp().to_string();
+        let nonce_str = get_random_string(32);
+        let package = format!("prepay_id={}", prepay_id.as_ref());
+        let message = sign::build_message(&[&app_id, &time_stamp, &nonce_str, &package]);
+        let pay_sign = sign::sign(&self.config.mch_private_key, &message)?;
+        Ok(JsapiPayParams {
+            app_id,
+            time_stamp,
+            nonce_str,
+            package,
+            sign_type: "RSA".to_owned(),
+            pay_sign,
+        })
+    }
+
+    /// 生成 APP 调起支付的参数
+    pub fn app_pay_params<S: AsRef<str>>(&self, prepay_id: S) -> SdkResult<AppPayParams> {
+        let appid = self.sdk.app_id.clone();
+        let timestamp = get_timestamp().to_string();
+        let noncestr = get_random_string(32);
+        let prepayid = prepay_id.as_ref().to_owned();
+        let message = sign::build_message(&[&appid, &timestamp, &noncestr, &prepayid]);
+        let sign = sign::sign(&self.config.mch_private_key, &message)?;
+        Ok(AppPayParams {
+            appid,
+            partnerid: self.config.mch_id.clone(),
+            prepayid,
+            package: "Sign=WXPay".to_owned(),
+            noncestr,
+            timestamp,
+            sign,
+        })
+    }
+
+    /// 解析微信支付推送的支付通知或退款通知
+    ///
+    /// `headers` should contains `Wechatpay-Serial`, `Wechatpay-Signature`, `Wechatpay-Timestamp` and `Wechatpay-Nonce`.
+    pub async fn parse_notification<S: AsRef<str>>(
+        &self,
+        body: S,
+        headers: &HashMap<String, String>,
+    ) -> SdkResult<Notification> {
+        let body = body.as_ref();
+        self.verify_signature(headers, body).await?;
+        notify::decrypt_notification(body, &self.config.mch_apiv3_key)
+    }
+
+    /// 下载平台证书并更新缓存
+    ///
+    /// 下载的证书用于验证本次应答的签名，验证通过后才会加入缓存。
+    pub async fn refresh_certificates(&self) -> SdkResult<Vec<PlatformCertificate>> {
+        let (headers, body) = self
+            .request::<()>(Method::GET, "/v3/certificates", None)
+            .await?;
+        let list = serde_json::from_str::<CertificateList>(&body)?;
+        let certificates = list
+            .data
+            .iter()
+            .map(|c| c.decrypt(&self.config.mch_apiv3_key))
+            .collect::<SdkResult<Vec<PlatformCertificate>>>()?;
+
+        let serial = notify::get_serial(&headers)?;
+        let certificate = certificates
+            .iter()
+            .find(|c| c.serial_no == serial)
+            .ok_or_else(|| {
+                SdkError::InvalidParams(format!("unknown platform certificate serial {}", serial))
+            })?;
+        notify::verify_signature(certificate, &headers, &body)?;
+
+        for c in certificates.iter() {
+            self.certificates.insert(c.clone()).await;
+        }
+        Ok(certificates)
+    }
+
+    /// 根据序列号获取平台证书，缓存中没有时下载平台证书
+    ///
+    /// 下载受 [CERTIFICATE_REFRESH_INTERVAL][certificate::CERTIFICATE_REFRESH_INTERVAL] 限制，
+    /// 下载后仍未找到的序列号会被记住，不再触发下载。
+    pub async fn get_certificate(&self, serial_no: &str) -> SdkResult<PlatformCertificate> {
+        if let Some(c) = self.certificates.get(serial_no).await {
+            return Ok(c);
+        }
+        let unknown = || {
+            SdkError::InvalidParams(format!("unknown platform certificate serial {}", serial_no))
+        };
+        let _downloading = self.certificates.lock_download().await;
+        // downloaded by a concurrent caller
+        if let Some(c) = self.certificates.get(serial_no).await {
+            return Ok(c);
+        }
+        if !self.certificates.try_begin_refresh(serial_no) {
+            return Err(unknown());
+        }
+        let found = self
+            .refresh_certificates()
+            .await?
+            .into_iter()
+            .find(|c| c.serial_no == serial_no);
+        if found.is_none() {
+            self.certificates.mark_missed(serial_no);
+        }
+        found.ok_or_else(unknown)
+    }
+
+    /// 使用 `Wechatpay-Serial` 对应的平台证书验证应答或通知的签名
+    async fn verify_signature(
+        &self,
+        headers: &HashMap<String, String>,
+        body: &str,
+    ) -> SdkResult<()> {
+        let serial = notify::get_serial(headers)?;
+        let certificate = self.get_certificate(serial).await?;
+        notify::verify_signature(&certificate, headers, body)
+    }
+
+    /// Send a signed request, `url` is the absolute path with query, like `/v3/certificates`.
+    ///
+    /// Return the response headers and body without verifying the signature.
+    async fn request<D: Serialize>(
+        &self,
+        method: Method,
+        url: &str,
+        body: Option<&D>,
+    ) -> SdkResult<(HashMap<String, String>, String)> {
+        let body = match body {
+            Some(b) => serde_json::to_string(b)?,
+            None => String::new(),
+        };
+        let authorization = self.config.authorization(method.as_str(), url, &body)?;
+        let mut builder = self
+            .sdk
+            .http_client
+            .request(method, format!("{}{}", BASE_URL, url))
+            .header(header::AUTHORIZATION, authorization)
+            .header(header::ACCEPT, "application/json")
+            .header(
+                header::USER_AGENT,
+                concat!("wx-sdk/", env!("CARGO_PKG_VERSION")),
+            );
+        if !body.is_empty() {
+            builder = builder
+                .header(header::CONTENT_TYPE, "application/json")
+                .body(body);
+        }
+        let res = builder.send().await?;
+        if !res.status().is_success() {
+            let e = res.json::<PayError>().await?;
+            return Err(SdkError::PayApiError(e));
+        }
+        let headers = res
+            .headers()
+            .iter()
+            .filter_map(|(k, v)| v.to_str().ok().map(|v| (k.to_string(), v.to_owned())))
+            .collect();
+        let body = res.text().await?;
+        Ok((headers, body))
+    }
+
+    /// Send a signed request and verify the signature of response, return the response body.
+    pub(crate) async fn send<D: Serialize>(
+        &self,
+        method: Method,
+        url: &str,
+        body: Option<&D>,
+    ) -> SdkResult<String> {
+        let (headers, body) = self.request(method, url, body).await?;
+        self.verify_signature(&headers, &body).await?;
+        Ok(body)
     }
+
+    pub(crate) async fn pay_get<R: DeserializeOwned>(&self, url: &str) -> SdkResult<R> {
+        let res = self.send::<()>(Method::GET, url, None).await?;
+        Ok(serde_json::from_str(&res)?)
+    }
+
+    pub(crate) async fn pay_post<R: DeserializeOwned, D: Serialize>(
+        &self,
+        url: &str,
+        post_data: &D,
+    ) -> SdkResult<R> {
+        let res = self.send(Method::POST, url, Some(post_data)).await?;
+        Ok(serde_json::from_str(&res)?)
+    }
+}
+
+/// Attach the `appid` and `mchid` to the request data.
+#[derive(Serialize)]
+pub(crate) struct WithMerchant<'a, D: Serialize> {
+    pub appid: &'a str,
+    pub mchid: &'a str,
+    #[serde(flatten)]
+    pub data: &'a D,
+}
+
+pub(crate) fn get_timestamp() -> u64 {
+    SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
 }
 
-// pub(crate) mod utils;
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_parse_notification() -> SdkResult<()> {
+        let mut config = PaySdkConfig::from_pem(
+            "1230000109",
+            "1DDE55AD98ED71D6EDD4A4A16996DE7B47773A8C",
+            "0123456789abcdef0123456789abcdef",
+            include_str!("../tests/pay/apiclient_key.pem"),
+        )?;
+        config.add_platform_certificate(include_str!("../tests/pay/platform_cert.pem"))?;
+        let paysdk =
+            WxSdk::new_with_default_token_client("wxd678efh567hg6787", "secret").pay(config);
+
+        let body = r#"{"id":"EV-2018022511223320873","create_time":"2015-05-20T13:29:35+08:00","resource_type":"encrypt-resource","event_type":"REFUND.CLOSED","summary":"退款关闭","resource":{"original_type":"refund","algorithm":"AEAD_AES_256_GCM","ciphertext":"invalid","associated_data":"refund","nonce":"fdasflkja484"}}"#;
+        let platform_key = sign::load_private_key(include_str!("../tests/pay/platform_key.pem"))?;
+        let timestamp = get_timestamp().to_string();
+        let message = sign::build_message(&[&timestamp, "nonce", body]);
+        let mut headers = HashMap::new();
+        headers.insert(
+            notify::HEADER_SERIAL.to_owned(),
+            "5157F09EFDC096DE15EBE81A47057A7232F1B8E1".to_owned(),
+        );
+        headers.insert(
+            notify::HEADER_SIGNATURE.to_owned(),
+            sign::sign(&platform_key, &message)?,
+        );
+        headers.insert(notify::HEADER_TIMESTAMP.to_owned(), timestamp);
+        headers.insert(notify::HEADER_NONCE.to_owned(), "nonce".to_owned());
+
+        // signature passed, but the ciphertext is invalid
+        let res = paysdk.parse_notification(body, &headers).await;
+        assert!(matches!(res, Err(SdkError::MsgDecryptError(_))));
+
+        let res = paysdk.parse_notification("{}", &headers).await;
+        assert!(matches!(res, Err(SdkError::InvalidSignature)));
+        Ok(())
+    }
+
+    #[test]
+    fn test_jsapi_pay_params() -> SdkResult<()> {
+        let config = PaySdkConfig::from_pem(
+            "1230000109",
+            "1DDE55AD98ED71D6EDD4A4A16996DE7B47773A8C",
+            "0123456789abcdef0123456789abcdef",
+            include_str!("../tests/pay/apiclient_key.pem"),
+        )?;
+        let paysdk =
+            WxSdk::new_with_default_token_client("wxd678efh567hg6787", "secret").pay(config);
+        let params = paysdk.jsapi_pay_params("wx201410272009395522657a690389285100")?;
+        assert_eq!(
+            params.package,
+            "prepay_id=wx201410272009395522657a690389285100"
+        );
+        assert_eq!(params.sign_type, "RSA");
+        Ok(())
+    }
+}
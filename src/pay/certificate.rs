@@ -0,0 +1,283 @@
+//! 微信支付平台证书
+//!
+//! 平台证书会定期更换，[CertificateManager] 按证书序列号缓存平台证书，
+//! 遇到未知序列号时通过 [下载平台证书](https://pay.weixin.qq.com/wiki/doc/apiv3/apis/wechatpay5_1.shtml) 接口刷新。
+//! 通知接口可被任何人调用，为防止伪造的序列号触发大量下载，[CERTIFICATE_REFRESH_INTERVAL] 内最多下载一次，
+//! 下载后仍未找到的序列号在 [MISSED_SERIAL_TTL] 内不再触发下载。
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use rsa::{pkcs8::DecodePublicKey, RsaPublicKey};
+use serde::{Deserialize, Serialize};
+use x509_parser::pem::parse_x509_pem;
+
+use crate::{cache::Cache, error::SdkError, SdkResult};
+
+use super::{crypto, get_timestamp};
+
+/// 因未知序列号下载平台证书的最小间隔
+pub const CERTIFICATE_REFRESH_INTERVAL: Duration = Duration::from_secs(60);
+/// 下载后仍未找到的序列号被记住的时间
+pub const MISSED_SERIAL_TTL: Duration = Duration::from_secs(600);
+
+/// 微信支付平台证书，用于验证应答和回调通知的签名
+#[derive(Clone, Debug)]
+pub struct PlatformCertificate {
+    /// 证书序列号，大写十六进制
+    pub serial_no: String,
+    /// 证书过期时间戳
+    pub expire_time: i64,
+    pub(crate) public_key: RsaPublicKey,
+}
+
+impl PlatformCertificate {
+    /// 从 PEM 格式的 X.509 证书解析平台证书
+    pub fn from_pem<S: AsRef<str>>(pem: S) -> SdkResult<Self> {
+        let (_, pem) = parse_x509_pem(pem.as_ref().as_bytes())
+            .map_err(|e| SdkError::InvalidParams(format!("invalid certificate pem: {}", e)))?;
+        let cert = pem
+            .parse_x509()
+            .map_err(|e| SdkError::InvalidParams(format!("invalid certificate: {}", e)))?;
+        // DER 编码的正整数可能带有前导 0x00
+        let raw_serial = match cert.raw_serial() {
+            [0, rest @ ..] if !rest.is_empty() => rest,
+            raw => raw,
+        };
+        let serial_no = raw_serial
+            .iter()
+            .map(|b| format!("{:02X}", b))
+            .collect::<String>();
+        let public_key = RsaPublicKey::from_public_key_der(cert.public_key().raw)
+            .map_err(|e| SdkError::InvalidParams(format!("invalid certificate key: {}", e)))?;
+        Ok(PlatformCertificate {
+            serial_no,
+            expire_time: cert.validity().not_after.timestamp(),
+            public_key,
+        })
+    }
+
+    /// 距离证书过期的时长，已过期返回 `None`
+    pub fn time_to_expire(&self) -> Option<Duration> {
+        let now = get_timestamp() as i64;
+        if self.expire_time > now {
+            Some(Duration::from_secs((self.expire_time - now) as u64))
+        } else {
+            None
+        }
+    }
+}
+
+/// 下载平台证书接口返回的加密证书
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct EncryptCertificate {
+    /// 加密算法类型，目前只支持 AEAD_AES_256_GCM
+    pub algorithm: String,
+    /// 加密使用的随机串
+    pub nonce: String,
+    /// 附加数据
+    pub associated_data: String,
+    /// Base64编码后的证书密文
+    pub ciphertext: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CertificateItem {
+    /// 证书序列号
+    pub serial_no: String,
+    /// 证书启用时间
+    pub effective_time: String,
+    /// 证书弃用时间
+    pub expire_time: String,
+    /// 证书信息
+    pub encrypt_certificate: EncryptCertificate,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CertificateList {
+    pub data: Vec<CertificateItem>,
+}
+
+impl CertificateItem {
+    /// 使用 APIv3 密钥解密得到平台证书
+    pub fn decrypt<S: AsRef<str>>(&self, apiv3_key: S) -> SdkResult<PlatformCertificate> {
+        let cert = &self.encrypt_certificate;
+        let pem = crypto::decrypt_aes_256_gcm(
+            apiv3_key,
+            &cert.nonce,
+            &cert.associated_data,
+            &cert.ciphertext,
+        )?;
+        PlatformCertificate::from_pem(pem)
+    }
+}
+
+/// 平台证书管理，以证书序列号为键缓存平台证书，缓存在证书过期时失效
+#[derive(Clone)]
+pub struct CertificateManager {
+    cache: Arc<Cache<String, PlatformCertificate>>,
+    /// 上次因未知序列号下载平台证书的时间
+    last_refresh: Arc<Mutex<Option<Instant>>>,
+    /// 下载后仍未找到的序列号
+    missed: Arc<Mutex<HashMap<String, Instant>>>,
+    /// 同一时间只下载一次，并发的调用者等待下载完成
+    downloading: Arc<tokio::sync::Mutex<()>>,
+}
+
+impl CertificateManager {
+    /// 使用预先配置的平台证书构造
+    pub fn new<I: IntoIterator<Item = PlatformCertificate>>(certificates: I) -> Self {
+        let items = certificates.into_iter().filter_map(|c| {
+            c.time_to_expire()
+                .map(|d| (c.serial_no.clone(), c, Some(d)))
+        });
+        CertificateManager {
+            cache: Arc::new(Cache::with_items(items)),
+            last_refresh: Arc::new(Mutex::new(None)),
+            missed: Arc::new(Mutex::new(HashMap::new())),
+            downloading: Arc::new(tokio::sync::Mutex::new(())),
+        }
+    }
+
+    /// 根据序列号获取平台证书
+    pub async fn get(&self, serial_no: &str) -> Option<PlatformCertificate> {
+        self.cache.get(&serial_no.to_owned()).await
+    }
+
+    /// 添加平台证书，已过期的证书会被忽略
+    pub async fn insert(&self, certificate: PlatformCertificate) {
+        if let Some(d) = certificate.time_to_expire() {
+            self.cache
+                .set(certificate.serial_no.clone(), certificate, Some(d))
+                .await;
+        }
+    }
+
+    /// 等待正在进行的下载完成，并占用下载
+    pub(crate) async fn lock_download(&self) -> tokio::sync::MutexGuard<'_, ()> {
+        self.downloading.lock().await
+    }
+
+    /// 是否可以因未知序列号 `serial_no` 下载平台证书，返回 `true` 时记为已下载
+    pub(crate) fn try_begin_refresh(&self, serial_no: &str) -> bool {
+        let now = Instant::now();
+        let missed = self.missed.lock().unwrap();
+        if missed
+            .get(serial_no)
+            .is_some_and(|t| now.duration_since(*t) < MISSED_SERIAL_TTL)
+        {
+            return false;
+        }
+        let mut last_refresh = self.last_refresh.lock().unwrap();
+        if last_refresh.is_some_and(|t| now.duration_since(t) < CERTIFICATE_REFRESH_INTERVAL) {
+            return false;
+        }
+        *last_refresh = Some(now);
+        true
+    }
+
+    /// 记住下载后仍未找到的序列号
+    pub(crate) fn mark_missed(&self, serial_no: &str) {
+        let now = Instant::now();
+        let mut missed = self.missed.lock().unwrap();
+        missed.retain(|_, t| now.duration_since(*t) < MISSED_SERIAL_TTL);
+        missed.insert(serial_no.to_owned(), now);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::base64;
+    use aes_gcm::aead::{Aead, NewAead, Payload};
+    use aes_gcm::{Aes256Gcm, Key, Nonce};
+
+    #[test]
+    fn test_from_pem() -> SdkResult<()> {
+        let cert =
+            PlatformCertificate::from_pem(include_str!("../../tests/pay/platform_cert.pem"))?;
+        assert_eq!(cert.serial_no, "5157F09EFDC096DE15EBE81A47057A7232F1B8E1");
+        assert!(cert.time_to_expire().is_some());
+        assert!(PlatformCertificate::from_pem("invalid pem").is_err());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_decrypt_and_manage() -> SdkResult<()> {
+        let apiv3_key = "0123456789abcdef0123456789abcdef";
+        let cipher = Aes256Gcm::new(Key::from_slice(apiv3_key.as_bytes()));
+        let ciphertext = cipher
+            .encrypt(
+                Nonce::from_slice(b"27c8f0b8b2a4"),
+                Payload {
+                    msg: include_bytes!("../../tests/pay/platform_cert.pem"),
+                    aad: b"certificate",
+                },
+            )
+            .unwrap();
+        let json = serde_json::json!({
+            "data": [{
+                "serial_no": "5157F09EFDC096DE15EBE81A47057A7232F1B8E1",
+                "effective_time": "2018-06-08T10:34:56+08:00",
+                "expire_time": "2118-06-08T10:34:56+08:00",
+                "encrypt_certificate": {
+                    "algorithm": "AEAD_AES_256_GCM",
+                    "nonce": "27c8f0b8b2a4",
+                    "associated_data": "certificate",
+                    "ciphertext": base64::encode(ciphertext)
+                }
+            }]
+        });
+        let list: CertificateList = serde_json::from_value(json).unwrap();
+        let cert = list.data[0].decrypt(apiv3_key)?;
+        assert_eq!(cert.serial_no, list.data[0].serial_no);
+
+        let manager = CertificateManager::new(vec![]);
+        assert!(manager.get(&cert.serial_no).await.is_none());
+        manager.insert(cert.clone()).await;
+        assert!(manager.get(&cert.serial_no).await.is_some());
+
+        let mut expired = cert;
+        expired.serial_no = "EXPIRED".to_owned();
+        expired.expire_time = 0;
+        let manager = CertificateManager::new(vec![expired]);
+        assert!(manager.get("EXPIRED").await.is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn test_refresh_throttle() {
+        let manager = CertificateManager::new(vec![]);
+        assert!(manager.try_begin_refresh("FORGED_1"));
+        // at most one download in the interval
+        assert!(!manager.try_begin_refresh("FORGED_2"));
+        manager.mark_missed("FORGED_1");
+
+        *manager.last_refresh.lock().unwrap() = Some(Instant::now() - CERTIFICATE_REFRESH_INTERVAL);
+        assert!(!manager.try_begin_refresh("FORGED_1"));
+        assert!(manager.try_begin_refresh("FORGED_2"));
+    }
+
+    #[tokio::test]
+    async fn test_wait_for_download() -> SdkResult<()> {
+        let cert =
+            PlatformCertificate::from_pem(include_str!("../../tests/pay/platform_cert.pem"))?;
+        let manager = CertificateManager::new(vec![]);
+        let downloading = manager.lock_download().await;
+        let waiting = {
+            let manager = manager.clone();
+            let serial_no = cert.serial_no.clone();
+            tokio::spawn(async move {
+                let _downloading = manager.lock_download().await;
+                manager.get(&serial_no).await
+            })
+        };
+        manager.insert(cert.clone()).await;
+        drop(downloading);
+        // the concurrent caller finds the downloaded certificate
+        assert!(waiting.await.unwrap().is_some());
+        Ok(())
+    }
+}
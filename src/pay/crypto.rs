@@ -0,0 +1,69 @@
+use aes_gcm::aead::{Aead, NewAead, Payload};
+use aes_gcm::{Aes256Gcm, Key, Nonce};
+
+use crate::error::SdkError;
+use crate::utils::base64;
+use crate::SdkResult;
+
+/// 使用 APIv3 密钥解密 `AEAD_AES_256_GCM` 加密的数据，如回调通知的 `resource` 和平台证书
+pub fn decrypt_aes_256_gcm<S: AsRef<str>>(
+    apiv3_key: S,
+    nonce: &str,
+    associated_data: &str,
+    ciphertext: &str,
+) -> SdkResult<String> {
+    let key = apiv3_key.as_ref().as_bytes();
+    if key.len() != 32 {
+        return Err(SdkError::MsgDecryptError(
+            "apiv3 key should be 32 bytes".to_owned(),
+        ));
+    }
+    let nonce = nonce.as_bytes();
+    if nonce.len() != 12 {
+        return Err(SdkError::MsgDecryptError(
+            "nonce should be 12 bytes".to_owned(),
+        ));
+    }
+    let cipher = Aes256Gcm::new(Key::from_slice(key));
+    let ciphertext =
+        base64::decode(ciphertext).map_err(|e| SdkError::MsgDecryptError(e.to_string()))?;
+    let plaintext = cipher
+        .decrypt(
+            Nonce::from_slice(nonce),
+            Payload {
+                msg: &ciphertext,
+                aad: associated_data.as_bytes(),
+            },
+        )
+        .map_err(|e| SdkError::MsgDecryptError(e.to_string()))?;
+    String::from_utf8(plaintext).map_err(|e| SdkError::MsgDecryptError(e.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decrypt() -> SdkResult<()> {
+        let key = "0123456789abcdef0123456789abcdef";
+        let cipher = Aes256Gcm::new(Key::from_slice(key.as_bytes()));
+        let ciphertext = cipher
+            .encrypt(
+                Nonce::from_slice(b"fdasflkja484"),
+                Payload {
+                    msg: br#"{"out_trade_no":"1217752501201407033233368018"}"#,
+                    aad: b"transaction",
+                },
+            )
+            .unwrap();
+        let ciphertext = base64::encode(ciphertext);
+
+        let plaintext = decrypt_aes_256_gcm(key, "fdasflkja484", "transaction", &ciphertext)?;
+        assert_eq!(
+            plaintext,
+            r#"{"out_trade_no":"1217752501201407033233368018"}"#
+        );
+        assert!(decrypt_aes_256_gcm(key, "fdasflkja484", "refund", &ciphertext).is_err());
+        Ok(())
+    }
+}
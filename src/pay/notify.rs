@@ -0,0 +1,273 @@
+//! 支付通知和退款通知的验签与解密
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{error::SdkError, SdkResult};
+
+use super::{
+    certificate::PlatformCertificate, crypto, get_timestamp, refund::RefundNotification, sign,
+    transactions::Transaction,
+};
+
+pub const HEADER_SERIAL: &str = "Wechatpay-Serial";
+pub const HEADER_SIGNATURE: &str = "Wechatpay-Signature";
+pub const HEADER_TIMESTAMP: &str = "Wechatpay-Timestamp";
+pub const HEADER_NONCE: &str = "Wechatpay-Nonce";
+
+/// 应答和通知的时间戳与当前时间相差超过5分钟即视为过期
+const TIMESTAMP_TOLERANCE: u64 = 5 * 60;
+
+const EVENT_TRANSACTION_PREFIX: &str = "TRANSACTION.";
+const EVENT_REFUND_PREFIX: &str = "REFUND.";
+
+/// 通知数据中加密的 `resource`
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct EncryptResource {
+    /// 加密算法类型，目前只支持 AEAD_AES_256_GCM
+    pub algorithm: String,
+    /// Base64编码后的数据密文
+    pub ciphertext: String,
+    /// 附加数据
+    #[serde(default)]
+    pub associated_data: Option<String>,
+    /// 原始回调类型，如 transaction、refund
+    pub original_type: String,
+    /// 加密使用的随机串
+    pub nonce: String,
+}
+
+impl EncryptResource {
+    /// 使用 APIv3 密钥解密
+    pub fn decrypt<S: AsRef<str>>(&self, apiv3_key: S) -> SdkResult<String> {
+        crypto::decrypt_aes_256_gcm(
+            apiv3_key,
+            &self.nonce,
+            self.associated_data.as_deref().unwrap_or_default(),
+            &self.ciphertext,
+        )
+    }
+}
+
+/// 微信支付推送的通知原文
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct NotifyRequest {
+    /// 通知的唯一ID
+    pub id: String,
+    /// 通知创建的时间，遵循rfc3339标准格式
+    pub create_time: String,
+    /// 通知的类型，如 TRANSACTION.SUCCESS、REFUND.SUCCESS
+    pub event_type: String,
+    /// 通知的资源数据类型，支付成功通知为 encrypt-resource
+    pub resource_type: String,
+    /// 回调摘要
+    pub summary: String,
+    /// 通知资源数据
+    pub resource: EncryptResource,
+}
+
+/// 解密后的通知资源
+#[derive(Debug)]
+pub enum NotifyResource {
+    Transaction(Box<Transaction>),
+    Refund(RefundNotification),
+    /// 未处理的通知类型，保留解密后的明文
+    Unhandled(String),
+}
+
+/// 验签并解密后的通知
+#[derive(Debug)]
+pub struct Notification {
+    pub id: String,
+    pub create_time: String,
+    pub event_type: String,
+    pub summary: String,
+    pub resource: NotifyResource,
+}
+
+/// 在请求头中查找，忽略大小写
+pub(crate) fn get_header<'a>(
+    headers: &'a HashMap<String, String>,
+    name: &str,
+) -> SdkResult<&'a str> {
+    headers
+        .iter()
+        .find(|(k, _)| k.eq_ignore_ascii_case(name))
+        .map(|(_, v)| v.as_str())
+        .ok_or_else(|| SdkError::InvalidParams(name.to_owned()))
+}
+
+/// 获取请求头中的平台证书序列号
+pub fn get_serial(headers: &HashMap<String, String>) -> SdkResult<&str> {
+    get_header(headers, HEADER_SERIAL)
+}
+
+/// 使用平台证书验证应答或通知的签名
+pub fn verify_signature(
+    certificate: &PlatformCertificate,
+    headers: &HashMap<String, String>,
+    body: &str,
+) -> SdkResult<()> {
+    let signature = get_header(headers, HEADER_SIGNATURE)?;
+    let timestamp = get_header(headers, HEADER_TIMESTAMP)?;
+    let nonce = get_header(headers, HEADER_NONCE)?;
+
+    let ts = timestamp
+        .parse::<u64>()
+        .map_err(|_| SdkError::InvalidParams(HEADER_TIMESTAMP.to_owned()))?;
+    if get_timestamp().abs_diff(ts) > TIMESTAMP_TOLERANCE {
+        return Err(SdkError::InvalidParams(format!(
+            "{} expired",
+            HEADER_TIMESTAMP
+        )));
+    }
+
+    let message = sign::build_message(&[timestamp, nonce, body]);
+    if !sign::verify(&certificate.public_key, &message, signature) {
+        return Err(SdkError::InvalidSignature);
+    }
+    Ok(())
+}
+
+/// 解密通知的 `resource` 并按 `event_type` 解析为对应的结构
+pub fn decrypt_notification<S: AsRef<str>>(body: &str, apiv3_key: S) -> SdkResult<Notification> {
+    let req = serde_json::from_str::<NotifyRequest>(body)
+        .map_err(|e| SdkError::InvalidParams(e.to_string()))?;
+    let plaintext = req.resource.decrypt(apiv3_key)?;
+    let resource = if req.event_type.starts_with(EVENT_TRANSACTION_PREFIX) {
+        NotifyResource::Transaction(
+            serde_json::from_str(&plaintext)
+                .map_err(|e| SdkError::MsgDecryptError(e.to_string()))?,
+        )
+    } else if req.event_type.starts_with(EVENT_REFUND_PREFIX) {
+        NotifyResource::Refund(
+            serde_json::from_str(&plaintext)
+                .map_err(|e| SdkError::MsgDecryptError(e.to_string()))?,
+        )
+    } else {
+        NotifyResource::Unhandled(plaintext)
+    };
+    Ok(Notification {
+        id: req.id,
+        create_time: req.create_time,
+        event_type: req.event_type,
+        summary: req.summary,
+        resource,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{pay::sign::load_private_key, utils::base64};
+    use aes_gcm::aead::{Aead, NewAead, Payload};
+    use aes_gcm::{Aes256Gcm, Key, Nonce};
+
+    const APIV3_KEY: &str = "0123456789abcdef0123456789abcdef";
+
+    fn encrypt(plaintext: &str, aad: &str) -> String {
+        let cipher = Aes256Gcm::new(Key::from_slice(APIV3_KEY.as_bytes()));
+        let ciphertext = cipher
+            .encrypt(
+                Nonce::from_slice(b"fdasflkja484"),
+                Payload {
+                    msg: plaintext.as_bytes(),
+                    aad: aad.as_bytes(),
+                },
+            )
+            .unwrap();
+        base64::encode(ciphertext)
+    }
+
+    fn notify_body(event_type: &str, original_type: &str, plaintext: &str) -> String {
+        serde_json::json!({
+            "id": "EV-2018022511223320873",
+            "create_time": "2015-05-20T13:29:35+08:00",
+            "resource_type": "encrypt-resource",
+            "event_type": event_type,
+            "summary": "支付成功",
+            "resource": {
+                "original_type": original_type,
+                "algorithm": "AEAD_AES_256_GCM",
+                "ciphertext": encrypt(plaintext, original_type),
+                "associated_data": original_type,
+                "nonce": "fdasflkja484"
+            }
+        })
+        .to_string()
+    }
+
+    #[test]
+    fn test_verify_signature() -> SdkResult<()> {
+        let certificate =
+            PlatformCertificate::from_pem(include_str!("../../tests/pay/platform_cert.pem"))?;
+        let private_key = load_private_key(include_str!("../../tests/pay/platform_key.pem"))?;
+        let body = r#"{"id":"EV-2018022511223320873"}"#;
+        let timestamp = get_timestamp().to_string();
+        let message = sign::build_message(&[&timestamp, "nonce", body]);
+        let signature = sign::sign(&private_key, &message)?;
+
+        let mut headers = HashMap::new();
+        headers.insert("wechatpay-serial".to_owned(), certificate.serial_no.clone());
+        headers.insert("wechatpay-signature".to_owned(), signature);
+        headers.insert("wechatpay-timestamp".to_owned(), timestamp);
+        headers.insert("wechatpay-nonce".to_owned(), "nonce".to_owned());
+        assert_eq!(
+            get_serial(&headers)?,
+            "5157F09EFDC096DE15EBE81A47057A7232F1B8E1"
+        );
+        verify_signature(&certificate, &headers, body)?;
+        assert!(matches!(
+            verify_signature(&certificate, &headers, "{}"),
+            Err(SdkError::InvalidSignature)
+        ));
+
+        headers.insert("wechatpay-timestamp".to_owned(), "1554208460".to_owned());
+        assert!(verify_signature(&certificate, &headers, body).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_decrypt_notification() -> SdkResult<()> {
+        let transaction = r#"{
+            "appid": "wxd678efh567hg6787",
+            "mchid": "1230000109",
+            "out_trade_no": "1217752501201407033233368018",
+            "transaction_id": "1217752501201407033233368018",
+            "trade_type": "JSAPI",
+            "trade_state": "SUCCESS",
+            "trade_state_desc": "支付成功",
+            "bank_type": "CMC",
+            "success_time": "2018-06-08T10:34:56+08:00",
+            "payer": { "openid": "oUpF8uMuAJO_M2pxb1Q9zNjWeS6o" },
+            "amount": { "total": 100, "payer_total": 100, "currency": "CNY", "payer_currency": "CNY" }
+        }"#;
+        let body = notify_body("TRANSACTION.SUCCESS", "transaction", transaction);
+        let notification = decrypt_notification(&body, APIV3_KEY)?;
+        match notification.resource {
+            NotifyResource::Transaction(t) => {
+                assert_eq!(t.out_trade_no, "1217752501201407033233368018")
+            }
+            _ => panic!("should be a transaction notification"),
+        }
+
+        let refund = r#"{
+            "mchid": "1900000100",
+            "transaction_id": "1008450740201411110005820873",
+            "out_trade_no": "20150806125346",
+            "refund_id": "50200207182018070300011301001",
+            "out_refund_no": "7752501201407033233368018",
+            "refund_status": "SUCCESS",
+            "success_time": "2018-06-08T10:34:56+08:00",
+            "user_received_account": "招商银行信用卡0403",
+            "amount" : { "total": 999, "refund": 999, "payer_total": 999, "payer_refund": 999 }
+        }"#;
+        let body = notify_body("REFUND.SUCCESS", "refund", refund);
+        let notification = decrypt_notification(&body, APIV3_KEY)?;
+        match notification.resource {
+            NotifyResource::Refund(r) => assert_eq!(r.refund_status, "SUCCESS"),
+            _ => panic!("should be a refund notification"),
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,193 @@
+//! 退款：申请退款，查询单笔退款
+use serde::{Deserialize, Serialize};
+
+use crate::{access_token::AccessTokenProvider, SdkResult};
+
+use super::PaySdk;
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RefundAmountRequest {
+    /// 退款金额，单位为分，不能超过原订单支付金额
+    pub refund: i64,
+    /// 原支付交易的订单总金额，单位为分
+    pub total: i64,
+    /// 退款币种，目前只支持人民币：CNY
+    pub currency: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RefundGoodsDetail {
+    /// 商户侧商品编码
+    pub merchant_goods_id: String,
+    /// 微信支付商品编码
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub wechatpay_goods_id: Option<String>,
+    /// 商品的实际名称
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub goods_name: Option<String>,
+    /// 商品单价，单位为分
+    pub unit_price: i64,
+    /// 商品退款金额，单位为分
+    pub refund_amount: i64,
+    /// 单品的退款数量
+    pub refund_quantity: i32,
+}
+
+/// 申请退款请求参数，`transaction_id` 和 `out_trade_no` 必须二选一
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RefundRequest {
+    /// 微信支付订单号
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub transaction_id: Option<String>,
+    /// 商户订单号
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub out_trade_no: Option<String>,
+    /// 商户系统内部的退款单号，商户系统内部唯一
+    pub out_refund_no: String,
+    /// 退款原因
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+    /// 退款结果回调url
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub notify_url: Option<String>,
+    /// 退款资金来源，枚举值：AVAILABLE：可用余额账户
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub funds_account: Option<String>,
+    /// 订单金额信息
+    pub amount: RefundAmountRequest,
+    /// 退款商品
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub goods_detail: Option<Vec<RefundGoodsDetail>>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RefundAmount {
+    /// 订单总金额，单位为分
+    pub total: i64,
+    /// 退款金额，单位为分
+    pub refund: i64,
+    /// 用户支付金额，单位为分
+    pub payer_total: i64,
+    /// 用户退款金额，单位为分
+    pub payer_refund: i64,
+    /// 应结退款金额，单位为分
+    pub settlement_refund: Option<i64>,
+    /// 应结订单金额，单位为分
+    pub settlement_total: Option<i64>,
+    /// 优惠退款金额，单位为分
+    pub discount_refund: Option<i64>,
+    /// 退款币种
+    pub currency: Option<String>,
+}
+
+/// 退款单信息
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Refund {
+    /// 微信支付退款单号
+    pub refund_id: String,
+    /// 商户退款单号
+    pub out_refund_no: String,
+    /// 微信支付订单号
+    pub transaction_id: String,
+    /// 商户订单号
+    pub out_trade_no: String,
+    /// 退款渠道，ORIGINAL、BALANCE、OTHER_BALANCE、OTHER_BANKCARD
+    pub channel: Option<String>,
+    /// 退款入账账户
+    pub user_received_account: String,
+    /// 退款成功时间
+    pub success_time: Option<String>,
+    /// 退款创建时间
+    pub create_time: Option<String>,
+    /// 退款状态，SUCCESS、CLOSED、PROCESSING、ABNORMAL
+    pub status: String,
+    /// 资金账户
+    pub funds_account: Option<String>,
+    /// 金额信息
+    pub amount: RefundAmount,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RefundNotificationAmount {
+    /// 订单总金额，单位为分
+    pub total: i64,
+    /// 退款金额，单位为分
+    pub refund: i64,
+    /// 用户支付金额，单位为分
+    pub payer_total: i64,
+    /// 用户退款金额，单位为分
+    pub payer_refund: i64,
+}
+
+/// 退款结果通知中解密后的退款信息
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RefundNotification {
+    /// 直连商户号
+    pub mchid: String,
+    /// 商户订单号
+    pub out_trade_no: String,
+    /// 微信支付订单号
+    pub transaction_id: String,
+    /// 商户退款单号
+    pub out_refund_no: String,
+    /// 微信支付退款单号
+    pub refund_id: String,
+    /// 退款状态，SUCCESS、CLOSED、ABNORMAL
+    pub refund_status: String,
+    /// 退款成功时间
+    pub success_time: Option<String>,
+    /// 退款入账账户
+    pub user_received_account: String,
+    /// 金额信息
+    pub amount: RefundNotificationAmount,
+}
+
+pub struct RefundModule<'a, T: AccessTokenProvider>(pub(crate) &'a PaySdk<T>);
+
+impl<'a, T: AccessTokenProvider> RefundModule<'a, T> {
+    /// 申请退款
+    pub async fn create(&self, data: &RefundRequest) -> SdkResult<Refund> {
+        let url = "/v3/refund/domestic/refunds";
+        self.0.pay_post(url, data).await
+    }
+
+    /// 查询单笔退款
+    pub async fn query(&self, out_refund_no: &str) -> SdkResult<Refund> {
+        let url = format!("/v3/refund/domestic/refunds/{}", out_refund_no);
+        self.0.pay_get(&url).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_refund() {
+        let json = r#"{
+            "refund_id": "50000000382019052709732678859",
+            "out_refund_no": "1217752501201407033233368018",
+            "transaction_id": "1217752501201407033233368018",
+            "out_trade_no": "1217752501201407033233368018",
+            "channel": "ORIGINAL",
+            "user_received_account": "招商银行信用卡0403",
+            "success_time": "2020-12-01T16:18:12+08:00",
+            "create_time": "2020-12-01T16:18:12+08:00",
+            "status": "SUCCESS",
+            "funds_account": "UNSETTLED",
+            "amount": {
+                "total": 100,
+                "refund": 100,
+                "payer_total": 90,
+                "payer_refund": 90,
+                "settlement_refund": 100,
+                "settlement_total": 100,
+                "discount_refund": 10,
+                "currency": "CNY"
+            }
+        }"#;
+        let refund: Refund = serde_json::from_str(json).unwrap();
+        assert_eq!(refund.status, "SUCCESS");
+        assert_eq!(refund.amount.payer_refund, 90);
+    }
+}
@@ -0,0 +1,111 @@
+//! The `WECHATPAY2-SHA256-RSA2048` [signature](https://pay.weixin.qq.com/wiki/doc/apiv3/wechatpay/wechatpay4_0.shtml) releated functions.
+use rsa::{pkcs8::DecodePrivateKey, Hash, PaddingScheme, PublicKey, RsaPrivateKey, RsaPublicKey};
+use sha2::{Digest, Sha256};
+
+use crate::{error::SdkError, utils::base64, SdkResult};
+
+pub const AUTH_SCHEMA: &str = "WECHATPAY2-SHA256-RSA2048";
+
+/// Load the merchant private key(`apiclient_key.pem`) from a PKCS#8 PEM string.
+pub fn load_private_key<S: AsRef<str>>(pem: S) -> SdkResult<RsaPrivateKey> {
+    RsaPrivateKey::from_pkcs8_pem(pem.as_ref()).map_err(|e| SdkError::PaySignError(e.to_string()))
+}
+
+/// Build the message to be signed, every part ends with a `\n`.
+pub fn build_message(parts: &[&str]) -> String {
+    parts.iter().map(|p| format!("{}\n", p)).collect()
+}
+
+/// Sign the message with SHA256-RSA, return the base64 encoded signature.
+pub fn sign(private_key: &RsaPrivateKey, message: &str) -> SdkResult<String> {
+    let hashed = Sha256::digest(message.as_bytes());
+    let signature = private_key
+        .sign(
+            PaddingScheme::new_pkcs1v15_sign(Some(Hash::SHA2_256)),
+            hashed.as_slice(),
+        )
+        .map_err(|e| SdkError::PaySignError(e.to_string()))?;
+    Ok(base64::encode(signature))
+}
+
+/// Verify the base64 encoded SHA256-RSA signature with the wechat pay platform public key.
+pub fn verify(public_key: &RsaPublicKey, message: &str, signature: &str) -> bool {
+    let hashed = Sha256::digest(message.as_bytes());
+    match base64::decode(signature) {
+        Ok(signature) => public_key
+            .verify(
+                PaddingScheme::new_pkcs1v15_sign(Some(Hash::SHA2_256)),
+                hashed.as_slice(),
+                &signature,
+            )
+            .is_ok(),
+        Err(_) => false,
+    }
+}
+
+/// Compute the `Authorization` header value of a wechat pay v3 request.
+///
+/// `message` should be built by [build_message] with `[method, url, timestamp, nonce_str, body]`,
+/// `url` is the absolute path with query of the request, like `/v3/certificates?a=b`.
+pub fn authorization(
+    private_key: &RsaPrivateKey,
+    mch_id: &str,
+    serial_no: &str,
+    timestamp: &str,
+    nonce_str: &str,
+    message: &str,
+) -> SdkResult<String> {
+    let signature = sign(private_key, message)?;
+    Ok(format!(
+        r#"{} mchid="{}",nonce_str="{}",signature="{}",timestamp="{}",serial_no="{}""#,
+        AUTH_SCHEMA, mch_id, nonce_str, signature, timestamp, serial_no
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_build_message() {
+        let message = build_message(&[
+            "GET",
+            "/v3/certificates",
+            "1554208460",
+            "593BEC0C930BF1AFEB40B4A08C8FB242",
+            "",
+        ]);
+        assert_eq!(
+            message,
+            "GET\n/v3/certificates\n1554208460\n593BEC0C930BF1AFEB40B4A08C8FB242\n\n"
+        );
+    }
+
+    #[test]
+    fn test_sign() -> SdkResult<()> {
+        let mut rng = rand::thread_rng();
+        let private_key = RsaPrivateKey::new(&mut rng, 1024).unwrap();
+        let public_key = RsaPublicKey::from(&private_key);
+        let message = build_message(&["GET", "/v3/certificates", "1554208460", "nonce", ""]);
+        let signature = sign(&private_key, &message)?;
+
+        assert!(verify(&public_key, &message, &signature));
+        assert!(!verify(&public_key, "other message", &signature));
+
+        let auth = authorization(
+            &private_key,
+            "1900009191",
+            "1DDE55AD98ED71D6EDD4A4A16996DE7B47773A8C",
+            "1554208460",
+            "nonce",
+            &message,
+        )?;
+        assert!(auth.starts_with(
+            r#"WECHATPAY2-SHA256-RSA2048 mchid="1900009191",nonce_str="nonce",signature=""#
+        ));
+        assert!(auth.ends_with(
+            r#"timestamp="1554208460",serial_no="1DDE55AD98ED71D6EDD4A4A16996DE7B47773A8C""#
+        ));
+        Ok(())
+    }
+}
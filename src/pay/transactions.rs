@@ -0,0 +1,382 @@
+//! 基础支付：JSAPI、Native、H5、APP 下单，查询订单，关闭订单
+use reqwest::Method;
+use serde::{Deserialize, Serialize};
+
+use crate::{access_token::AccessTokenProvider, SdkResult};
+
+use super::{PaySdk, WithMerchant};
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct OrderAmount {
+    /// 订单总金额，单位为分
+    pub total: i64,
+    /// 货币类型，CNY：人民币，境内商户号仅支持人民币
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub currency: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Payer {
+    /// 用户在直连商户appid下的唯一标识
+    pub openid: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct GoodsDetail {
+    /// 商户侧商品编码
+    pub merchant_goods_id: String,
+    /// 微信支付商品编码
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub wechatpay_goods_id: Option<String>,
+    /// 商品的实际名称
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub goods_name: Option<String>,
+    /// 用户购买的数量
+    pub quantity: i32,
+    /// 商品单价，单位为分
+    pub unit_price: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct OrderDetail {
+    /// 订单原价
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cost_price: Option<i64>,
+    /// 商家小票ID
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub invoice_id: Option<String>,
+    /// 单品列表信息
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub goods_detail: Option<Vec<GoodsDetail>>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct StoreInfo {
+    /// 商户侧门店编号
+    pub id: String,
+    /// 商户侧门店名称
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// 地区编码
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub area_code: Option<String>,
+    /// 详细的商户门店地址
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub address: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct H5Info {
+    /// 场景类型，示例值：iOS, Android, Wap
+    #[serde(rename = "type")]
+    pub h5_type: String,
+    /// 应用名称
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub app_name: Option<String>,
+    /// 网站URL
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub app_url: Option<String>,
+    /// iOS平台BundleID
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bundle_id: Option<String>,
+    /// Android平台PackageName
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub package_name: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SceneInfo {
+    /// 用户的客户端IP，支持IPv4和IPv6两种格式的IP地址
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub payer_client_ip: Option<String>,
+    /// 商户端设备号（门店号或收银设备ID）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub device_id: Option<String>,
+    /// 商户门店信息
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub store_info: Option<StoreInfo>,
+    /// H5场景信息，H5下单时必填
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub h5_info: Option<H5Info>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SettleInfo {
+    /// 是否指定分账
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub profit_sharing: Option<bool>,
+}
+
+/// 下单请求参数，`appid` 和 `mchid` 由 SDK 自动填充
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Order {
+    /// 商品描述
+    pub description: String,
+    /// 商户系统内部订单号，只能是数字、大小写字母_-*且在同一个商户号下唯一
+    pub out_trade_no: String,
+    /// 订单失效时间，遵循rfc3339标准格式
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub time_expire: Option<String>,
+    /// 附加数据，在查询API和支付通知中原样返回
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub attach: Option<String>,
+    /// 异步接收微信支付结果通知的回调地址，必须为外网可访问的https url，不能携带参数
+    pub notify_url: String,
+    /// 订单优惠标记
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub goods_tag: Option<String>,
+    /// 订单金额信息
+    pub amount: OrderAmount,
+    /// 支付者信息，JSAPI下单时必填
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub payer: Option<Payer>,
+    /// 优惠功能
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub detail: Option<OrderDetail>,
+    /// 场景信息，H5下单时必填
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scene_info: Option<SceneInfo>,
+    /// 结算信息
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub settle_info: Option<SettleInfo>,
+}
+
+/// JSAPI、小程序、APP 下单返回的预支付交易会话标识
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PrepayId {
+    /// 预支付交易会话标识，用于后续接口调用中使用，该值有效期为2小时
+    pub prepay_id: String,
+}
+
+/// Native 下单返回的二维码链接
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CodeUrl {
+    /// 此URL用于生成支付二维码，然后提供给用户扫码支付
+    pub code_url: String,
+}
+
+/// H5 下单返回的支付跳转链接
+#[derive(Debug, Serialize, Deserialize)]
+pub struct H5Url {
+    /// 支付跳转链接，有效期为5分钟
+    pub h5_url: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TransactionAmount {
+    /// 订单总金额，单位为分
+    pub total: Option<i64>,
+    /// 用户支付金额，单位为分
+    pub payer_total: Option<i64>,
+    /// 货币类型
+    pub currency: Option<String>,
+    /// 用户支付币种
+    pub payer_currency: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PromotionGoodsDetail {
+    /// 商品编码
+    pub goods_id: String,
+    /// 商品数量
+    pub quantity: i32,
+    /// 商品单价，单位为分
+    pub unit_price: i64,
+    /// 商品优惠金额
+    pub discount_amount: i64,
+    /// 商品备注
+    pub goods_remark: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PromotionDetail {
+    /// 券ID
+    pub coupon_id: String,
+    /// 优惠名称
+    pub name: Option<String>,
+    /// 优惠范围，GLOBAL：全场代金券，SINGLE：单品优惠
+    pub scope: Option<String>,
+    /// 优惠类型，CASH：充值型代金券，NOCASH：免充值型代金券
+    #[serde(rename = "type")]
+    pub promotion_type: Option<String>,
+    /// 优惠券面额
+    pub amount: i64,
+    /// 活动ID
+    pub stock_id: Option<String>,
+    /// 微信出资，单位为分
+    pub wechatpay_contribute: Option<i64>,
+    /// 商户出资，单位为分
+    pub merchant_contribute: Option<i64>,
+    /// 其他出资，单位为分
+    pub other_contribute: Option<i64>,
+    /// 优惠币种
+    pub currency: Option<String>,
+    /// 单品列表信息
+    pub goods_detail: Option<Vec<PromotionGoodsDetail>>,
+}
+
+/// 订单信息，查询订单和支付通知时返回
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Transaction {
+    /// 应用ID
+    pub appid: String,
+    /// 直连商户号
+    pub mchid: String,
+    /// 商户订单号
+    pub out_trade_no: String,
+    /// 微信支付订单号
+    pub transaction_id: Option<String>,
+    /// 交易类型，JSAPI、NATIVE、APP、MICROPAY、MWEB、FACEPAY
+    pub trade_type: Option<String>,
+    /// 交易状态，SUCCESS、REFUND、NOTPAY、CLOSED、REVOKED、USERPAYING、PAYERROR
+    pub trade_state: String,
+    /// 交易状态描述
+    pub trade_state_desc: String,
+    /// 付款银行
+    pub bank_type: Option<String>,
+    /// 附加数据
+    pub attach: Option<String>,
+    /// 支付完成时间，遵循rfc3339标准格式
+    pub success_time: Option<String>,
+    /// 支付者
+    pub payer: Option<Payer>,
+    /// 订单金额
+    pub amount: Option<TransactionAmount>,
+    /// 场景信息
+    pub scene_info: Option<SceneInfo>,
+    /// 优惠功能
+    pub promotion_detail: Option<Vec<PromotionDetail>>,
+}
+
+pub struct TransactionsModule<'a, T: AccessTokenProvider>(pub(crate) &'a PaySdk<T>);
+
+impl<'a, T: AccessTokenProvider> TransactionsModule<'a, T> {
+    async fn prepay<R: serde::de::DeserializeOwned>(
+        &self,
+        url: &str,
+        order: &Order,
+    ) -> SdkResult<R> {
+        let sdk = self.0;
+        let data = WithMerchant {
+            appid: &sdk.sdk.app_id,
+            mchid: &sdk.config.mch_id,
+            data: order,
+        };
+        sdk.pay_post(url, &data).await
+    }
+
+    /// JSAPI下单，小程序下单也使用该接口
+    pub async fn jsapi(&self, order: &Order) -> SdkResult<PrepayId> {
+        self.prepay("/v3/pay/transactions/jsapi", order).await
+    }
+
+    /// APP下单
+    pub async fn app(&self, order: &Order) -> SdkResult<PrepayId> {
+        self.prepay("/v3/pay/transactions/app", order).await
+    }
+
+    /// Native下单
+    pub async fn native(&self, order: &Order) -> SdkResult<CodeUrl> {
+        self.prepay("/v3/pay/transactions/native", order).await
+    }
+
+    /// H5下单
+    pub async fn h5(&self, order: &Order) -> SdkResult<H5Url> {
+        self.prepay("/v3/pay/transactions/h5", order).await
+    }
+
+    /// 微信支付订单号查询
+    pub async fn query_by_transaction_id(&self, transaction_id: &str) -> SdkResult<Transaction> {
+        let sdk = self.0;
+        let url = format!(
+            "/v3/pay/transactions/id/{}?mchid={}",
+            transaction_id, sdk.config.mch_id
+        );
+        sdk.pay_get(&url).await
+    }
+
+    /// 商户订单号查询
+    pub async fn query_by_out_trade_no(&self, out_trade_no: &str) -> SdkResult<Transaction> {
+        let sdk = self.0;
+        let url = format!(
+            "/v3/pay/transactions/out-trade-no/{}?mchid={}",
+            out_trade_no, sdk.config.mch_id
+        );
+        sdk.pay_get(&url).await
+    }
+
+    /// 关闭订单，成功时微信支付返回 204 No Content
+    pub async fn close(&self, out_trade_no: &str) -> SdkResult<()> {
+        let sdk = self.0;
+        let url = format!("/v3/pay/transactions/out-trade-no/{}/close", out_trade_no);
+        let data = serde_json::json!({ "mchid": &sdk.config.mch_id });
+        sdk.send(Method::POST, &url, Some(&data)).await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_transaction() {
+        let json = r#"{
+            "amount": {
+                "currency": "CNY",
+                "payer_currency": "CNY",
+                "payer_total": 1,
+                "total": 1
+            },
+            "appid": "wxdace645e0bc2cXXX",
+            "attach": "",
+            "bank_type": "OTHERS",
+            "mchid": "1900006XXX",
+            "out_trade_no": "44_2126281063_5504",
+            "payer": {
+                "openid": "o4GgauJP_mgWEWictzA15WT15XXX"
+            },
+            "promotion_detail": [],
+            "success_time": "2021-03-22T10:29:05+08:00",
+            "trade_state": "SUCCESS",
+            "trade_state_desc": "支付成功",
+            "trade_type": "JSAPI",
+            "transaction_id": "4200000891202103228088184743"
+        }"#;
+        let transaction: Transaction = serde_json::from_str(json).unwrap();
+        assert_eq!(transaction.trade_state, "SUCCESS");
+        assert_eq!(transaction.amount.unwrap().payer_total, Some(1));
+    }
+
+    #[test]
+    fn test_order_with_merchant() {
+        let order = Order {
+            description: "Image形象店-深圳腾大-QQ公仔".to_owned(),
+            out_trade_no: "1217752501201407033233368018".to_owned(),
+            time_expire: None,
+            attach: None,
+            notify_url: "https://www.weixin.qq.com/wxpay/pay.php".to_owned(),
+            goods_tag: None,
+            amount: OrderAmount {
+                total: 100,
+                currency: Some("CNY".to_owned()),
+            },
+            payer: Some(Payer {
+                openid: "oUpF8uMuAJO_M2pxb1Q9zNjWeS6o".to_owned(),
+            }),
+            detail: None,
+            scene_info: None,
+            settle_info: None,
+        };
+        let data = WithMerchant {
+            appid: "wxd678efh567hg6787",
+            mchid: "1230000109",
+            data: &order,
+        };
+        let value = serde_json::to_value(&data).unwrap();
+        assert_eq!(value["appid"], "wxd678efh567hg6787");
+        assert_eq!(value["mchid"], "1230000109");
+        assert_eq!(value["amount"]["total"], 100);
+        assert!(value.get("time_expire").is_none());
+    }
+}
@@ -0,0 +1,328 @@
+//! Manage many official accounts and mini programs in one process.
+//!
+//! The [WxSdk] of an account is built when it's used at the first time, all the accounts share one http client and one [TokenStore].
+//! The pushed messages are routed to the account by `ToUserName`, or by the appid in the decrypted message.
+//!
+//! Example
+//! ```rust
+//! use wx_sdk::mp::{EncodingMode, ServerConfig};
+//! use wx_sdk::registry::{AccountConfig, WxSdkRegistry};
+//! let registry = WxSdkRegistry::new();
+//! registry.register(
+//!     AccountConfig::new("app_id", "app_secret")
+//!         .original_id("gh_123456789abc")
+//!         .server_config(ServerConfig::new("token", EncodingMode::Plain)),
+//! );
+//! let msg = "<xml><ToUserName><![CDATA[gh_123456789abc]]></ToUserName></xml>";
+//! let mp = registry.mp_for_msg(msg).unwrap();
+//! assert_eq!(mp.app_id(), "app_id");
+//! ```
+use std::{
+    collections::HashMap,
+    sync::{Arc, RwLock},
+};
+
+use reqwest::Client;
+
+#[cfg(feature = "wxa")]
+use crate::wxa::WxaSdk;
+use crate::{
+    access_token::{MemoryTokenStore, TokenStore},
+    error::SdkError,
+    mp::{event::crypto::decrypt_message, EncodingMode, MpSdk, ServerConfig},
+    wechat::{FailoverPolicy, RetryPolicy, API_BASE_URL},
+    SdkResult, TokenClient, WxSdk, WxSdkBuilder,
+};
+
+/// The configuration of an account.
+#[derive(Clone)]
+pub struct AccountConfig {
+    pub app_id: String,
+    pub app_secret: String,
+    /// The original id `gh_xxx`, it's the `ToUserName` of the pushed messages.
+    pub original_id: Option<String>,
+    /// The message push configuration.
+    pub server_config: Option<ServerConfig>,
+}
+
+impl AccountConfig {
+    pub fn new<S: AsRef<str>>(app_id: S, app_secret: S) -> Self {
+        AccountConfig {
+            app_id: app_id.as_ref().to_owned(),
+            app_secret: app_secret.as_ref().to_owned(),
+            original_id: None,
+            server_config: None,
+        }
+    }
+
+    pub fn original_id<S: AsRef<str>>(mut self, original_id: S) -> Self {
+        self.original_id = Some(original_id.as_ref().to_owned());
+        self
+    }
+
+    pub fn server_config(mut self, server_config: ServerConfig) -> Self {
+        self.server_config = Some(server_config);
+        self
+    }
+}
+
+/// The accounts keyed by app_id.
+pub struct WxSdkRegistry {
+    http_client: Client,
+    token_store: Arc<dyn TokenStore>,
+    failover: FailoverPolicy,
+    retry: RetryPolicy,
+    accounts: RwLock<HashMap<String, AccountConfig>>,
+    sdks: RwLock<HashMap<String, WxSdk<TokenClient>>>,
+}
+
+impl Default for WxSdkRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl WxSdkRegistry {
+    /// The accounts share a default http client and a [MemoryTokenStore].
+    pub fn new() -> Self {
+        WxSdkRegistry {
+            http_client: Client::new(),
+            token_store: Arc::new(MemoryTokenStore::new()),
+            failover: FailoverPolicy::single(API_BASE_URL),
+            retry: RetryPolicy::default(),
+            accounts: RwLock::new(HashMap::new()),
+            sdks: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// Share a custom [reqwest::Client].
+    pub fn with_http_client(mut self, http_client: Client) -> Self {
+        self.http_client = http_client;
+        self
+    }
+
+    /// Share a custom [TokenStore], e.g. [RedisTokenStore][crate::access_token::RedisTokenStore].
+    pub fn with_token_store<S: TokenStore + 'static>(mut self, store: S) -> Self {
+        self.token_store = Arc::new(store);
+        self
+    }
+
+    /// See [WxSdkBuilder::failover].
+    pub fn with_failover(mut self, policy: FailoverPolicy) -> Self {
+        self.failover = policy;
+        self
+    }
+
+    /// See [WxSdkBuilder::retry].
+    pub fn with_retry(mut self, policy: RetryPolicy) -> Self {
+        self.retry = policy;
+        self
+    }
+
+    /// Add or replace an account.
+    pub fn register(&self, account: AccountConfig) {
+        // locked in the same order as `sdk`, so it never caches the sdk of the replaced account
+        let mut sdks = self.sdks.write().unwrap();
+        let mut accounts = self.accounts.write().unwrap();
+        sdks.remove(&account.app_id);
+        accounts.insert(account.app_id.clone(), account);
+    }
+
+    /// Remove an account.
+    pub fn unregister(&self, app_id: &str) -> Option<AccountConfig> {
+        let mut sdks = self.sdks.write().unwrap();
+        let mut accounts = self.accounts.write().unwrap();
+        sdks.remove(app_id);
+        accounts.remove(app_id)
+    }
+
+    /// The app_ids of all the accounts.
+    pub fn app_ids(&self) -> Vec<String> {
+        self.accounts.read().unwrap().keys().cloned().collect()
+    }
+
+    fn account(&self, app_id: &str) -> SdkResult<AccountConfig> {
+        self.accounts
+            .read()
+            .unwrap()
+            .get(app_id)
+            .cloned()
+            .ok_or_else(|| SdkError::InvalidParams(format!("unknown app_id {}", app_id)))
+    }
+
+    /// The sdk of the account, it's built at the first call.
+    pub fn sdk(&self, app_id: &str) -> SdkResult<WxSdk<TokenClient>> {
+        if let Some(sdk) = self.sdks.read().unwrap().get(app_id) {
+            return Ok(sdk.clone());
+        }
+        let mut sdks = self.sdks.write().unwrap();
+        if let Some(sdk) = sdks.get(app_id) {
+            return Ok(sdk.clone());
+        }
+        // read under the lock of sdks, the account can't be replaced before the sdk is cached
+        let account = self.account(app_id)?;
+        let sdk = WxSdkBuilder::new(&account.app_id, &account.app_secret)
+            .http_client(self.http_client.clone())
+            .token_store(self.token_store.clone())
+            .failover(self.failover.clone())
+            .retry(self.retry.clone())
+            .build()?;
+        sdks.insert(account.app_id, sdk.clone());
+        Ok(sdk)
+    }
+
+    /// The official account sdk, the account must have a [AccountConfig::server_config].
+    pub fn mp(&self, app_id: &str) -> SdkResult<MpSdk<TokenClient>> {
+        let server_config = self.account(app_id)?.server_config.ok_or_else(|| {
+            SdkError::InvalidParams(format!("server_config of app_id {}", app_id))
+        })?;
+        Ok(self.sdk(app_id)?.mp(server_config))
+    }
+
+    /// The mini program sdk.
+    #[cfg(feature = "wxa")]
+    pub fn wxa(&self, app_id: &str) -> SdkResult<WxaSdk<TokenClient>> {
+        let server_config = self.account(app_id)?.server_config;
+        let sdk = self.sdk(app_id)?;
+        Ok(match server_config {
+            Some(server_config) => sdk.wxa_with_server_config(server_config),
+            None => sdk.wxa(),
+        })
+    }
+
+    /// Find the app_id of a pushed XML or JSON message.
+    ///
+    /// The `ToUserName` is matched with [AccountConfig::original_id] at first,
+    /// then the `Encrypt` is decrypted by the aes keys of the accounts to get the appid.
+    pub fn route_msg<S: AsRef<str>>(&self, msg: S) -> SdkResult<String> {
+        let (to_user_name, encrypt) = parse_route_fields(msg.as_ref())?;
+        let accounts = self.accounts.read().unwrap();
+        if let Some(to_user_name) = to_user_name {
+            let found = accounts
+                .values()
+                .find(|a| a.original_id.as_deref() == Some(to_user_name.as_str()));
+            if let Some(account) = found {
+                return Ok(account.app_id.clone());
+            }
+        }
+        if let Some(encrypt) = encrypt {
+            let found = accounts.values().find(|account| {
+                let aes_key = match account.server_config.as_ref().map(|c| &c.encoding_mode) {
+                    Some(EncodingMode::Compat(aes_key)) | Some(EncodingMode::Security(aes_key)) => {
+                        aes_key
+                    }
+                    _ => return false,
+                };
+                decrypt_message(&encrypt, aes_key).is_ok_and(|(_, app_id)| app_id == account.app_id)
+            });
+            if let Some(account) = found {
+                return Ok(account.app_id.clone());
+            }
+        }
+        Err(SdkError::InvalidAppid)
+    }
+
+    /// The official account sdk to handle a pushed message, see [WxSdkRegistry::route_msg].
+    pub fn mp_for_msg<S: AsRef<str>>(&self, msg: S) -> SdkResult<MpSdk<TokenClient>> {
+        self.mp(&self.route_msg(msg)?)
+    }
+
+    /// The mini program sdk to handle a pushed message, see [WxSdkRegistry::route_msg].
+    #[cfg(feature = "wxa")]
+    pub fn wxa_for_msg<S: AsRef<str>>(&self, msg: S) -> SdkResult<WxaSdk<TokenClient>> {
+        self.wxa(&self.route_msg(msg)?)
+    }
+}
+
+/// The `ToUserName` and `Encrypt` of a XML or JSON message.
+fn parse_route_fields(msg: &str) -> SdkResult<(Option<String>, Option<String>)> {
+    let msg = msg.trim();
+    if msg.starts_with('{') {
+        let value: serde_json::Value = serde_json::from_str(msg)?;
+        let field = |key: &str| value.get(key).and_then(|v| v.as_str()).map(str::to_owned);
+        return Ok((field("ToUserName"), field("Encrypt")));
+    }
+    let doc = roxmltree::Document::parse(msg)?;
+    let field = |key: &str| {
+        doc.root_element()
+            .children()
+            .find(|n| n.has_tag_name(key))
+            .and_then(|n| n.text())
+            .map(str::to_owned)
+    };
+    Ok((field("ToUserName"), field("Encrypt")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        access_token::AccessTokenProvider, mp::event::crypto::encrypt_message, AccessToken,
+    };
+
+    const AES_KEY: &str = "jWmYm7qr5nMoAUwZRjGtBxmz3KA1tkAj3ykkR6q2B2C";
+
+    #[tokio::test]
+    async fn test_registry() -> SdkResult<()> {
+        let store = Arc::new(MemoryTokenStore::new());
+        let registry = WxSdkRegistry::new().with_token_store(store.clone());
+        registry.register(
+            AccountConfig::new("wx_mp", "secret_mp")
+                .original_id("gh_mp")
+                .server_config(ServerConfig::new("token", EncodingMode::Plain)),
+        );
+        registry.register(AccountConfig::new("wx_safe", "secret_safe").server_config(
+            ServerConfig::new("token", EncodingMode::Security(AES_KEY.to_owned())),
+        ));
+        let mut app_ids = registry.app_ids();
+        app_ids.sort();
+        assert_eq!(app_ids, vec!["wx_mp", "wx_safe"]);
+
+        // the token store is shared
+        store
+            .set(
+                "wx_sdk:access_token:wx_mp",
+                &AccessToken {
+                    access_token: "TOKEN_MP".to_owned(),
+                    expires_in: 7200,
+                },
+            )
+            .await?;
+        let sdk = registry.sdk("wx_mp")?;
+        assert_eq!(
+            sdk.token_client.get_access_token().await?.access_token,
+            "TOKEN_MP"
+        );
+        // the sdk is built once
+        assert!(Arc::ptr_eq(&sdk.domains, &registry.sdk("wx_mp")?.domains));
+        assert!(registry.sdk("wx_unknown").is_err());
+
+        let msg = "<xml><ToUserName><![CDATA[gh_mp]]></ToUserName><Content>hi</Content></xml>";
+        assert_eq!(registry.route_msg(msg)?, "wx_mp");
+        assert_eq!(registry.mp_for_msg(msg)?.app_id(), "wx_mp");
+        let msg = r#"{"ToUserName":"gh_mp","MsgType":"text"}"#;
+        assert_eq!(registry.route_msg(msg)?, "wx_mp");
+
+        let encrypt = encrypt_message("<xml></xml>", AES_KEY, "wx_safe")?;
+        let msg = format!(
+            "<xml><ToUserName><![CDATA[gh_safe]]></ToUserName><Encrypt><![CDATA[{}]]></Encrypt></xml>",
+            encrypt
+        );
+        assert_eq!(registry.route_msg(&msg)?, "wx_safe");
+
+        let encrypt = encrypt_message("<xml></xml>", AES_KEY, "wx_other")?;
+        let msg = format!("<xml><Encrypt><![CDATA[{}]]></Encrypt></xml>", encrypt);
+        assert!(matches!(
+            registry.route_msg(&msg),
+            Err(SdkError::InvalidAppid)
+        ));
+
+        // the cached sdk is replaced with the account
+        registry.register(AccountConfig::new("wx_mp", "secret_new"));
+        assert_eq!(registry.sdk("wx_mp")?.app_secret, "secret_new");
+
+        registry.unregister("wx_mp");
+        assert!(registry.sdk("wx_mp").is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,399 @@
+//! In-process mock of WeChat api server, enabled by the `testing` feature.
+//!
+//! The server serves `/cgi-bin/token` and `/cgi-bin/stable_token` with a fixed token,
+//! the other paths reply the scripted responses, and every request is recorded.
+//!
+//! Example
+//! ```rust
+//! use wx_sdk::mp::{EncodingMode, ServerConfig};
+//! use wx_sdk::testing::{MockResponse, MockServer};
+//! # async fn run() -> wx_sdk::SdkResult<()> {
+//! let server = MockServer::start().await?;
+//! server.mock("/cgi-bin/user/info/updateremark", MockResponse::ok());
+//!
+//! let sdk = server.sdk_builder("app_id", "app_secret").build()?;
+//! let mp = sdk.mp(ServerConfig::new("token", EncodingMode::Plain));
+//! mp.user().info_updateremark("openid", "remark").await?;
+//!
+//! let requests = server.requests_to("/cgi-bin/user/info/updateremark");
+//! assert_eq!(requests[0].json::<serde_json::Value>()?["remark"], "remark");
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! The responses of real WeChat api can be recorded by [FixtureRecorder], then replayed by [MockServer::replay_file].
+use std::{
+    collections::{HashMap, VecDeque},
+    net::SocketAddr,
+    sync::{Arc, Mutex},
+};
+
+use futures::future::BoxFuture;
+use serde::{de::DeserializeOwned, Serialize};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+    task::JoinHandle,
+};
+
+use crate::{SdkResult, WxSdkBuilder};
+
+mod fixture;
+
+pub use fixture::{Fixture, FixtureRecorder};
+
+/// The access token served by the mock server.
+pub const MOCK_ACCESS_TOKEN: &str = "MOCK_ACCESS_TOKEN";
+
+/// A scripted response.
+#[derive(Debug, Clone)]
+pub struct MockResponse {
+    pub status: u16,
+    pub content_type: String,
+    pub body: Vec<u8>,
+}
+
+impl MockResponse {
+    /// A `200` response with the JSON body.
+    pub fn json<B: Serialize + ?Sized>(body: &B) -> Self {
+        MockResponse {
+            status: 200,
+            content_type: "application/json; encoding=utf-8".to_owned(),
+            body: serde_json::to_vec(body).unwrap_or_default(),
+        }
+    }
+
+    /// `{"errcode":0,"errmsg":"ok"}`
+    pub fn ok() -> Self {
+        Self::error(0, "ok")
+    }
+
+    /// A `200` response with the errcode and errmsg, as WeChat does.
+    pub fn error(errcode: i32, errmsg: &str) -> Self {
+        Self::json(&serde_json::json!({ "errcode": errcode, "errmsg": errmsg }))
+    }
+
+    /// A response with the raw body, e.g. a media file.
+    pub fn bytes<S: AsRef<str>>(content_type: S, body: Vec<u8>) -> Self {
+        MockResponse {
+            status: 200,
+            content_type: content_type.as_ref().to_owned(),
+            body,
+        }
+    }
+
+    /// Change the http status.
+    pub fn with_status(mut self, status: u16) -> Self {
+        self.status = status;
+        self
+    }
+}
+
+/// A request received by the mock server.
+#[derive(Debug, Clone)]
+pub struct RecordedRequest {
+    pub method: String,
+    /// The url path, without the query params.
+    pub path: String,
+    /// The decoded query params.
+    pub query: HashMap<String, String>,
+    /// The headers with lowercase names.
+    pub headers: HashMap<String, String>,
+    pub body: Vec<u8>,
+}
+
+impl RecordedRequest {
+    /// Parse the JSON body.
+    pub fn json<T: DeserializeOwned>(&self) -> SdkResult<T> {
+        Ok(serde_json::from_slice(&self.body)?)
+    }
+
+    /// The `access_token` query param.
+    pub fn access_token(&self) -> Option<&str> {
+        self.query.get("access_token").map(String::as_str)
+    }
+}
+
+#[derive(Debug, Default)]
+struct MockState {
+    once: HashMap<String, VecDeque<MockResponse>>,
+    always: HashMap<String, MockResponse>,
+    requests: Vec<RecordedRequest>,
+}
+
+impl MockState {
+    fn respond(&mut self, path: &str) -> MockResponse {
+        if let Some(res) = self.once.get_mut(path).and_then(VecDeque::pop_front) {
+            return res;
+        }
+        if let Some(res) = self.always.get(path) {
+            return res.clone();
+        }
+        match path {
+            "/cgi-bin/token" | "/cgi-bin/stable_token" => MockResponse::json(
+                &serde_json::json!({ "access_token": MOCK_ACCESS_TOKEN, "expires_in": 7200 }),
+            ),
+            _ => MockResponse::error(40066, "invalid url"),
+        }
+    }
+}
+
+/// A local http server mocking WeChat api, it's stopped when dropped.
+pub struct MockServer {
+    addr: SocketAddr,
+    state: Arc<Mutex<MockState>>,
+    handle: JoinHandle<()>,
+}
+
+impl MockServer {
+    /// Listen on a random local port.
+    pub async fn start() -> SdkResult<Self> {
+        let state = Arc::new(Mutex::new(MockState::default()));
+        let handler_state = state.clone();
+        let (addr, handle) = spawn_server(Arc::new(move |request: RecordedRequest| {
+            let mut state = handler_state.lock().unwrap();
+            let response = state.respond(&request.path);
+            state.requests.push(request);
+            Box::pin(async move { response })
+        }))
+        .await?;
+        Ok(MockServer {
+            addr,
+            state,
+            handle,
+        })
+    }
+
+    /// The base url to replace [API_BASE_URL][crate::wechat::API_BASE_URL].
+    pub fn base_url(&self) -> String {
+        format!("http://{}", self.addr)
+    }
+
+    /// A [WxSdkBuilder] sending the requests to the mock server.
+    pub fn sdk_builder<S: AsRef<str>>(&self, app_id: S, app_secret: S) -> WxSdkBuilder {
+        WxSdkBuilder::new(app_id, app_secret).base_url(self.base_url())
+    }
+
+    /// Reply every request to `path` with `response`.
+    pub fn mock<S: AsRef<str>>(&self, path: S, response: MockResponse) {
+        let mut state = self.state.lock().unwrap();
+        state.always.insert(path.as_ref().to_owned(), response);
+    }
+
+    /// Reply the next request to `path` with `response`, the queued responses are used before [MockServer::mock].
+    pub fn mock_once<S: AsRef<str>>(&self, path: S, response: MockResponse) {
+        let mut state = self.state.lock().unwrap();
+        state
+            .once
+            .entry(path.as_ref().to_owned())
+            .or_default()
+            .push_back(response);
+    }
+
+    /// Reply the requests with the recorded fixtures, the fixtures of the same path are replayed in order.
+    pub fn replay(&self, fixtures: &[Fixture]) {
+        for fixture in fixtures {
+            self.mock_once(&fixture.path, fixture.response());
+        }
+    }
+
+    /// Replay the fixtures saved by [FixtureRecorder::save].
+    pub fn replay_file<P: AsRef<std::path::Path>>(&self, path: P) -> SdkResult<()> {
+        self.replay(&Fixture::load(path)?);
+        Ok(())
+    }
+
+    /// All the received requests in order.
+    pub fn requests(&self) -> Vec<RecordedRequest> {
+        self.state.lock().unwrap().requests.clone()
+    }
+
+    /// The received requests to `path`.
+    pub fn requests_to<S: AsRef<str>>(&self, path: S) -> Vec<RecordedRequest> {
+        let state = self.state.lock().unwrap();
+        state
+            .requests
+            .iter()
+            .filter(|r| r.path == path.as_ref())
+            .cloned()
+            .collect()
+    }
+
+    /// Remove the scripted responses and the recorded requests.
+    pub fn reset(&self) {
+        *self.state.lock().unwrap() = MockState::default();
+    }
+}
+
+impl Drop for MockServer {
+    fn drop(&mut self) {
+        self.handle.abort();
+    }
+}
+
+/// Produce the response of a request.
+type Handler = Arc<dyn Fn(RecordedRequest) -> BoxFuture<'static, MockResponse> + Send + Sync>;
+
+/// Listen on a random local port and serve the requests by `handler`.
+async fn spawn_server(handler: Handler) -> SdkResult<(SocketAddr, JoinHandle<()>)> {
+    let listener = TcpListener::bind("127.0.0.1:0").await?;
+    let addr = listener.local_addr()?;
+    let handle = tokio::spawn(async move {
+        while let Ok((stream, _)) = listener.accept().await {
+            tokio::spawn(handle_connection(stream, handler.clone()));
+        }
+    });
+    Ok((addr, handle))
+}
+
+async fn handle_connection(mut stream: TcpStream, handler: Handler) {
+    let request = match read_request(&mut stream).await {
+        Some(request) => request,
+        None => return,
+    };
+    let response = handler(request).await;
+    let head = format!(
+        "HTTP/1.1 {} {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        response.status,
+        reason_phrase(response.status),
+        response.content_type,
+        response.body.len()
+    );
+    let _ = stream.write_all(head.as_bytes()).await;
+    let _ = stream.write_all(&response.body).await;
+    let _ = stream.shutdown().await;
+}
+
+async fn read_request(stream: &mut TcpStream) -> Option<RecordedRequest> {
+    let mut buf = Vec::new();
+    let mut chunk = [0u8; 4096];
+    let head_end = loop {
+        if let Some(pos) = buf.windows(4).position(|w| w == b"\r\n\r\n") {
+            break pos;
+        }
+        let n = stream.read(&mut chunk).await.ok()?;
+        if n == 0 {
+            return None;
+        }
+        buf.extend_from_slice(&chunk[..n]);
+    };
+    let head = String::from_utf8_lossy(&buf[..head_end]).to_string();
+    let mut lines = head.lines();
+    let mut request_line = lines.next()?.split_whitespace();
+    let method = request_line.next()?.to_owned();
+    let target = request_line.next()?;
+    let headers: HashMap<String, String> = lines
+        .filter_map(|l| l.split_once(':'))
+        .map(|(k, v)| (k.trim().to_lowercase(), v.trim().to_owned()))
+        .collect();
+
+    let mut body = buf.split_off(head_end + 4);
+    if headers
+        .get("transfer-encoding")
+        .is_some_and(|v| v.contains("chunked"))
+    {
+        while !body.ends_with(b"0\r\n\r\n") {
+            let n = stream.read(&mut chunk).await.ok()?;
+            if n == 0 {
+                break;
+            }
+            body.extend_from_slice(&chunk[..n]);
+        }
+        body = decode_chunked(&body);
+    } else {
+        let content_length = headers
+            .get("content-length")
+            .and_then(|v| v.parse::<usize>().ok())
+            .unwrap_or(0);
+        while body.len() < content_length {
+            let n = stream.read(&mut chunk).await.ok()?;
+            if n == 0 {
+                break;
+            }
+            body.extend_from_slice(&chunk[..n]);
+        }
+    }
+
+    let url = url::Url::parse(&format!("http://localhost{}", target)).ok()?;
+    Some(RecordedRequest {
+        method,
+        path: url.path().to_owned(),
+        query: url.query_pairs().into_owned().collect(),
+        headers,
+        body,
+    })
+}
+
+fn decode_chunked(mut data: &[u8]) -> Vec<u8> {
+    let mut body = Vec::new();
+    while let Some(pos) = data.windows(2).position(|w| w == b"\r\n") {
+        let size = String::from_utf8_lossy(&data[..pos]);
+        let size =
+            usize::from_str_radix(size.split(';').next().unwrap_or("").trim(), 16).unwrap_or(0);
+        data = &data[pos + 2..];
+        if size == 0 || data.len() < size {
+            break;
+        }
+        body.extend_from_slice(&data[..size]);
+        data = data.get(size + 2..).unwrap_or_default();
+    }
+    body
+}
+
+fn reason_phrase(status: u16) -> &'static str {
+    reqwest::StatusCode::from_u16(status)
+        .ok()
+        .and_then(|s| s.canonical_reason())
+        .unwrap_or("Unknown")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        access_token::AccessTokenProvider,
+        error::{CommonError, SdkError},
+        wechat::WxApiRequestBuilder,
+    };
+
+    #[tokio::test]
+    async fn test_mock_server() -> SdkResult<()> {
+        let server = MockServer::start().await?;
+        server.mock_once(
+            "/cgi-bin/menu/delete",
+            MockResponse::error(40003, "invalid openid"),
+        );
+        server.mock("/cgi-bin/menu/delete", MockResponse::ok());
+
+        let sdk = server.sdk_builder("app_id", "app_secret").build()?;
+        let at = sdk.token_client.get_access_token().await?;
+        assert_eq!(at.access_token, MOCK_ACCESS_TOKEN);
+
+        let url = "https://api.weixin.qq.com/cgi-bin/menu/delete";
+        let send = || async {
+            let res: CommonError = sdk.wx_get(url).await?.send().await?.json().await?;
+            SdkResult::<()>::from(res)
+        };
+        assert!(matches!(send().await, Err(SdkError::WxApiError(e)) if e.errcode == 40003));
+        assert!(send().await.is_ok());
+
+        let requests = server.requests_to("/cgi-bin/menu/delete");
+        assert_eq!(requests.len(), 2);
+        assert_eq!(requests[0].method, "GET");
+        assert_eq!(requests[0].access_token(), Some(MOCK_ACCESS_TOKEN));
+        assert_eq!(server.requests()[0].path, "/cgi-bin/token");
+        assert_eq!(server.requests()[0].query["appid"], "app_id");
+
+        server.reset();
+        assert!(server.requests().is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_decode_chunked() {
+        assert_eq!(
+            decode_chunked(b"4\r\nWiki\r\n5\r\npedia\r\n0\r\n\r\n"),
+            b"Wikipedia"
+        );
+    }
+}
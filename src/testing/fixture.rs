@@ -0,0 +1,352 @@
+//! Record the real api responses to fixture files, and replay them by [MockServer][super::MockServer].
+//!
+//! The [FixtureRecorder] is a local proxy in front of WeChat api server, build the sdk with [FixtureRecorder::sdk_builder]
+//! to record the calls. The access token and the other secrets are scrubbed from the fixtures,
+//! and the token requests are not recorded, because [MockServer][super::MockServer] serves them.
+//!
+//! Example
+//! ```no_run
+//! use wx_sdk::mp::{datacube::TimeSpan, EncodingMode, ServerConfig};
+//! use wx_sdk::testing::{FixtureRecorder, MockServer};
+//! use wx_sdk::wechat::API_BASE_URL;
+//! # async fn run() -> wx_sdk::SdkResult<()> {
+//! // record
+//! let recorder = FixtureRecorder::start(API_BASE_URL).await?;
+//! let sdk = recorder.sdk_builder("app_id", "app_secret").build()?;
+//! let mp = sdk.mp(ServerConfig::new("token", EncodingMode::Plain));
+//! let time = TimeSpan {
+//!     begin_date: "2021-08-01".to_owned(),
+//!     end_date: "2021-08-07".to_owned(),
+//! };
+//! mp.datacube().get_user_summary(&time).await?;
+//! recorder.save("tests/fixtures/datacube.json")?;
+//!
+//! // replay
+//! let server = MockServer::start().await?;
+//! server.replay_file("tests/fixtures/datacube.json")?;
+//! let sdk = server.sdk_builder("app_id", "app_secret").build()?;
+//! # Ok(())
+//! # }
+//! ```
+use std::{
+    collections::BTreeMap,
+    net::SocketAddr,
+    path::Path,
+    sync::{Arc, Mutex},
+};
+
+use base64ct::{Base64, Encoding};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tokio::task::JoinHandle;
+
+use super::{spawn_server, MockResponse, RecordedRequest};
+use crate::{SdkResult, WxSdkBuilder};
+
+/// The values of these keys in the query params and the JSON bodies are replaced by `***`.
+const SECRET_KEYS: [&str; 15] = [
+    "access_token",
+    "secret",
+    "appsecret",
+    "session_key",
+    "refresh_token",
+    "ticket",
+    "code",
+    "js_code",
+    "pre_auth_code",
+    "authorization_code",
+    "component_appsecret",
+    "component_access_token",
+    "component_verify_ticket",
+    "authorizer_access_token",
+    "authorizer_refresh_token",
+];
+
+/// The token requests are not recorded.
+const TOKEN_PATHS: [&str; 2] = ["/cgi-bin/token", "/cgi-bin/stable_token"];
+
+const SCRUBBED: &str = "***";
+
+/// A recorded request and response pair.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Fixture {
+    pub method: String,
+    pub path: String,
+    /// The query params without `access_token`.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub query: BTreeMap<String, String>,
+    /// The JSON request body.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub request_body: Option<Value>,
+    pub status: u16,
+    pub content_type: String,
+    /// The JSON response body.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub response_body: Option<Value>,
+    /// The other response body, e.g. a media file, in base64.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub response_base64: Option<String>,
+}
+
+impl Fixture {
+    fn record(request: &RecordedRequest, response: &MockResponse) -> Self {
+        let query = request
+            .query
+            .iter()
+            .filter(|(k, _)| k.as_str() != "access_token")
+            .map(|(k, v)| {
+                let v = if SECRET_KEYS.contains(&k.as_str()) {
+                    SCRUBBED.to_owned()
+                } else {
+                    v.clone()
+                };
+                (k.clone(), v)
+            })
+            .collect();
+        let request_body = serde_json::from_slice(&request.body).ok().map(scrub);
+        let response_body: Option<Value> = serde_json::from_slice(&response.body).ok().map(scrub);
+        let response_base64 = match response_body {
+            None if !response.body.is_empty() => Some(Base64::encode_string(&response.body)),
+            _ => None,
+        };
+        Fixture {
+            method: request.method.clone(),
+            path: request.path.clone(),
+            query,
+            request_body,
+            status: response.status,
+            content_type: response.content_type.clone(),
+            response_body,
+            response_base64,
+        }
+    }
+
+    /// The response to replay.
+    pub fn response(&self) -> MockResponse {
+        let body = match (&self.response_body, &self.response_base64) {
+            (Some(json), _) => serde_json::to_vec(json).unwrap_or_default(),
+            (None, Some(base64)) => Base64::decode_vec(base64).unwrap_or_default(),
+            (None, None) => Vec::new(),
+        };
+        MockResponse {
+            status: self.status,
+            content_type: self.content_type.clone(),
+            body,
+        }
+    }
+
+    /// Load the fixtures saved by [FixtureRecorder::save].
+    pub fn load<P: AsRef<Path>>(path: P) -> SdkResult<Vec<Fixture>> {
+        let content = std::fs::read(path)?;
+        Ok(serde_json::from_slice(&content)?)
+    }
+}
+
+/// Replace the values of [SECRET_KEYS] recursively.
+fn scrub(value: Value) -> Value {
+    match value {
+        Value::Object(map) => Value::Object(
+            map.into_iter()
+                .map(|(k, v)| {
+                    let v = if SECRET_KEYS.contains(&k.as_str()) {
+                        Value::String(SCRUBBED.to_owned())
+                    } else {
+                        scrub(v)
+                    };
+                    (k, v)
+                })
+                .collect(),
+        ),
+        Value::Array(values) => Value::Array(values.into_iter().map(scrub).collect()),
+        value => value,
+    }
+}
+
+/// A local proxy recording the api calls to the upstream server, it's stopped when dropped.
+pub struct FixtureRecorder {
+    addr: SocketAddr,
+    fixtures: Arc<Mutex<Vec<Fixture>>>,
+    handle: JoinHandle<()>,
+}
+
+impl FixtureRecorder {
+    /// Forward the requests to `upstream`, e.g. [API_BASE_URL][crate::wechat::API_BASE_URL].
+    pub async fn start<S: AsRef<str>>(upstream: S) -> SdkResult<Self> {
+        let upstream = upstream.as_ref().trim_end_matches('/').to_owned();
+        let client = reqwest::Client::new();
+        let fixtures = Arc::new(Mutex::new(Vec::new()));
+        let recorded = fixtures.clone();
+        let (addr, handle) = spawn_server(Arc::new(move |request: RecordedRequest| {
+            let upstream = upstream.clone();
+            let client = client.clone();
+            let recorded = recorded.clone();
+            Box::pin(async move {
+                let response = match forward(&client, &upstream, &request).await {
+                    Ok(response) => response,
+                    Err(e) => return MockResponse::error(-1, &e.to_string()).with_status(502),
+                };
+                if !TOKEN_PATHS.contains(&request.path.as_str()) {
+                    let fixture = Fixture::record(&request, &response);
+                    recorded.lock().unwrap().push(fixture);
+                }
+                response
+            })
+        }))
+        .await?;
+        Ok(FixtureRecorder {
+            addr,
+            fixtures,
+            handle,
+        })
+    }
+
+    /// The base url of the proxy.
+    pub fn base_url(&self) -> String {
+        format!("http://{}", self.addr)
+    }
+
+    /// A [WxSdkBuilder] sending the requests through the proxy.
+    pub fn sdk_builder<S: AsRef<str>>(&self, app_id: S, app_secret: S) -> WxSdkBuilder {
+        WxSdkBuilder::new(app_id, app_secret).base_url(self.base_url())
+    }
+
+    /// The recorded fixtures in order.
+    pub fn fixtures(&self) -> Vec<Fixture> {
+        self.fixtures.lock().unwrap().clone()
+    }
+
+    /// Save the recorded fixtures as a JSON file.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> SdkResult<()> {
+        let path = path.as_ref();
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let content = serde_json::to_vec_pretty(&self.fixtures())?;
+        std::fs::write(path, content)?;
+        Ok(())
+    }
+}
+
+impl Drop for FixtureRecorder {
+    fn drop(&mut self) {
+        self.handle.abort();
+    }
+}
+
+async fn forward(
+    client: &reqwest::Client,
+    upstream: &str,
+    request: &RecordedRequest,
+) -> SdkResult<MockResponse> {
+    let method = reqwest::Method::from_bytes(request.method.as_bytes())
+        .map_err(|e| crate::error::SdkError::InvalidParams(e.to_string()))?;
+    let mut builder = client
+        .request(method, format!("{}{}", upstream, request.path))
+        .query(&request.query)
+        .body(request.body.clone());
+    if let Some(content_type) = request.headers.get("content-type") {
+        builder = builder.header(reqwest::header::CONTENT_TYPE, content_type);
+    }
+    let res = builder.send().await?;
+    let status = res.status().as_u16();
+    let content_type = res
+        .headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or_default()
+        .to_owned();
+    let body = res.bytes().await?.to_vec();
+    Ok(MockResponse {
+        status,
+        content_type,
+        body,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        error::CommonResponse,
+        testing::{MockServer, MOCK_ACCESS_TOKEN},
+        wechat::WxApiRequestBuilder,
+    };
+
+    #[tokio::test]
+    async fn test_record_and_replay() -> SdkResult<()> {
+        let url = "https://api.weixin.qq.com/datacube/getusersummary";
+        let summary = serde_json::json!({
+            "list": [{ "ref_date": "2014-12-07", "user_source": 0, "new_user": 0, "cancel_user": 0 }],
+            "session_key": "SECRET_SESSION_KEY"
+        });
+        let upstream = MockServer::start().await?;
+        upstream.mock("/datacube/getusersummary", MockResponse::json(&summary));
+
+        let recorder = FixtureRecorder::start(upstream.base_url()).await?;
+        let sdk = recorder.sdk_builder("app_id", "app_secret").build()?;
+        let body = serde_json::json!({ "begin_date": "2014-12-02", "end_date": "2014-12-07" });
+        let recorded: Value = sdk
+            .wx_post(url)
+            .await?
+            .json(&body)
+            .send()
+            .await?
+            .json()
+            .await?;
+        assert_eq!(recorded, summary);
+
+        let file = std::env::temp_dir()
+            .join(format!("wx_sdk_fixture_{}", rand::random::<u32>()))
+            .join("datacube.json");
+        recorder.save(&file)?;
+        let content = std::fs::read_to_string(&file)?;
+        assert!(!content.contains(MOCK_ACCESS_TOKEN));
+        assert!(!content.contains("SECRET_SESSION_KEY"));
+        let fixtures = Fixture::load(&file)?;
+        std::fs::remove_dir_all(file.parent().unwrap())?;
+        assert_eq!(fixtures.len(), 1);
+        assert_eq!(fixtures[0].request_body, Some(body.clone()));
+
+        let server = MockServer::start().await?;
+        server.replay(&fixtures);
+        let sdk = server.sdk_builder("app_id", "app_secret").build()?;
+        let replayed: CommonResponse<Value> = sdk
+            .wx_post(url)
+            .await?
+            .json(&body)
+            .send()
+            .await?
+            .json()
+            .await?;
+        let replayed: Value = SdkResult::from(replayed)?;
+        assert_eq!(replayed["list"], summary["list"]);
+        assert_eq!(replayed["session_key"], SCRUBBED);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_scrub_query() -> SdkResult<()> {
+        let upstream = MockServer::start().await?;
+        let recorder = FixtureRecorder::start(upstream.base_url()).await?;
+        let sdk = recorder.sdk_builder("app_id", "app_secret").build()?;
+        sdk.wx_get("https://api.weixin.qq.com/sns/jscode2session")
+            .await?
+            .query(&[
+                ("appid", "app_id"),
+                ("secret", "app_secret"),
+                ("js_code", "SECRET_JS_CODE"),
+            ])
+            .send()
+            .await?;
+
+        let fixtures = recorder.fixtures();
+        assert_eq!(fixtures.len(), 1);
+        assert_eq!(fixtures[0].query["appid"], "app_id");
+        assert_eq!(fixtures[0].query["secret"], SCRUBBED);
+        assert_eq!(fixtures[0].query["js_code"], SCRUBBED);
+        let content = serde_json::to_string(&fixtures)?;
+        assert!(!content.contains("SECRET_JS_CODE"));
+        assert!(!content.contains("app_secret"));
+        Ok(())
+    }
+}
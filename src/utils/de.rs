@@ -0,0 +1,111 @@
+//! Deserialize helpers for the pushed messages, the same field could be a string in XML and a number in JSON.
+use std::{fmt::Display, str::FromStr};
+
+use serde::{de, Deserialize, Deserializer};
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum StrOrNum<T> {
+    Str(String),
+    Num(T),
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum OneOrMany<T> {
+    Many(Vec<T>),
+    One(T),
+}
+
+/// Deserialize a number from a number or a numeric string.
+pub(crate) fn number_from_str<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+where
+    D: Deserializer<'de>,
+    T: FromStr + Deserialize<'de>,
+    T::Err: Display,
+{
+    match StrOrNum::<T>::deserialize(deserializer)? {
+        StrOrNum::Str(s) => s.trim().parse::<T>().map_err(de::Error::custom),
+        StrOrNum::Num(n) => Ok(n),
+    }
+}
+
+/// Like [number_from_str], but for an optional field.
+#[cfg(feature = "wxa")]
+pub(crate) fn option_number_from_str<'de, D, T>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: FromStr + Deserialize<'de>,
+    T::Err: Display,
+{
+    match Option::<StrOrNum<T>>::deserialize(deserializer)? {
+        Some(StrOrNum::Str(s)) if s.trim().is_empty() => Ok(None),
+        Some(StrOrNum::Str(s)) => s.trim().parse::<T>().map(Some).map_err(de::Error::custom),
+        Some(StrOrNum::Num(n)) => Ok(Some(n)),
+        None => Ok(None),
+    }
+}
+
+/// Deserialize a list from a single item or an array, a repeated XML tag with only one item is not an array.
+pub(crate) fn one_or_many<'de, D, T>(deserializer: D) -> Result<Vec<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    match Option::<OneOrMany<T>>::deserialize(deserializer)? {
+        Some(OneOrMany::Many(v)) => Ok(v),
+        Some(OneOrMany::One(t)) => Ok(vec![t]),
+        None => Ok(vec![]),
+    }
+}
+
+/// Deserialize an optional string, the empty string is treated as `None`.
+pub(crate) fn empty_string_as_none<'de, D>(deserializer: D) -> Result<Option<String>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let s = Option::<String>::deserialize(deserializer)?;
+    Ok(s.filter(|s| !s.trim().is_empty()))
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum ValueOrStr<T> {
+    Value(T),
+    Str(String),
+}
+
+/// Deserialize the default value from an empty string, an empty XML element is converted to an empty string.
+pub(crate) fn empty_as_default<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de> + Default,
+{
+    match Option::<ValueOrStr<T>>::deserialize(deserializer)? {
+        Some(ValueOrStr::Value(v)) => Ok(v),
+        Some(ValueOrStr::Str(s)) if s.trim().is_empty() => Ok(T::default()),
+        Some(ValueOrStr::Str(s)) => Err(de::Error::custom(format!("unexpected string `{}`", s))),
+        None => Ok(T::default()),
+    }
+}
+
+/// Like [one_or_many], but each item is deserialized by [number_from_str].
+pub(crate) fn numbers_from_str<'de, D, T>(deserializer: D) -> Result<Vec<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: FromStr + Deserialize<'de>,
+    T::Err: Display,
+{
+    let items = match Option::<OneOrMany<StrOrNum<T>>>::deserialize(deserializer)? {
+        Some(OneOrMany::Many(v)) => v,
+        Some(OneOrMany::One(t)) => vec![t],
+        None => vec![],
+    };
+    items
+        .into_iter()
+        .map(|item| match item {
+            StrOrNum::Str(s) => s.trim().parse::<T>().map_err(de::Error::custom),
+            StrOrNum::Num(n) => Ok(n),
+        })
+        .collect()
+}
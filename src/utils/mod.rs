@@ -1,6 +1,7 @@
 use rand::{distributions::Alphanumeric, thread_rng, Rng};
 
 pub(crate) mod base64;
+pub(crate) mod de;
 
 pub(crate) fn get_random_string(size: usize) -> String {
     let rand_string: String = thread_rng()
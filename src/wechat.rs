@@ -12,11 +12,25 @@
 //! let token_clinet = MyTokenClient{};
 //! let sdk = WxSdk::new("app_id", "app_sercret", config, token_client);
 //! ```
+//! Use [WxSdkBuilder] to customize the http client and the api base url.
+//! ```rust
+//! use std::time::Duration;
+//! use wx_sdk::wechat::WxSdkBuilder;
+//! let sdk = WxSdkBuilder::new("app_id", "app_secret")
+//!     .base_url("https://sh.api.weixin.qq.com")
+//!     .timeout(Duration::from_secs(10))
+//!     .build()
+//!     .unwrap();
+//! ```
 
-use std::sync::Arc;
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
 
 use async_trait::async_trait;
 use reqwest::Client;
+use serde::{Deserialize, Serialize};
 
 #[cfg(feature = "mp")]
 use crate::mp::{MpSdk, ServerConfig};
@@ -24,7 +38,30 @@ use crate::mp::{MpSdk, ServerConfig};
 #[cfg(feature = "wxa")]
 use crate::wxa::WxaSdk;
 
-use crate::{access_token::AccessTokenProvider, cache::Cache, SdkResult, TokenClient};
+#[cfg(feature = "pay")]
+use crate::pay::{PaySdk, PaySdkConfig};
+
+use crate::{
+    access_token::{AccessTokenProvider, TokenStore},
+    cache::Cache,
+    error::{CommonError, SdkError, WxErrorCode},
+    AccessToken, SdkResult, TokenClient,
+};
+
+mod failover;
+mod observe;
+mod rate_limit;
+mod retry;
+
+pub(crate) use failover::DomainPool;
+pub use failover::{DomainHealth, FailoverPolicy, API_DOMAINS};
+pub use observe::{redact_url, ApiCall, ApiHook};
+pub(crate) use rate_limit::RateLimiter;
+pub use rate_limit::{RateLimit, RateLimitMode, RateLimitUsage};
+pub use retry::RetryPolicy;
+
+/// The default base url of wechat api server, the urls in every module start with it.
+pub const API_BASE_URL: &str = "https://api.weixin.qq.com";
 
 /// This is the sdk object. We provide a `new` method to construct it.
 #[derive(Clone)]
@@ -34,6 +71,13 @@ pub struct WxSdk<T: AccessTokenProvider> {
     pub(crate) http_client: Client,
     pub(crate) token_client: T,
     pub(crate) cache: Arc<Cache<String, String>>,
+    pub(crate) domains: Arc<DomainPool>,
+    pub(crate) retry: Arc<RetryPolicy>,
+    pub(crate) rate_limiter: Option<Arc<RateLimiter>>,
+    pub(crate) api_hook: Option<ApiHook>,
+    /// The appid of the open platform component calling apis on behalf of this account,
+    /// the pushed messages are encrypted with it.
+    pub(crate) component_app_id: Option<String>,
 }
 
 impl<T: AccessTokenProvider> WxSdk<T> {
@@ -44,9 +88,63 @@ impl<T: AccessTokenProvider> WxSdk<T> {
             app_secret: app_secret.as_ref().to_owned(),
             token_client,
             cache: Arc::new(Cache::new()),
+            domains: Arc::new(DomainPool::new(FailoverPolicy::single(API_BASE_URL))),
+            retry: Arc::new(RetryPolicy::none()),
+            rate_limiter: None,
+            api_hook: None,
+            component_app_id: None,
+        }
+    }
+
+    /// A sdk of another account sharing the http client, api domains, retry policy and hook.
+    /// The rate limits are not shared, they're counted by account.
+    #[cfg(feature = "open")]
+    pub(crate) fn with_token_client<S: AsRef<str>, U: AccessTokenProvider>(
+        &self,
+        app_id: S,
+        token_client: U,
+    ) -> WxSdk<U> {
+        WxSdk {
+            http_client: self.http_client.clone(),
+            app_id: app_id.as_ref().to_owned(),
+            app_secret: String::new(),
+            token_client,
+            cache: Arc::new(Cache::new()),
+            domains: self.domains.clone(),
+            retry: self.retry.clone(),
+            rate_limiter: None,
+            api_hook: self.api_hook.clone(),
+            component_app_id: None,
+        }
+    }
+
+    /// Replace the [API_BASE_URL] of `url` with the configured base url.
+    pub(crate) fn api_url(&self, url: &str) -> String {
+        match url.strip_prefix(API_BASE_URL) {
+            Some(path) => format!("{}{}", self.domains.primary(), path),
+            None => url.to_owned(),
         }
     }
 
+    /// The appid in the encrypted messages, it's the component appid for an authorized account.
+    #[cfg(feature = "mp")]
+    pub(crate) fn msg_app_id(&self) -> &str {
+        self.component_app_id.as_deref().unwrap_or(&self.app_id)
+    }
+
+    /// The health status of the api domains configured by [WxSdkBuilder::failover].
+    pub fn domain_health(&self) -> Vec<DomainHealth> {
+        self.domains.health()
+    }
+
+    /// The usage of the limits configured by [WxSdkBuilder::rate_limit], empty if there is none.
+    pub fn rate_limit_usage(&self) -> Vec<RateLimitUsage> {
+        self.rate_limiter
+            .as_ref()
+            .map(|limiter| limiter.usage())
+            .unwrap_or_default()
+    }
+
     /// Official account(Media Press) module
     #[cfg(feature = "mp")]
     pub fn mp(self, server_config: ServerConfig) -> MpSdk<T> {
@@ -59,7 +157,25 @@ impl<T: AccessTokenProvider> WxSdk<T> {
     /// miniprogram module
     #[cfg(feature = "wxa")]
     pub fn wxa(self) -> WxaSdk<T> {
-        WxaSdk { sdk: self }
+        WxaSdk {
+            sdk: self,
+            server_config: None,
+        }
+    }
+
+    /// miniprogram module with message push server configuration
+    #[cfg(feature = "wxa")]
+    pub fn wxa_with_server_config(self, server_config: ServerConfig) -> WxaSdk<T> {
+        WxaSdk {
+            sdk: self,
+            server_config: Some(server_config),
+        }
+    }
+
+    /// wechat pay module
+    #[cfg(feature = "pay")]
+    pub fn pay(self, config: PaySdkConfig) -> PaySdk<T> {
+        PaySdk::new(self, config)
     }
 }
 
@@ -74,36 +190,797 @@ impl WxSdk<TokenClient> {
             app_secret,
             token_client,
             cache: Arc::new(Cache::new()),
+            domains: Arc::new(DomainPool::new(FailoverPolicy::single(API_BASE_URL))),
+            retry: Arc::new(RetryPolicy::none()),
+            rate_limiter: None,
+            api_hook: None,
+            component_app_id: None,
+        }
+    }
+}
+
+/// Builder of [WxSdk] with a custom http client and api base url.
+pub struct WxSdkBuilder {
+    app_id: String,
+    app_secret: String,
+    http_client: Option<Client>,
+    failover: FailoverPolicy,
+    retry: RetryPolicy,
+    rate_limits: Vec<RateLimit>,
+    api_hook: Option<ApiHook>,
+    token_store: Option<Arc<dyn TokenStore>>,
+    timeout: Option<Duration>,
+}
+
+impl WxSdkBuilder {
+    pub fn new<S: AsRef<str>>(app_id: S, app_secret: S) -> Self {
+        WxSdkBuilder {
+            app_id: app_id.as_ref().to_owned(),
+            app_secret: app_secret.as_ref().to_owned(),
+            http_client: None,
+            failover: FailoverPolicy::single(API_BASE_URL),
+            retry: RetryPolicy::none(),
+            rate_limits: Vec::new(),
+            api_hook: None,
+            token_store: None,
+            timeout: None,
+        }
+    }
+
+    /// Use a custom [reqwest::Client], e.g. with proxy or TLS configuration.
+    pub fn http_client(mut self, http_client: Client) -> Self {
+        self.http_client = Some(http_client);
+        self
+    }
+
+    /// Replace [API_BASE_URL], e.g. the regional domain `https://sh.api.weixin.qq.com` or a local mock server.
+    pub fn base_url<S: AsRef<str>>(mut self, base_url: S) -> Self {
+        self.failover = FailoverPolicy::single(base_url);
+        self
+    }
+
+    /// Switch to the next api domain when a request fails, see [FailoverPolicy].
+    pub fn failover(mut self, policy: FailoverPolicy) -> Self {
+        self.failover = policy;
+        self
+    }
+
+    /// Retry the failed api calls, see [RetryPolicy]. It's [RetryPolicy::none] by default.
+    ///
+    /// Every attempt goes through the [FailoverPolicy], so a call sends at most
+    /// `RetryPolicy::max_attempts * FailoverPolicy::max_attempts` requests.
+    pub fn retry(mut self, policy: RetryPolicy) -> Self {
+        self.retry = policy;
+        self
+    }
+
+    /// Add a client side rate limit, a call is limited by all the limits matching its url path.
+    /// Building fails with [SdkError::InvalidParams] if a limit has zero capacity.
+    /// ```rust
+    /// use std::time::Duration;
+    /// use wx_sdk::wechat::{RateLimit, WxSdkBuilder};
+    /// let sdk = WxSdkBuilder::new("app_id", "app_secret")
+    ///     .rate_limit(RateLimit::per_app(10_000, Duration::from_secs(60)))
+    ///     .rate_limit(RateLimit::per_path("/cgi-bin/message/template/send", 100_000, Duration::from_secs(86400)).reject())
+    ///     .build()
+    ///     .unwrap();
+    /// ```
+    pub fn rate_limit(mut self, limit: RateLimit) -> Self {
+        self.rate_limits.push(limit);
+        self
+    }
+
+    /// Register a callback receiving every [ApiCall], e.g. to export the latency and errcode metrics.
+    /// ```rust
+    /// use wx_sdk::wechat::WxSdkBuilder;
+    /// let sdk = WxSdkBuilder::new("app_id", "app_secret")
+    ///     .on_api_call(|call| println!("{} {} {:?} {:?}", call.method, call.path, call.errcode, call.duration))
+    ///     .build()
+    ///     .unwrap();
+    /// ```
+    pub fn on_api_call<F: Fn(&ApiCall) + Send + Sync + 'static>(mut self, hook: F) -> Self {
+        self.api_hook = Some(Arc::new(hook));
+        self
+    }
+
+    /// The [TokenStore] of the default [TokenClient], e.g. shared by many accounts or processes.
+    pub fn token_store(mut self, store: Arc<dyn TokenStore>) -> Self {
+        self.token_store = Some(store);
+        self
+    }
+
+    /// Timeout of the default http client, configure it on the client when using [WxSdkBuilder::http_client].
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    fn build_http_client(&self) -> SdkResult<Client> {
+        match (&self.http_client, self.timeout) {
+            (Some(client), None) => Ok(client.clone()),
+            (Some(_), Some(_)) => Err(SdkError::InvalidParams(
+                "timeout should be configured on the custom http client".to_owned(),
+            )),
+            (None, timeout) => {
+                let mut builder = Client::builder();
+                if let Some(timeout) = timeout {
+                    builder = builder.timeout(timeout);
+                }
+                Ok(builder.build()?)
+            }
+        }
+    }
+
+    /// Build with the default [TokenClient], which uses the same http client and base url.
+    pub fn build(self) -> SdkResult<WxSdk<TokenClient>> {
+        let http_client = self.build_http_client()?;
+        let domains = Arc::new(DomainPool::new(self.failover.clone()));
+        let (app_id, app_secret) = (self.app_id.clone(), self.app_secret.clone());
+        let token_client = match self.token_store.clone() {
+            Some(store) => TokenClient::with_store(app_id, app_secret, store),
+            None => TokenClient::new(app_id, app_secret),
+        };
+        let token_client = token_client
+            .with_http_client(http_client.clone())
+            .with_domains(domains.clone());
+        self.build_inner(http_client, token_client, domains)
+    }
+
+    /// Build with a custom [AccessTokenProvider], the [WxSdkBuilder::token_store] is ignored.
+    pub fn build_with_token_client<T: AccessTokenProvider>(
+        self,
+        token_client: T,
+    ) -> SdkResult<WxSdk<T>> {
+        let http_client = self.build_http_client()?;
+        let domains = Arc::new(DomainPool::new(self.failover.clone()));
+        self.build_inner(http_client, token_client, domains)
+    }
+
+    fn build_inner<T: AccessTokenProvider>(
+        self,
+        http_client: Client,
+        token_client: T,
+        domains: Arc<DomainPool>,
+    ) -> SdkResult<WxSdk<T>> {
+        let rate_limiter = if self.rate_limits.is_empty() {
+            None
+        } else {
+            Some(Arc::new(RateLimiter::new(self.rate_limits)?))
+        };
+        Ok(WxSdk {
+            app_id: self.app_id,
+            app_secret: self.app_secret,
+            http_client,
+            token_client,
+            cache: Arc::new(Cache::new()),
+            domains,
+            retry: Arc::new(self.retry),
+            rate_limiter,
+            api_hook: self.api_hook,
+            component_app_id: None,
+        })
+    }
+}
+
+/// Object safe part of [AccessTokenProvider] used by [WxApiRequest].
+#[async_trait]
+trait TokenRenewer: Send + Sync {
+    async fn renew(&self, stale_token: &str) -> SdkResult<AccessToken>;
+}
+
+#[async_trait]
+impl<T: AccessTokenProvider> TokenRenewer for T {
+    async fn renew(&self, stale_token: &str) -> SdkResult<AccessToken> {
+        self.invalidate(stale_token).await?;
+        self.get_access_token().await
+    }
+}
+
+/// A request to wechat api server with the `access_token` query param.
+///
+/// When the response errcode [means the token is invalid or expired][WxErrorCode::is_token_error],
+/// the token is invalidated and the request is replayed once with a new token.
+/// The transient failures are retried by the [RetryPolicy] of [WxSdk] if it's enabled,
+/// the non-idempotent requests are only retried when the connection failed.
+/// Every attempt takes a token from the [RateLimit]s matching the url path, and is reported to the [ApiHook].
+/// The request with a multipart body can't be replayed or retried.
+pub struct WxApiRequest<'a> {
+    builder: reqwest::RequestBuilder,
+    http_client: &'a Client,
+    domains: &'a DomainPool,
+    retry: &'a RetryPolicy,
+    rate_limiter: Option<&'a RateLimiter>,
+    api_hook: Option<&'a ApiHook>,
+    idempotent: Option<bool>,
+    token_client: &'a dyn TokenRenewer,
+    /// The `access_token` of the app in the url, `None` for the apis authorized otherwise.
+    access_token: Option<String>,
+}
+
+impl<'a> WxApiRequest<'a> {
+    /// Add url query params, same as [reqwest::RequestBuilder::query].
+    pub fn query<Q: Serialize + ?Sized>(mut self, query: &Q) -> Self {
+        self.builder = self.builder.query(query);
+        self
+    }
+
+    /// Set a JSON body, same as [reqwest::RequestBuilder::json].
+    pub fn json<B: Serialize + ?Sized>(mut self, json: &B) -> Self {
+        self.builder = self.builder.json(json);
+        self
+    }
+
+    /// Set a url encoded form body, same as [reqwest::RequestBuilder::form].
+    pub fn form<F: Serialize + ?Sized>(mut self, form: &F) -> Self {
+        self.builder = self.builder.form(form);
+        self
+    }
+
+    /// Set a multipart body, same as [reqwest::RequestBuilder::multipart].
+    pub fn multipart(mut self, multipart: reqwest::multipart::Form) -> Self {
+        self.builder = self.builder.multipart(multipart);
+        self
+    }
+
+    /// Override whether the request is safe to retry, it's decided by [RetryPolicy::non_idempotent_paths] by default.
+    pub fn idempotent(mut self, idempotent: bool) -> Self {
+        self.idempotent = Some(idempotent);
+        self
+    }
+
+    /// Send the request, replay it once if the access token is invalid or expired,
+    /// and retry it on the transient failures.
+    pub async fn send(self) -> SdkResult<reqwest::Response> {
+        let mut request = self.builder.build()?;
+        let idempotent = self
+            .idempotent
+            .unwrap_or_else(|| self.retry.is_idempotent(&request));
+        let mut attempt = 1;
+        let mut replayed = false;
+        loop {
+            if let Some(limiter) = self.rate_limiter {
+                limiter.acquire(request.url().path()).await?;
+            }
+            let current = match request.try_clone() {
+                Some(current) => current,
+                None => {
+                    return Ok(execute(
+                        self.http_client,
+                        self.domains,
+                        self.api_hook,
+                        request,
+                        idempotent,
+                        attempt,
+                    )
+                    .await?
+                    .0)
+                }
+            };
+            let (res, errcode) = match execute(
+                self.http_client,
+                self.domains,
+                self.api_hook,
+                current,
+                idempotent,
+                attempt,
+            )
+            .await
+            {
+                Ok(res) => res,
+                Err(e) => {
+                    if attempt < self.retry.max_attempts
+                        && self.retry.should_retry_error(&e, idempotent)
+                    {
+                        tokio::time::sleep(self.retry.delay(attempt)).await;
+                        attempt += 1;
+                        continue;
+                    }
+                    return Err(e);
+                }
+            };
+            let status = res.status();
+            let stale_token = self.access_token.as_deref().filter(|_| !replayed);
+            if let Some(stale_token) = stale_token
+                .filter(|_| errcode.is_some_and(|c| WxErrorCode::from(c).is_token_error()))
+            {
+                // the token replay isn't counted as a retry
+                let at = self.token_client.renew(stale_token).await?;
+                set_access_token(request.url_mut(), &at.access_token);
+                replayed = true;
+                continue;
+            }
+            if attempt < self.retry.max_attempts
+                && self
+                    .retry
+                    .should_retry_response(status, errcode, idempotent)
+            {
+                tokio::time::sleep(self.retry.delay(attempt)).await;
+                attempt += 1;
+                continue;
+            }
+            return Ok(res);
         }
     }
 }
 
+/// Execute an attempt and report it, the JSON response is buffered to get the errcode.
+#[cfg_attr(not(feature = "tracing"), allow(unused_variables))]
+async fn execute(
+    http_client: &Client,
+    domains: &DomainPool,
+    api_hook: Option<&ApiHook>,
+    request: reqwest::Request,
+    idempotent: bool,
+    attempt: u32,
+) -> SdkResult<(reqwest::Response, Option<i32>)> {
+    let method = request.method().clone();
+    let path = request.url().path().to_owned();
+    #[cfg(feature = "tracing")]
+    let span = tracing::info_span!(
+        "wx_api",
+        %method,
+        url = %redact_url(request.url()),
+        attempt,
+        status = tracing::field::Empty,
+        errcode = tracing::field::Empty,
+        rid = tracing::field::Empty,
+    );
+    let start = Instant::now();
+    let result = async {
+        let res = domains.execute(http_client, request, idempotent).await?;
+        if is_json_response(&res) {
+            buffer_response(res).await
+        } else {
+            Ok((res, None))
+        }
+    };
+    #[cfg(feature = "tracing")]
+    let result = tracing::Instrument::instrument(result, span.clone());
+    let result = result.await;
+    let duration = start.elapsed();
+
+    let (status, error) = match &result {
+        Ok((res, error)) => (Some(res.status()), error.as_ref()),
+        Err(_) => (None, None),
+    };
+    let errcode = error.map(|e| e.errcode);
+    let rid = error.and_then(|e| e.rid());
+    #[cfg(feature = "tracing")]
+    span.in_scope(|| {
+        let duration_ms = duration.as_millis() as u64;
+        if let Some(status) = status {
+            span.record("status", status.as_u16());
+        }
+        if let Some(errcode) = errcode {
+            span.record("errcode", errcode);
+        }
+        if let Some(rid) = rid {
+            span.record("rid", rid);
+        }
+        match &result {
+            Err(e) => tracing::warn!(duration_ms, error = %e, "wechat api call failed"),
+            Ok(_) if errcode.is_some_and(|c| c != 0) => {
+                tracing::warn!(duration_ms, "wechat api call returned an error")
+            }
+            Ok(_) => tracing::debug!(duration_ms, "wechat api call"),
+        }
+    });
+    if let Some(hook) = api_hook {
+        hook(&ApiCall {
+            method,
+            path,
+            status,
+            duration,
+            errcode,
+            rid: rid.map(str::to_owned),
+        });
+    }
+    result.map(|(res, _)| (res, errcode))
+}
+
+/// The error responses are JSON, the media files are not checked.
+fn is_json_response(res: &reqwest::Response) -> bool {
+    res.headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.contains("json") || v.starts_with("text/plain"))
+}
+
+/// Read the body to get the errcode and errmsg, then rebuild the response for the caller.
+async fn buffer_response(
+    res: reqwest::Response,
+) -> SdkResult<(reqwest::Response, Option<CommonError>)> {
+    #[derive(Deserialize)]
+    struct ErrCode {
+        errcode: i32,
+        #[serde(default)]
+        errmsg: String,
+    }
+
+    let mut builder = http::Response::builder()
+        .status(res.status())
+        .version(res.version());
+    if let Some(headers) = builder.headers_mut() {
+        *headers = res.headers().clone();
+    }
+    let body = res.bytes().await?;
+    let error = serde_json::from_slice::<ErrCode>(&body)
+        .ok()
+        .map(|e| CommonError {
+            errcode: e.errcode,
+            errmsg: e.errmsg,
+        });
+    let res = builder
+        .body(body)
+        .map_err(|e| SdkError::InvalidParams(e.to_string()))?;
+    Ok((res.into(), error))
+}
+
+fn set_access_token(url: &mut reqwest::Url, access_token: &str) {
+    let pairs: Vec<(String, String)> = url
+        .query_pairs()
+        .filter(|(k, _)| k != "access_token")
+        .map(|(k, v)| (k.into_owned(), v.into_owned()))
+        .collect();
+    url.query_pairs_mut()
+        .clear()
+        .append_pair("access_token", access_token)
+        .extend_pairs(pairs);
+}
+
 /// This trait warps two common http request method that [wx_get][WxApiRequestBuilder::wx_get] and [wx_post][WxApiRequestBuilder::wx_post] with wechat api server.
 #[async_trait]
 pub trait WxApiRequestBuilder {
-    async fn wx_get(&self, url: &'static str) -> SdkResult<reqwest::RequestBuilder>;
-    async fn wx_post(&self, url: &'static str) -> SdkResult<reqwest::RequestBuilder>;
+    async fn wx_get(&self, url: &'static str) -> SdkResult<WxApiRequest<'_>>;
+    async fn wx_post(&self, url: &'static str) -> SdkResult<WxApiRequest<'_>>;
 }
 
-#[async_trait]
-impl<T: AccessTokenProvider> WxApiRequestBuilder for WxSdk<T> {
-    async fn wx_get(&self, url: &'static str) -> SdkResult<reqwest::RequestBuilder> {
+impl<T: AccessTokenProvider> WxSdk<T> {
+    async fn wx_request(
+        &self,
+        method: reqwest::Method,
+        url: &'static str,
+    ) -> SdkResult<WxApiRequest<'_>> {
         let at = self.token_client.get_access_token().await?;
-        let mut url = reqwest::Url::parse(url)?;
+        let mut url = reqwest::Url::parse(&self.api_url(url))?;
         url.query_pairs_mut()
             .append_pair("access_token", at.access_token.as_ref());
 
-        let builder = self.http_client.get(url);
-        Ok(builder)
+        Ok(WxApiRequest {
+            builder: self.http_client.request(method, url),
+            http_client: &self.http_client,
+            domains: &self.domains,
+            retry: &self.retry,
+            rate_limiter: self.rate_limiter.as_deref(),
+            api_hook: self.api_hook.as_ref(),
+            idempotent: None,
+            token_client: &self.token_client,
+            access_token: Some(at.access_token),
+        })
     }
 
-    async fn wx_post(&self, url: &'static str) -> SdkResult<reqwest::RequestBuilder> {
-        let at = self.token_client.get_access_token().await?;
-        let mut url = reqwest::Url::parse(url)?;
-        url.query_pairs_mut()
-            .append_pair("access_token", at.access_token.as_ref());
+    /// A request without the `access_token` of the app, e.g. authorized by the app secret or the user's token.
+    /// It's still rate limited, retried, failed over and reported, but not replayed on token errors.
+    pub(crate) fn wx_request_without_token(
+        &self,
+        method: reqwest::Method,
+        url: &str,
+    ) -> SdkResult<WxApiRequest<'_>> {
+        let url = reqwest::Url::parse(&self.api_url(url))?;
+        Ok(WxApiRequest {
+            builder: self.http_client.request(method, url),
+            http_client: &self.http_client,
+            domains: &self.domains,
+            retry: &self.retry,
+            rate_limiter: self.rate_limiter.as_deref(),
+            api_hook: self.api_hook.as_ref(),
+            idempotent: None,
+            token_client: &self.token_client,
+            access_token: None,
+        })
+    }
+}
+
+#[async_trait]
+impl<T: AccessTokenProvider> WxApiRequestBuilder for WxSdk<T> {
+    async fn wx_get(&self, url: &'static str) -> SdkResult<WxApiRequest<'_>> {
+        self.wx_request(reqwest::Method::GET, url).await
+    }
+
+    async fn wx_post(&self, url: &'static str) -> SdkResult<WxApiRequest<'_>> {
+        self.wx_request(reqwest::Method::POST, url).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use super::*;
+    use crate::error::{CommonError, CommonResponse};
+    use crate::testing::{MockResponse, MockServer, MOCK_ACCESS_TOKEN};
+
+    #[derive(Clone)]
+    struct StaleToken(Arc<Mutex<String>>);
+
+    #[async_trait]
+    impl AccessTokenProvider for StaleToken {
+        async fn get_access_token(&self) -> SdkResult<AccessToken> {
+            Ok(AccessToken {
+                access_token: self.0.lock().unwrap().clone(),
+                expires_in: 7200,
+            })
+        }
+
+        async fn invalidate(&self, access_token: &str) -> SdkResult<()> {
+            assert_eq!(access_token, "STALE");
+            *self.0.lock().unwrap() = "FRESH".to_owned();
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_replay_on_token_error() -> SdkResult<()> {
+        let server = MockServer::start().await?;
+        let path = "/cgi-bin/user/info";
+        server.mock_once(path, MockResponse::error(40001, "invalid credential"));
+        server.mock_once(path, MockResponse::ok());
+        server.mock(path, MockResponse::error(40003, "invalid openid"));
+
+        let token = StaleToken(Arc::new(Mutex::new("STALE".to_owned())));
+        let sdk = server
+            .sdk_builder("app_id", "app_secret")
+            .build_with_token_client(token.clone())?;
+        let res: CommonResponse<CommonError> = sdk
+            .wx_get("https://api.weixin.qq.com/cgi-bin/user/info")
+            .await?
+            .query(&[("openid", "o1")])
+            .send()
+            .await?
+            .json()
+            .await?;
+        assert_eq!(
+            res,
+            CommonResponse::Ok(CommonError {
+                errcode: 0,
+                errmsg: "ok".to_owned()
+            })
+        );
+        assert_eq!(token.0.lock().unwrap().as_str(), "FRESH");
+        let requests = server.requests_to(path);
+        assert_eq!(requests[0].access_token(), Some("STALE"));
+        assert_eq!(requests[1].access_token(), Some("FRESH"));
+        assert_eq!(requests[1].query["openid"], "o1");
+
+        // other errors are not replayed
+        let res: CommonResponse<CommonError> = sdk
+            .wx_get("https://api.weixin.qq.com/cgi-bin/user/info")
+            .await?
+            .send()
+            .await?
+            .json()
+            .await?;
+        let res: SdkResult<()> = res.into();
+        assert!(matches!(res, Err(SdkError::WxApiError(e)) if e.errcode == 40003));
+        assert_eq!(server.requests_to(path).len(), 3);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_builder() -> SdkResult<()> {
+        let server = MockServer::start().await?;
+        server.mock("/cgi-bin/user/info", MockResponse::ok());
+        server.mock(
+            "/cgi-bin/stable_token",
+            MockResponse::json(&serde_json::json!({
+                "access_token": "STABLE_TOKEN",
+                "expires_in": 7200
+            })),
+        );
+        let base_url = format!("{}/", server.base_url());
+
+        let sdk = WxSdkBuilder::new("app_id", "app_secret")
+            .base_url(&base_url)
+            .timeout(Duration::from_secs(5))
+            .build()?;
+        let res: CommonResponse<CommonError> = sdk
+            .wx_get("https://api.weixin.qq.com/cgi-bin/user/info")
+            .await?
+            .send()
+            .await?
+            .json()
+            .await?;
+        assert_eq!(
+            res,
+            CommonResponse::Ok(CommonError {
+                errcode: 0,
+                errmsg: "ok".to_owned()
+            })
+        );
+        assert_eq!(
+            server.requests_to("/cgi-bin/user/info")[0].access_token(),
+            Some(MOCK_ACCESS_TOKEN)
+        );
+
+        let token_client = TokenClient::new("app_id".to_owned(), "app_secret".to_owned())
+            .with_mode(crate::access_token::TokenMode::Stable)
+            .with_base_url(&base_url);
+        let sdk = WxSdkBuilder::new("app_id", "app_secret")
+            .http_client(Client::new())
+            .build_with_token_client(token_client)?;
+        let at = sdk.token_client.get_access_token().await?;
+        assert_eq!(at.access_token, "STABLE_TOKEN");
+
+        assert!(WxSdkBuilder::new("app_id", "app_secret")
+            .http_client(Client::new())
+            .timeout(Duration::from_secs(5))
+            .build()
+            .is_err());
+        Ok(())
+    }
+
+    /// A server replying `500` to the api calls.
+    async fn server_error() -> SdkResult<MockServer> {
+        let server = MockServer::start().await?;
+        for path in [
+            "/cgi-bin/token",
+            "/cgi-bin/user/info",
+            "/cgi-bin/message/custom/send",
+        ] {
+            server.mock(
+                path,
+                MockResponse::bytes("text/html", Vec::new()).with_status(500),
+            );
+        }
+        Ok(server)
+    }
+
+    #[tokio::test]
+    async fn test_failover() -> SdkResult<()> {
+        // nothing listens on the port of a dropped listener
+        let closed = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+        let closed_url = format!("http://{}", closed.local_addr()?);
+        drop(closed);
+        let error_server = server_error().await?;
+        let error_url = error_server.base_url();
+        let ok_server = MockServer::start().await?;
+        ok_server.mock("/cgi-bin/user/info", MockResponse::ok());
+        let ok_url = ok_server.base_url();
+
+        let sdk = WxSdkBuilder::new("app_id", "app_secret")
+            .failover(FailoverPolicy {
+                base_urls: vec![closed_url.clone(), error_url.clone(), ok_url.clone()],
+                ..Default::default()
+            })
+            .build()?;
+        for _ in 0..2 {
+            let res: CommonResponse<CommonError> = sdk
+                .wx_get("https://api.weixin.qq.com/cgi-bin/user/info")
+                .await?
+                .send()
+                .await?
+                .json()
+                .await?;
+            let res: SdkResult<()> = res.into();
+            assert!(res.is_ok());
+        }
+        // the domains failed by the token request are skipped by the api calls
+        assert_eq!(error_server.requests().len(), 1);
+        assert_eq!(ok_server.requests_to("/cgi-bin/user/info").len(), 2);
+
+        // the message may have been sent by the server returning 5xx, it isn't sent to the next domain
+        let fixed_token = StaleToken(Arc::new(Mutex::new("FRESH".to_owned())));
+        let post_sdk = WxSdkBuilder::new("app_id", "app_secret")
+            .failover(FailoverPolicy {
+                base_urls: vec![closed_url.clone(), error_url.clone(), ok_url.clone()],
+                ..Default::default()
+            })
+            .build_with_token_client(fixed_token)?;
+        let res = post_sdk
+            .wx_post("https://api.weixin.qq.com/cgi-bin/message/custom/send")
+            .await?
+            .json(&serde_json::json!({ "touser": "o1" }))
+            .send()
+            .await?;
+        assert_eq!(res.status(), reqwest::StatusCode::INTERNAL_SERVER_ERROR);
+        assert!(ok_server
+            .requests_to("/cgi-bin/message/custom/send")
+            .is_empty());
+        assert_eq!(
+            sdk.domain_health(),
+            vec![
+                DomainHealth {
+                    base_url: closed_url,
+                    healthy: false
+                },
+                DomainHealth {
+                    base_url: error_url,
+                    healthy: false
+                },
+                DomainHealth {
+                    base_url: ok_url,
+                    healthy: true
+                },
+            ]
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_retry() -> SdkResult<()> {
+        let server = MockServer::start().await?;
+        // busy for the first two queries, always busy for sending messages
+        let busy = MockResponse::error(-1, "system error");
+        server.mock_once("/cgi-bin/user/info", busy.clone());
+        server.mock_once("/cgi-bin/user/info", busy.clone());
+        server.mock("/cgi-bin/user/info", MockResponse::ok());
+        server.mock("/cgi-bin/message/custom/send", busy);
+
+        let sdk = server
+            .sdk_builder("app_id", "app_secret")
+            .retry(RetryPolicy {
+                base_delay: Duration::from_millis(10),
+                ..Default::default()
+            })
+            .build()?;
+        let res: CommonResponse<CommonError> = sdk
+            .wx_get("https://api.weixin.qq.com/cgi-bin/user/info")
+            .await?
+            .send()
+            .await?
+            .json()
+            .await?;
+        let res: SdkResult<()> = res.into();
+        assert!(res.is_ok());
+        assert_eq!(server.requests_to("/cgi-bin/user/info").len(), 3);
+
+        // sending a message is not retried
+        let res: CommonResponse<CommonError> = sdk
+            .wx_post("https://api.weixin.qq.com/cgi-bin/message/custom/send")
+            .await?
+            .json(&serde_json::json!({"touser": "o1"}))
+            .send()
+            .await?
+            .json()
+            .await?;
+        let res: SdkResult<()> = res.into();
+        assert!(matches!(res, Err(SdkError::WxApiError(e)) if e.errcode == -1));
+        assert_eq!(server.requests_to("/cgi-bin/message/custom/send").len(), 1);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_api_hook() -> SdkResult<()> {
+        let server = MockServer::start().await?;
+        server.mock("/cgi-bin/user/info", MockResponse::ok());
+        server.mock(
+            "/cgi-bin/menu/create",
+            MockResponse::error(-1, "system error"),
+        );
+
+        let calls = Arc::new(Mutex::new(Vec::new()));
+        let recorded = calls.clone();
+        let sdk = server
+            .sdk_builder("app_id", "app_secret")
+            // not retried by default
+            .on_api_call(move |call| recorded.lock().unwrap().push(call.clone()))
+            .build()?;
+        sdk.wx_get("https://api.weixin.qq.com/cgi-bin/user/info")
+            .await?
+            .send()
+            .await?;
+        sdk.wx_post("https://api.weixin.qq.com/cgi-bin/menu/create")
+            .await?
+            .send()
+            .await?;
 
-        let builder = self.http_client.post(url);
-        Ok(builder)
+        let calls = calls.lock().unwrap();
+        assert_eq!(calls.len(), 2);
+        assert_eq!(calls[0].method, reqwest::Method::GET);
+        assert_eq!(calls[0].path, "/cgi-bin/user/info");
+        assert_eq!(calls[0].status, Some(reqwest::StatusCode::OK));
+        assert_eq!(calls[0].errcode, Some(0));
+        assert_eq!(calls[1].errcode, Some(-1));
+        Ok(())
     }
 }
@@ -0,0 +1,208 @@
+//! Regional api domain failover.
+//!
+//! WeChat provides several [api domains](https://developers.weixin.qq.com/doc/offiaccount/Basic_Information/Interface_field_description.html),
+//! when a request fails to connect or gets a 5xx response, it's retried against the next domain.
+//! The non-idempotent requests may have been handled by the server returning 5xx, they're only retried on connection failure.
+//! The failed domain is skipped for a while, so the following requests go to a healthy one directly.
+use std::{
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use reqwest::{Client, Request, Response, Url};
+
+use crate::SdkResult;
+
+use super::API_BASE_URL;
+
+/// The api domains of WeChat, in the order of preference.
+pub const API_DOMAINS: [&str; 5] = [
+    API_BASE_URL,
+    "https://api2.weixin.qq.com",
+    "https://sh.api.weixin.qq.com",
+    "https://sz.api.weixin.qq.com",
+    "https://hk.api.weixin.qq.com",
+];
+
+/// The policy of switching api domains.
+#[derive(Debug, Clone)]
+pub struct FailoverPolicy {
+    /// The base urls to try in order.
+    pub base_urls: Vec<String>,
+    /// How long a failed domain is skipped.
+    pub cooldown: Duration,
+    /// Whether to retry the 5xx responses against the next domain.
+    pub retry_server_error: bool,
+    /// The max number of domains tried by one request.
+    pub max_attempts: usize,
+}
+
+impl Default for FailoverPolicy {
+    /// All the [API_DOMAINS], skip a failed domain for 60 seconds.
+    fn default() -> Self {
+        FailoverPolicy {
+            base_urls: API_DOMAINS.iter().map(|d| d.to_string()).collect(),
+            cooldown: Duration::from_secs(60),
+            retry_server_error: true,
+            max_attempts: API_DOMAINS.len(),
+        }
+    }
+}
+
+impl FailoverPolicy {
+    /// Only one base url, no failover.
+    pub fn single<S: AsRef<str>>(base_url: S) -> Self {
+        FailoverPolicy {
+            base_urls: vec![base_url.as_ref().to_owned()],
+            max_attempts: 1,
+            ..Default::default()
+        }
+    }
+}
+
+/// The health status of an api domain.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DomainHealth {
+    pub base_url: String,
+    pub healthy: bool,
+}
+
+/// The api domains with their health status, shared by the clones of [WxSdk][crate::WxSdk] and its token client.
+#[derive(Debug)]
+pub(crate) struct DomainPool {
+    policy: FailoverPolicy,
+    /// The domain is unhealthy until the instant.
+    unhealthy_until: Mutex<Vec<Option<Instant>>>,
+}
+
+impl DomainPool {
+    pub(crate) fn new(mut policy: FailoverPolicy) -> Self {
+        for base_url in policy.base_urls.iter_mut() {
+            *base_url = base_url.trim_end_matches('/').to_owned();
+        }
+        if policy.base_urls.is_empty() {
+            policy.base_urls.push(API_BASE_URL.to_owned());
+        }
+        let unhealthy_until = Mutex::new(vec![None; policy.base_urls.len()]);
+        DomainPool {
+            policy,
+            unhealthy_until,
+        }
+    }
+
+    /// The preferred base url.
+    pub(crate) fn primary(&self) -> &str {
+        &self.policy.base_urls[0]
+    }
+
+    pub(crate) fn health(&self) -> Vec<DomainHealth> {
+        let now = Instant::now();
+        let unhealthy_until = self.unhealthy_until.lock().unwrap();
+        self.policy
+            .base_urls
+            .iter()
+            .zip(unhealthy_until.iter())
+            .map(|(base_url, until)| DomainHealth {
+                base_url: base_url.clone(),
+                healthy: until.is_none_or(|until| until <= now),
+            })
+            .collect()
+    }
+
+    /// The healthy domains in order, then the unhealthy ones by the time they recover.
+    fn candidates(&self) -> Vec<usize> {
+        let now = Instant::now();
+        let unhealthy_until = self.unhealthy_until.lock().unwrap();
+        let mut candidates: Vec<usize> = (0..self.policy.base_urls.len()).collect();
+        candidates.sort_by_key(|&i| unhealthy_until[i].filter(|until| *until > now));
+        candidates.truncate(self.policy.max_attempts.max(1));
+        candidates
+    }
+
+    fn set_health(&self, index: usize, healthy: bool) {
+        let mut unhealthy_until = self.unhealthy_until.lock().unwrap();
+        unhealthy_until[index] = if healthy {
+            None
+        } else {
+            Some(Instant::now() + self.policy.cooldown)
+        };
+    }
+
+    /// The path of a url starting with one of the base urls.
+    fn strip_base_url<'u>(&self, url: &'u str) -> Option<&'u str> {
+        self.policy
+            .base_urls
+            .iter()
+            .find_map(|base_url| url.strip_prefix(base_url.as_str()))
+            .filter(|path| path.is_empty() || path.starts_with('/') || path.starts_with('?'))
+    }
+
+    /// Send the request, try the next domain on connection failure, or on 5xx response if the request is `idempotent`.
+    pub(crate) async fn execute(
+        &self,
+        client: &Client,
+        request: Request,
+        idempotent: bool,
+    ) -> SdkResult<Response> {
+        let path = match self.strip_base_url(request.url().as_str()) {
+            Some(path) => path.to_owned(),
+            None => return Ok(client.execute(request).await?),
+        };
+        let candidates = self.candidates();
+        let mut request = Some(request);
+        for (i, &index) in candidates.iter().enumerate() {
+            // the request with a stream body can't be cloned, it's the last attempt
+            let (mut attempt, is_last) = match request.as_ref().and_then(Request::try_clone) {
+                Some(attempt) if i + 1 < candidates.len() => (attempt, false),
+                _ => (request.take().unwrap(), true),
+            };
+            *attempt.url_mut() = Url::parse(&format!("{}{}", self.policy.base_urls[index], path))?;
+            match client.execute(attempt).await {
+                Ok(res) if res.status().is_server_error() && self.policy.retry_server_error => {
+                    self.set_health(index, false);
+                    if is_last || !idempotent {
+                        return Ok(res);
+                    }
+                }
+                Ok(res) => {
+                    self.set_health(index, true);
+                    return Ok(res);
+                }
+                Err(e) if e.is_connect() => {
+                    self.set_health(index, false);
+                    if is_last {
+                        return Err(e.into());
+                    }
+                }
+                Err(e) => return Err(e.into()),
+            }
+        }
+        unreachable!("at least one domain is tried")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_candidates() {
+        let pool = DomainPool::new(FailoverPolicy::default());
+        assert_eq!(pool.candidates(), vec![0, 1, 2, 3, 4]);
+        pool.set_health(0, false);
+        pool.set_health(2, false);
+        assert_eq!(pool.candidates(), vec![1, 3, 4, 0, 2]);
+        assert!(!pool.health()[0].healthy);
+        pool.set_health(0, true);
+        assert_eq!(pool.candidates(), vec![0, 1, 3, 4, 2]);
+
+        let pool = DomainPool::new(FailoverPolicy::single("http://127.0.0.1:8080/"));
+        assert_eq!(pool.primary(), "http://127.0.0.1:8080");
+        assert_eq!(pool.candidates(), vec![0]);
+        assert_eq!(
+            pool.strip_base_url("http://127.0.0.1:8080/cgi-bin/token?a=1"),
+            Some("/cgi-bin/token?a=1")
+        );
+        assert_eq!(pool.strip_base_url("http://127.0.0.1:80801/cgi-bin"), None);
+    }
+}
@@ -0,0 +1,83 @@
+//! Observability of WeChat api calls.
+//!
+//! Every attempt of a [WxApiRequest][super::WxApiRequest] is reported to the [ApiHook] registered by
+//! [WxSdkBuilder::on_api_call][super::WxSdkBuilder::on_api_call], e.g. to export metrics.
+//! With the `tracing` feature, it runs in a `wx_api` span, the secrets in the url, e.g. `access_token` and `code`, are redacted.
+use std::{sync::Arc, time::Duration};
+
+use reqwest::{Method, StatusCode, Url};
+
+/// An attempt of calling WeChat api.
+#[derive(Debug, Clone)]
+pub struct ApiCall {
+    pub method: Method,
+    /// The url path, without the query params.
+    pub path: String,
+    /// `None` when the request failed without a response.
+    pub status: Option<StatusCode>,
+    pub duration: Duration,
+    /// The errcode of a JSON response.
+    pub errcode: Option<i32>,
+    /// The request id in the errmsg, see [CommonError::rid][crate::error::CommonError::rid].
+    pub rid: Option<String>,
+}
+
+/// The callback receiving every [ApiCall].
+pub type ApiHook = Arc<dyn Fn(&ApiCall) + Send + Sync>;
+
+/// The url with the value of the secret query params replaced by `***`.
+pub fn redact_url(url: &Url) -> String {
+    const SECRETS: [&str; 7] = [
+        "access_token",
+        "secret",
+        "appsecret",
+        "component_access_token",
+        "refresh_token",
+        "code",
+        "js_code",
+    ];
+    if !url
+        .query_pairs()
+        .any(|(k, _)| SECRETS.contains(&k.as_ref()))
+    {
+        return url.to_string();
+    }
+    let pairs: Vec<(String, String)> = url
+        .query_pairs()
+        .map(|(k, v)| {
+            let v = if SECRETS.contains(&k.as_ref()) {
+                "***".to_owned()
+            } else {
+                v.into_owned()
+            };
+            (k.into_owned(), v)
+        })
+        .collect();
+    let mut url = url.clone();
+    url.query_pairs_mut().clear().extend_pairs(pairs);
+    url.to_string()
+}
+
+#[test]
+fn test_redact_url() {
+    let url =
+        Url::parse("https://api.weixin.qq.com/cgi-bin/user/info?access_token=TOKEN&openid=o1")
+            .unwrap();
+    assert_eq!(
+        redact_url(&url),
+        "https://api.weixin.qq.com/cgi-bin/user/info?access_token=***&openid=o1"
+    );
+    let url = Url::parse(
+        "https://api.weixin.qq.com/sns/jscode2session?appid=APPID&secret=SECRET&js_code=CODE",
+    )
+    .unwrap();
+    assert_eq!(
+        redact_url(&url),
+        "https://api.weixin.qq.com/sns/jscode2session?appid=APPID&secret=***&js_code=***"
+    );
+    let url = Url::parse("https://api.weixin.qq.com/cgi-bin/menu/get").unwrap();
+    assert_eq!(
+        redact_url(&url),
+        "https://api.weixin.qq.com/cgi-bin/menu/get"
+    );
+}
@@ -0,0 +1,232 @@
+//! Client side rate limiting.
+//!
+//! WeChat limits the daily quota and the frequency of every api, the exceeded calls get errcode 45009 or 45011,
+//! and the api is locked until the quota is reset. The token buckets here stop the calls before they reach the limits.
+use std::{
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use crate::{error::SdkError, SdkResult};
+
+/// What to do with a call when the bucket is empty.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RateLimitMode {
+    /// Wait until a token is available.
+    #[default]
+    Queue,
+    /// Fail with [SdkError::RateLimited] immediately.
+    Reject,
+}
+
+/// A token bucket of `capacity` tokens, refilled evenly in every `period`.
+#[derive(Debug, Clone)]
+pub struct RateLimit {
+    /// The url path prefix of the limited apis, `/` limits all the apis of the app.
+    pub path: String,
+    pub capacity: u32,
+    pub period: Duration,
+    pub mode: RateLimitMode,
+}
+
+impl RateLimit {
+    /// Limit the apis whose url path starts with `path`, e.g. `/cgi-bin/message/template/send`.
+    pub fn per_path<S: AsRef<str>>(path: S, capacity: u32, period: Duration) -> Self {
+        RateLimit {
+            path: path.as_ref().to_owned(),
+            capacity,
+            period,
+            mode: RateLimitMode::default(),
+        }
+    }
+
+    /// Limit all the apis of the app.
+    pub fn per_app(capacity: u32, period: Duration) -> Self {
+        Self::per_path("/", capacity, period)
+    }
+
+    /// Reject the calls instead of queuing them.
+    pub fn reject(mut self) -> Self {
+        self.mode = RateLimitMode::Reject;
+        self
+    }
+}
+
+/// The current usage of a [RateLimit].
+#[derive(Debug, Clone, PartialEq)]
+pub struct RateLimitUsage {
+    pub path: String,
+    pub capacity: u32,
+    /// The tokens left in the bucket.
+    pub available: f64,
+    /// The calls passed the bucket since the sdk is built.
+    pub total: u64,
+    /// The calls rejected by the bucket since the sdk is built.
+    pub rejected: u64,
+}
+
+#[derive(Debug)]
+struct Bucket {
+    limit: RateLimit,
+    tokens: f64,
+    updated_at: Instant,
+    total: u64,
+    rejected: u64,
+}
+
+impl Bucket {
+    fn refill(&mut self, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.updated_at);
+        let refilled = elapsed.as_secs_f64() / self.limit.period.as_secs_f64().max(f64::EPSILON)
+            * self.limit.capacity as f64;
+        self.tokens = (self.tokens + refilled).min(self.limit.capacity as f64);
+        self.updated_at = now;
+    }
+
+    /// How long until a token is available.
+    fn wait_time(&self) -> Duration {
+        let missing = 1.0 - self.tokens;
+        if missing <= 0.0 {
+            return Duration::ZERO;
+        }
+        self.limit
+            .period
+            .mul_f64(missing / self.limit.capacity.max(1) as f64)
+    }
+}
+
+/// The token buckets shared by the clones of [WxSdk][crate::WxSdk].
+#[derive(Debug)]
+pub(crate) struct RateLimiter {
+    buckets: Mutex<Vec<Bucket>>,
+}
+
+impl RateLimiter {
+    /// A limit without capacity would never refill, so the calls queued on it would wait forever.
+    pub(crate) fn new(limits: Vec<RateLimit>) -> SdkResult<Self> {
+        if let Some(limit) = limits.iter().find(|l| l.capacity == 0) {
+            return Err(SdkError::InvalidParams(format!(
+                "rate limit of {} should have a positive capacity",
+                limit.path
+            )));
+        }
+        let now = Instant::now();
+        let buckets = limits
+            .into_iter()
+            .map(|limit| Bucket {
+                tokens: limit.capacity as f64,
+                limit,
+                updated_at: now,
+                total: 0,
+                rejected: 0,
+            })
+            .collect();
+        Ok(RateLimiter {
+            buckets: Mutex::new(buckets),
+        })
+    }
+
+    /// Take a token from every bucket matching `path`, the tokens are taken only when all the buckets have one.
+    pub(crate) async fn acquire(&self, path: &str) -> SdkResult<()> {
+        loop {
+            let wait = {
+                let now = Instant::now();
+                let mut buckets = self.buckets.lock().unwrap();
+                let mut matched: Vec<&mut Bucket> = buckets
+                    .iter_mut()
+                    .filter(|b| path.starts_with(b.limit.path.as_str()))
+                    .collect();
+                let mut wait = Duration::ZERO;
+                let mut rejected = false;
+                for bucket in matched.iter_mut() {
+                    bucket.refill(now);
+                    let bucket_wait = bucket.wait_time();
+                    if bucket_wait > Duration::ZERO && bucket.limit.mode == RateLimitMode::Reject {
+                        rejected = true;
+                    }
+                    wait = wait.max(bucket_wait);
+                }
+                if rejected {
+                    for bucket in matched.iter_mut() {
+                        bucket.rejected += 1;
+                    }
+                    return Err(SdkError::RateLimited(path.to_owned()));
+                }
+                if wait == Duration::ZERO {
+                    for bucket in matched.iter_mut() {
+                        bucket.tokens -= 1.0;
+                        bucket.total += 1;
+                    }
+                    return Ok(());
+                }
+                wait
+            };
+            tokio::time::sleep(wait).await;
+        }
+    }
+
+    pub(crate) fn usage(&self) -> Vec<RateLimitUsage> {
+        let now = Instant::now();
+        let mut buckets = self.buckets.lock().unwrap();
+        buckets
+            .iter_mut()
+            .map(|bucket| {
+                bucket.refill(now);
+                RateLimitUsage {
+                    path: bucket.limit.path.clone(),
+                    capacity: bucket.limit.capacity,
+                    available: bucket.tokens,
+                    total: bucket.total,
+                    rejected: bucket.rejected,
+                }
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_rate_limiter() {
+        let limiter = RateLimiter::new(vec![
+            RateLimit::per_app(100, Duration::from_secs(60)),
+            RateLimit::per_path("/cgi-bin/message/", 2, Duration::from_secs(3600)).reject(),
+            RateLimit::per_path("/cgi-bin/user/info", 1, Duration::from_millis(100)),
+        ])
+        .unwrap();
+        let path = "/cgi-bin/message/template/send";
+        assert!(limiter.acquire(path).await.is_ok());
+        assert!(limiter.acquire(path).await.is_ok());
+        assert!(matches!(
+            limiter.acquire(path).await,
+            Err(SdkError::RateLimited(p)) if p == path
+        ));
+
+        // the second call waits for the refill
+        let start = Instant::now();
+        limiter.acquire("/cgi-bin/user/info").await.unwrap();
+        limiter.acquire("/cgi-bin/user/info").await.unwrap();
+        assert!(start.elapsed() >= Duration::from_millis(80));
+
+        let usage = limiter.usage();
+        assert_eq!(usage[0].total, 4);
+        assert_eq!(usage[0].rejected, 1);
+        assert_eq!(usage[1].total, 2);
+        assert_eq!(usage[1].rejected, 1);
+        assert!(usage[1].available < 1.0);
+        assert_eq!(usage[2].total, 2);
+    }
+
+    #[test]
+    fn test_zero_capacity() {
+        assert!(matches!(
+            RateLimiter::new(vec![
+                RateLimit::per_app(100, Duration::from_secs(60)),
+                RateLimit::per_path("/cgi-bin/message/", 0, Duration::from_secs(60)),
+            ]),
+            Err(SdkError::InvalidParams(_))
+        ));
+    }
+}
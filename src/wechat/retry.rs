@@ -0,0 +1,162 @@
+//! Retry with exponential backoff for the transient failures of WeChat api.
+//!
+//! A request is retried on transport errors, 5xx responses and the [retryable errcodes][RetryPolicy::retryable_errcodes].
+//! The non-idempotent requests, e.g. sending messages, are only retried when the connection failed,
+//! because WeChat may have processed them already.
+use std::time::Duration;
+
+use rand::Rng;
+use reqwest::{Method, Request, StatusCode};
+
+use crate::error::{SdkError, WxErrorCode};
+
+/// The policy of retrying WeChat api calls.
+///
+/// [WxSdk][crate::WxSdk] doesn't retry by default, enable it by [WxSdkBuilder::retry][crate::WxSdkBuilder::retry].
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    /// The max number of attempts, including the first one, `1` means no retry.
+    pub max_attempts: u32,
+    /// The delay before the first retry, it doubles for every retry.
+    pub base_delay: Duration,
+    /// The upper bound of the delay.
+    pub max_delay: Duration,
+    /// The errcodes to retry, the [retryable][WxErrorCode::is_retryable] ones by default.
+    pub retryable_errcodes: Vec<i32>,
+    /// The url paths of non-idempotent `POST` apis, matched by prefix.
+    /// The other `POST` apis are treated as idempotent queries.
+    pub non_idempotent_paths: Vec<String>,
+}
+
+impl Default for RetryPolicy {
+    /// 3 attempts, starts from 200 milliseconds delay.
+    fn default() -> Self {
+        RetryPolicy {
+            max_attempts: 3,
+            base_delay: Duration::from_millis(200),
+            max_delay: Duration::from_secs(5),
+            retryable_errcodes: vec![WxErrorCode::SystemBusy.code()],
+            non_idempotent_paths: [
+                "/cgi-bin/message/",
+                "/cgi-bin/freepublish/submit",
+                "/cgi-bin/draft/add",
+                "/cgi-bin/media/upload",
+                "/cgi-bin/material/add_",
+                "/cgi-bin/customservice/kfaccount/add",
+                "/customservice/kfaccount/",
+                "/cgi-bin/tags/create",
+                "/cgi-bin/menu/addconditional",
+                "/cgi-bin/shorten/gen",
+                "/cgi-bin/clear_quota",
+                // the orders of logistics and immediate delivery
+                "/cgi-bin/express/business/order/add",
+                "/cgi-bin/express/local/business/order/add",
+                "/cgi-bin/express/local/business/order/readd",
+                "/cgi-bin/express/local/business/shop/add",
+                "/wxa/business/push_message",
+                "/wxaapi/newtmpl/addtemplate",
+                "/wxaapi/broadcast/room/create",
+                "/wxaapi/broadcast/goods/add",
+                "/tcb/sendsms",
+                "/tcb/createsendsmstask",
+                "/tcb/adddelayedfunctiontask",
+            ]
+            .iter()
+            .map(|p| p.to_string())
+            .collect(),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Never retry.
+    pub fn none() -> Self {
+        RetryPolicy {
+            max_attempts: 1,
+            ..Default::default()
+        }
+    }
+
+    pub(crate) fn is_idempotent(&self, request: &Request) -> bool {
+        if request.method() != Method::POST {
+            return true;
+        }
+        let path = request.url().path();
+        !self
+            .non_idempotent_paths
+            .iter()
+            .any(|p| path.starts_with(p.as_str()))
+    }
+
+    /// The delay before the `retry`th retry, with jitter in the upper half.
+    pub(crate) fn delay(&self, retry: u32) -> Duration {
+        let delay = self
+            .base_delay
+            .saturating_mul(2u32.saturating_pow(retry.saturating_sub(1)))
+            .min(self.max_delay);
+        let jitter = rand::thread_rng().gen_range(0.5..=1.0);
+        delay.mul_f64(jitter)
+    }
+
+    pub(crate) fn should_retry_error(&self, e: &SdkError, idempotent: bool) -> bool {
+        match e {
+            // the request isn't sent when failed to connect
+            SdkError::ReqwestError(e) if e.is_connect() => true,
+            SdkError::ReqwestError(e) => idempotent && (e.is_timeout() || e.is_request()),
+            _ => false,
+        }
+    }
+
+    pub(crate) fn should_retry_response(
+        &self,
+        status: StatusCode,
+        errcode: Option<i32>,
+        idempotent: bool,
+    ) -> bool {
+        idempotent
+            && (status.is_server_error()
+                || errcode.is_some_and(|c| self.retryable_errcodes.contains(&c)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_policy() {
+        let policy = RetryPolicy::default();
+        let client = reqwest::Client::new();
+        let send = client
+            .post("https://api.weixin.qq.com/cgi-bin/message/custom/send")
+            .build()
+            .unwrap();
+        assert!(!policy.is_idempotent(&send));
+        let query = client
+            .post("https://api.weixin.qq.com/datacube/getusersummary")
+            .build()
+            .unwrap();
+        assert!(policy.is_idempotent(&query));
+        for path in [
+            "/cgi-bin/express/business/order/add",
+            "/cgi-bin/express/local/business/order/add",
+        ] {
+            let order = client
+                .post(format!("https://api.weixin.qq.com{}", path))
+                .build()
+                .unwrap();
+            assert!(!policy.is_idempotent(&order));
+        }
+
+        assert!(policy.should_retry_response(StatusCode::OK, Some(-1), true));
+        assert!(!policy.should_retry_response(StatusCode::OK, Some(-1), false));
+        assert!(!policy.should_retry_response(StatusCode::OK, Some(40003), true));
+        assert!(policy.should_retry_response(StatusCode::BAD_GATEWAY, None, true));
+
+        for retry in 1..10 {
+            let delay = policy.delay(retry);
+            assert!(delay <= policy.max_delay);
+            assert!(delay >= policy.base_delay / 2);
+        }
+    }
+}
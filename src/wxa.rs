@@ -1,12 +1,22 @@
-use crate::{access_token::AccessTokenProvider, error::CommonResponse};
+use std::collections::HashMap;
+
+use crate::mp::{event::signature::Signature, EncodingMode, ServerConfig};
+use crate::{
+    access_token::AccessTokenProvider,
+    error::{CommonResponse, SdkError},
+};
 use crate::{wechat::WxApiRequestBuilder, SdkResult, WxSdk};
 use serde::{de::DeserializeOwned, Deserialize, Serialize};
 
+use self::crypto::WatermarkData;
+
 pub mod ad;
 pub mod cloudbase;
 pub mod content_security;
+pub mod crypto;
 pub mod customer_message;
 pub mod datacube;
+pub mod event;
 pub mod img;
 pub mod immediate_delivery;
 pub mod internet;
@@ -21,6 +31,7 @@ pub mod qrcode;
 pub mod redpacket_cover;
 pub mod risk_control;
 pub mod search;
+pub mod server;
 pub mod service_market;
 pub mod short_link;
 pub mod soter;
@@ -125,9 +136,15 @@ async fn post_send<'a, A: WxApiRequestBuilder, R: DeserializeOwned, D: Serialize
 #[derive(Clone)]
 pub struct WxaSdk<T: AccessTokenProvider> {
     pub(crate) sdk: crate::WxSdk<T>,
+    pub(crate) server_config: Option<ServerConfig>,
 }
 
 impl<T: AccessTokenProvider> WxaSdk<T> {
+    /// The app_id of the mini program.
+    pub fn app_id(&self) -> &str {
+        &self.sdk.app_id
+    }
+
     pub async fn code_to_session(&self, js_code: &str) -> SdkResult<LoginResult> {
         let url = "https://api.weixin.qq.com/sns/jscode2session?grant_type=authorization_code";
         let query = &serde_json::json!({
@@ -152,6 +169,126 @@ impl<T: AccessTokenProvider> WxaSdk<T> {
         post_send(&self.sdk, url, &query).await
     }
 
+    /// 解密开放数据并校验数据水印，如 [UserInfo][crypto::UserInfo]、[ShareInfo][crypto::ShareInfo]、[RunData][crypto::RunData]
+    pub fn decrypt_data<D: DeserializeOwned + WatermarkData>(
+        &self,
+        session_key: &str,
+        encrypted_data: &str,
+        iv: &str,
+    ) -> SdkResult<D> {
+        let plaintext = crypto::decrypt_data(session_key, encrypted_data, iv)?;
+        let data = serde_json::from_str::<D>(&plaintext)?;
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::SystemTime::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs() as i64;
+        crypto::check_watermark(data.watermark(), &self.sdk.app_id, now)?;
+        Ok(data)
+    }
+
+    /// 解析消息推送，自动识别 JSON 和 XML 格式。
+    /// 兼容模式和安全模式需要传入 url 中的 `signature`、`msg_signature`、`timestamp`、`nonce` 参数。
+    pub fn parse_received_msg<S: AsRef<str>>(
+        &self,
+        msg: S,
+        url_params: Option<HashMap<String, String>>,
+    ) -> SdkResult<event::ReceivedEvent> {
+        let server_config = self
+            .server_config
+            .as_ref()
+            .ok_or_else(|| SdkError::InvalidParams("needs server_config".to_owned()))?;
+        let msg = msg.as_ref();
+        let get_param = |key: &str| {
+            url_params
+                .as_ref()
+                .and_then(|params| params.get(key))
+                .cloned()
+                .ok_or_else(|| SdkError::InvalidParams(key.to_owned()))
+        };
+        let (encrypt_msg, aes_key) = match server_config.encoding_mode {
+            EncodingMode::Plain => return event::ReceivedEvent::parse(msg),
+            EncodingMode::Compat(ref aes_key) => {
+                // the plain `signature` is checked even if the message is encrypted
+                let check_sign = vec![
+                    server_config.token.clone(),
+                    get_param("timestamp")?,
+                    get_param("nonce")?,
+                ];
+                if !Signature::new(get_param("signature")?, check_sign).is_ok() {
+                    return Err(SdkError::InvalidSignature);
+                }
+                match event::get_encrypt(msg)? {
+                    Some(encrypt_msg) => (encrypt_msg, aes_key),
+                    None => return event::ReceivedEvent::parse(msg),
+                }
+            }
+            EncodingMode::Security(ref aes_key) => {
+                let encrypt_msg = event::get_encrypt(msg)?
+                    .ok_or_else(|| SdkError::InvalidParams("Encrypt".to_owned()))?;
+                (encrypt_msg, aes_key)
+            }
+        };
+        let check_sign = vec![
+            server_config.token.clone(),
+            get_param("timestamp")?,
+            get_param("nonce")?,
+            encrypt_msg.clone(),
+        ];
+        if !Signature::new(get_param("msg_signature")?, check_sign).is_ok() {
+            return Err(SdkError::InvalidSignature);
+        }
+        let (msg, app_id) = crate::mp::event::crypto::decrypt_message(&encrypt_msg, aes_key)?;
+        if app_id != self.sdk.msg_app_id() {
+            return Err(SdkError::InvalidAppid);
+        }
+        event::ReceivedEvent::parse(&msg)
+    }
+
+    /// 解密用户信息
+    pub fn decrypt_user_info(
+        &self,
+        session_key: &str,
+        encrypted_data: &str,
+        iv: &str,
+    ) -> SdkResult<crypto::UserInfo> {
+        self.decrypt_data(session_key, encrypted_data, iv)
+    }
+
+    /// 解密手机号（旧版 `getPhoneNumber` 返回的 `encryptedData`）
+    pub fn decrypt_phone_number(
+        &self,
+        session_key: &str,
+        encrypted_data: &str,
+        iv: &str,
+    ) -> SdkResult<crypto::PhoneInfo> {
+        self.decrypt_data(session_key, encrypted_data, iv)
+    }
+
+    /// 解密转发信息
+    pub fn decrypt_share_info(
+        &self,
+        session_key: &str,
+        encrypted_data: &str,
+        iv: &str,
+    ) -> SdkResult<crypto::ShareInfo> {
+        self.decrypt_data(session_key, encrypted_data, iv)
+    }
+
+    /// 解密微信运动步数
+    pub fn decrypt_run_data(
+        &self,
+        session_key: &str,
+        encrypted_data: &str,
+        iv: &str,
+    ) -> SdkResult<crypto::RunData> {
+        self.decrypt_data(session_key, encrypted_data, iv)
+    }
+
+    /// openApi module 接口额度及 rid 查询模块
+    pub fn openapi(&self) -> crate::openapi::OpenApiModule<'_, T> {
+        crate::openapi::OpenApiModule(&self.sdk)
+    }
+
     /// Data analysis 数据分析模块
     pub fn datacube(&self) -> datacube::DataCubeModule<WxSdk<T>> {
         datacube::DataCubeModule(&self.sdk)
@@ -316,3 +453,132 @@ impl<T: AccessTokenProvider> WxaSdk<T> {
 //     let builder = reqwest::Client::new().get("https://b.com/").query(&data);
 //     println!("{:?}", &builder); // query: None
 // }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mp::event::crypto::encrypt_message;
+    use event::{ReceivedEvent, ReceivedMessage};
+
+    const APP_ID: &str = "wx5823bf96d3bd56c7";
+    const TOKEN: &str = "QDG6eK";
+    const AES_KEY: &str = "jWmYm7qr5nMoAUwZRjGtBxmz3KA1tkAj3ykkR6q2B2C";
+    const XML: &str = "<xml><ToUserName><![CDATA[gh_123456789abc]]></ToUserName><FromUserName><![CDATA[fromUser]]></FromUserName><CreateTime>1482048670</CreateTime><MsgType><![CDATA[text]]></MsgType><Content><![CDATA[hello]]></Content><MsgId>1234567890123456</MsgId></xml>";
+    const JSON: &str = r#"{"ToUserName":"gh_123456789abc","FromUserName":"fromUser","CreateTime":1482048670,"MsgType":"text","Content":"hello","MsgId":1234567890123456}"#;
+
+    fn wxa(encoding_mode: EncodingMode) -> WxaSdk<crate::TokenClient> {
+        WxSdk::new_with_default_token_client(APP_ID, "app_secret")
+            .wxa_with_server_config(ServerConfig::new(TOKEN, encoding_mode))
+    }
+
+    /// Encrypt a message like WeChat does, in the same data format as `plain`.
+    fn encrypted(plain: &str) -> (String, HashMap<String, String>) {
+        let (timestamp, nonce) = ("1409659813", "1372623149");
+        let encrypt = encrypt_message(plain, AES_KEY, APP_ID).unwrap();
+        let msg = match event::DataFormat::detect(plain) {
+            event::DataFormat::Xml => format!(
+                "<xml><ToUserName><![CDATA[gh_123456789abc]]></ToUserName><Encrypt><![CDATA[{}]]></Encrypt></xml>",
+                encrypt
+            ),
+            event::DataFormat::Json => {
+                serde_json::json!({ "ToUserName": "gh_123456789abc", "Encrypt": encrypt })
+                    .to_string()
+            }
+        };
+        let signature = Signature::generate_signature(vec![
+            TOKEN.to_owned(),
+            timestamp.to_owned(),
+            nonce.to_owned(),
+        ]);
+        let msg_signature = Signature::generate_signature(vec![
+            TOKEN.to_owned(),
+            timestamp.to_owned(),
+            nonce.to_owned(),
+            encrypt,
+        ]);
+        let url_params = [
+            ("timestamp", timestamp),
+            ("nonce", nonce),
+            ("signature", signature.as_str()),
+            ("msg_signature", msg_signature.as_str()),
+        ]
+        .iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect();
+        (msg, url_params)
+    }
+
+    fn assert_hello(event: ReceivedEvent) {
+        assert_eq!(event.from, "fromUser");
+        assert!(matches!(event.body, ReceivedMessage::Text(t) if t.content == "hello"));
+    }
+
+    #[test]
+    fn test_parse_security_msg() -> SdkResult<()> {
+        let wxa = wxa(EncodingMode::Security(AES_KEY.to_owned()));
+        for plain in [XML, JSON] {
+            let (msg, url_params) = encrypted(plain);
+            assert_hello(wxa.parse_received_msg(&msg, Some(url_params.clone()))?);
+
+            let mut wrong = url_params;
+            wrong.insert("nonce".to_owned(), "0".to_owned());
+            assert!(matches!(
+                wxa.parse_received_msg(&msg, Some(wrong)),
+                Err(SdkError::InvalidSignature)
+            ));
+            assert!(wxa.parse_received_msg(plain, None).is_err());
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_compat_msg() -> SdkResult<()> {
+        let wxa = wxa(EncodingMode::Compat(AES_KEY.to_owned()));
+        for plain in [XML, JSON] {
+            let (msg, url_params) = encrypted(plain);
+            assert_hello(wxa.parse_received_msg(&msg, Some(url_params.clone()))?);
+            // the plain message is accepted with the plain signature
+            assert_hello(wxa.parse_received_msg(plain, Some(url_params.clone()))?);
+
+            // the plain signature is checked when `Encrypt` is present
+            let mut wrong = url_params.clone();
+            wrong.insert("signature".to_owned(), "0".to_owned());
+            assert!(matches!(
+                wxa.parse_received_msg(&msg, Some(wrong)),
+                Err(SdkError::InvalidSignature)
+            ));
+            let mut wrong = url_params;
+            wrong.insert("msg_signature".to_owned(), "0".to_owned());
+            assert!(matches!(
+                wxa.parse_received_msg(&msg, Some(wrong)),
+                Err(SdkError::InvalidSignature)
+            ));
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_other_app_msg() {
+        let wxa = wxa(EncodingMode::Security(AES_KEY.to_owned()));
+        let encrypt = encrypt_message(XML, AES_KEY, "wx_other").unwrap();
+        let msg = format!("<xml><Encrypt><![CDATA[{}]]></Encrypt></xml>", encrypt);
+        let msg_signature = Signature::generate_signature(vec![
+            TOKEN.to_owned(),
+            "1".to_owned(),
+            "2".to_owned(),
+            encrypt,
+        ]);
+        let url_params = [
+            ("timestamp", "1"),
+            ("nonce", "2"),
+            ("msg_signature", &msg_signature),
+        ]
+        .iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect();
+        assert!(matches!(
+            wxa.parse_received_msg(&msg, Some(url_params)),
+            Err(SdkError::InvalidAppid)
+        ));
+    }
+}
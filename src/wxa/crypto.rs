@@ -0,0 +1,175 @@
+//! 开放数据解密
+//!
+//! 小程序通过 `wx.getUserInfo`、`wx.getShareInfo`、`wx.getWeRunData` 等接口获取的 `encryptedData`，
+//! 需要使用 [code_to_session][crate::wxa::WxaSdk::code_to_session] 返回的 `session_key` 进行 AES-128-CBC 解密。
+use serde::{Deserialize, Serialize};
+
+use crate::error::SdkError;
+use crate::mp::event::crypto::aes_cbc_decrypt;
+use crate::utils::base64;
+use crate::SdkResult;
+
+pub use super::phone_number::{PhoneInfo, Watermark};
+
+/// 水印时间戳允许超前当前时间的秒数
+const WATERMARK_TOLERANCE: i64 = 5 * 60;
+/// 水印时间戳距当前时间的最大秒数，更早的数据视为重放
+pub const WATERMARK_MAX_AGE: i64 = 24 * 3600;
+
+/// 带有数据水印的开放数据
+pub trait WatermarkData {
+    fn watermark(&self) -> &Watermark;
+}
+
+/// 解密后的用户信息
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UserInfo {
+    pub open_id: String,
+    pub nick_name: String,
+    /// 性别 0：未知、1：男、2：女
+    pub gender: i8,
+    pub city: String,
+    pub province: String,
+    pub country: String,
+    pub avatar_url: String,
+    #[serde(default)]
+    pub union_id: Option<String>,
+    pub watermark: Watermark,
+}
+
+/// 解密后的转发信息
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ShareInfo {
+    /// 群对当前小程序的唯一 ID
+    #[serde(rename = "openGId")]
+    pub open_gid: String,
+    pub watermark: Watermark,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct StepInfo {
+    /// 时间戳，表示数据对应的时间
+    pub timestamp: i64,
+    /// 微信运动步数
+    pub step: i32,
+}
+
+/// 解密后的微信运动步数，包含最近30天的数据
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RunData {
+    pub step_info_list: Vec<StepInfo>,
+    pub watermark: Watermark,
+}
+
+impl WatermarkData for UserInfo {
+    fn watermark(&self) -> &Watermark {
+        &self.watermark
+    }
+}
+
+impl WatermarkData for ShareInfo {
+    fn watermark(&self) -> &Watermark {
+        &self.watermark
+    }
+}
+
+impl WatermarkData for RunData {
+    fn watermark(&self) -> &Watermark {
+        &self.watermark
+    }
+}
+
+impl WatermarkData for PhoneInfo {
+    fn watermark(&self) -> &Watermark {
+        &self.watermark
+    }
+}
+
+/// 解密开放数据，返回明文 JSON
+pub fn decrypt_data<S: AsRef<str>>(session_key: S, encrypted_data: S, iv: S) -> SdkResult<String> {
+    let key = base64::decode(session_key.as_ref())
+        .map_err(|e| SdkError::MsgDecryptError(e.to_string()))?;
+    let iv = base64::decode(iv.as_ref()).map_err(|e| SdkError::MsgDecryptError(e.to_string()))?;
+    if key.len() != 16 {
+        return Err(SdkError::MsgDecryptError("invalid session_key".to_owned()));
+    }
+    let decrypted = aes_cbc_decrypt(&key, &iv, encrypted_data.as_ref())?;
+
+    String::from_utf8(decrypted).map_err(|e| SdkError::MsgDecryptError(e.to_string()))
+}
+
+/// 校验数据水印中的 appid 和时间戳，时间戳早于 [WATERMARK_MAX_AGE] 的数据被拒绝
+pub fn check_watermark(watermark: &Watermark, app_id: &str, now: i64) -> SdkResult<()> {
+    check_watermark_with_max_age(watermark, app_id, now, WATERMARK_MAX_AGE)
+}
+
+/// 校验数据水印中的 appid 和时间戳，`max_age` 为水印时间戳距 `now` 的最大秒数
+pub fn check_watermark_with_max_age(
+    watermark: &Watermark,
+    app_id: &str,
+    now: i64,
+    max_age: i64,
+) -> SdkResult<()> {
+    if watermark.appid != app_id {
+        return Err(SdkError::InvalidAppid);
+    }
+    if watermark.timestamp <= 0
+        || watermark.timestamp > now + WATERMARK_TOLERANCE
+        || watermark.timestamp < now - max_age
+    {
+        return Err(SdkError::InvalidParams(format!(
+            "invalid watermark timestamp {}",
+            watermark.timestamp
+        )));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 官方文档中的示例数据
+    const APP_ID: &str = "wx4f4bc4dec97d474b";
+    const SESSION_KEY: &str = "tiihtNczf5v6AKRyjwEUhQ==";
+    const IV: &str = "r7BXXKkLb8qrSNn05n0qiA==";
+    const ENCRYPTED_DATA: &str = "CiyLU1Aw2KjvrjMdj8YKliAjtP4gsMZMQmRzooG2xrDcvSnxIMXFufNstNGTyaGS9uT5geRa0W4oTOb1WT7fJlAC+oNPdbB+3hVbJSRgv+4lGOETKUQz6OYStslQ142dNCuabNPGBzlooOmB231qMM85d2/fV6ChevvXvQP8Hkue1poOFtnEtpyxVLW1zAo6/1Xx1COxFvrc2d7UL/lmHInNlxuacJXwu0fjpXfz/YqYzBIBzD6WUfTIF9GRHpOn/Hz7saL8xz+W//FRAUid1OksQaQx4CMs8LOddcQhULW4ucetDf96JcR3g0gfRK4PC7E/r7Z6xNrXd2UIeorGj5Ef7b1pJAYB6Y5anaHqZ9J6nKEBvB4DnNLIVWSgARns/8wR2SiRS7MNACwTyrGvt9ts8p12PKFdlqYTopNHR1Vf7XjfhQlVsAJdNiKdYmYVoKlaRv85IfVunYzO0IKXsyl7JCUjCpoG20f0a04COwfneQAGGwd5oa+T8yO5hzuyDb/XcxxmK01EpqOyuxINew==";
+
+    #[test]
+    fn test_decrypt_data() -> SdkResult<()> {
+        let plaintext = decrypt_data(SESSION_KEY, ENCRYPTED_DATA, IV)?;
+        let user: UserInfo = serde_json::from_str(&plaintext)?;
+        assert_eq!(user.open_id, "oGZUI0egBJY1zhBYw2KhdUfwVJJE");
+        assert_eq!(user.nick_name, "Band");
+        assert_eq!(user.watermark().appid, APP_ID);
+
+        check_watermark(user.watermark(), APP_ID, 1477314187)?;
+        assert!(matches!(
+            check_watermark(user.watermark(), "wx5823bf96d3bd56c7", 1477314187),
+            Err(SdkError::InvalidAppid)
+        ));
+        assert!(check_watermark(user.watermark(), APP_ID, 1400000000).is_err());
+        // replayed payload
+        let now = 1477314187 + WATERMARK_MAX_AGE + 1;
+        assert!(check_watermark(user.watermark(), APP_ID, now).is_err());
+        check_watermark_with_max_age(user.watermark(), APP_ID, now, WATERMARK_MAX_AGE + 1)?;
+
+        assert!(decrypt_data("tiihtNczf5v6AKRyjwEUhQ==", "invalid", IV).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_phone_info() -> SdkResult<()> {
+        let json = r#"{
+            "phoneNumber": "13580006666",
+            "purePhoneNumber": "13580006666",
+            "countryCode": "86",
+            "watermark": { "appid": "wx4f4bc4dec97d474b", "timestamp": 1477314187 }
+        }"#;
+        let phone: PhoneInfo = serde_json::from_str(json)?;
+        assert_eq!(phone.pure_phone_number, "13580006666");
+        check_watermark(phone.watermark(), APP_ID, 1477314187)
+    }
+}
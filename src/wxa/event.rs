@@ -0,0 +1,667 @@
+//! 小程序消息推送
+//!
+//! 小程序的客服消息和事件推送支持 JSON 和 XML 两种数据格式，两种格式都会先转换为 JSON 再使用 serde 解析。
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::{
+    error::SdkError,
+    mp::event::xmlutil::xml_to_json,
+    utils::de::{number_from_str, one_or_many, option_number_from_str},
+    SdkResult,
+};
+
+const MSG_TEXT: &str = "text";
+const MSG_IMAGE: &str = "image";
+const MSG_MINIPROGRAMPAGE: &str = "miniprogrampage";
+const MSG_EVENT: &str = "event";
+
+const EVENT_USER_ENTER_TEMPSESSION: &str = "user_enter_tempsession";
+const EVENT_WXA_MEDIA_CHECK: &str = "wxa_media_check";
+const EVENT_SUBSCRIBE_MSG_POPUP: &str = "subscribe_msg_popup_event";
+const EVENT_SUBSCRIBE_MSG_CHANGE: &str = "subscribe_msg_change_event";
+const EVENT_SUBSCRIBE_MSG_SENT: &str = "subscribe_msg_sent_event";
+const EVENT_ADD_EXPRESS_PATH: &str = "add_express_path";
+const EVENT_LIVE_STATUS_CHANGE: &str = "live_status_change";
+
+/// 消息推送的数据格式
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DataFormat {
+    Json,
+    Xml,
+}
+
+impl DataFormat {
+    /// 根据消息内容的第一个字符判断数据格式
+    pub fn detect(input: &str) -> Self {
+        if input.trim_start().starts_with('<') {
+            DataFormat::Xml
+        } else {
+            DataFormat::Json
+        }
+    }
+
+    /// 将消息转换为 JSON
+    pub fn to_json(&self, input: &str) -> SdkResult<Value> {
+        match self {
+            DataFormat::Json => Ok(serde_json::from_str(input)?),
+            DataFormat::Xml => xml_to_json(input),
+        }
+    }
+}
+
+/// 文本消息
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct TextMessage {
+    pub content: String,
+    #[serde(deserialize_with = "number_from_str")]
+    pub msg_id: u64,
+}
+
+/// 图片消息
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct ImageMessage {
+    pub pic_url: String,
+    pub media_id: String,
+    #[serde(deserialize_with = "number_from_str")]
+    pub msg_id: u64,
+}
+
+/// 小程序卡片消息
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct MiniProgramPageMessage {
+    pub title: String,
+    pub app_id: String,
+    pub page_path: String,
+    pub thumb_url: String,
+    pub thumb_media_id: String,
+    #[serde(deserialize_with = "number_from_str")]
+    pub msg_id: u64,
+}
+
+/// 进入会话事件
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct UserEnterTempSessionEvent {
+    /// 开发者在客服会话按钮设置的 session-from 属性
+    #[serde(default)]
+    pub session_from: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MediaCheckResult {
+    /// 建议，有risky、pass、review三种值
+    pub suggest: String,
+    /// 命中标签枚举值，100 正常；20001 时政；20002 色情；20006 违法犯罪；21000 其他
+    #[serde(deserialize_with = "number_from_str")]
+    pub label: i32,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MediaCheckDetail {
+    /// 策略类型
+    pub strategy: String,
+    /// 错误码，仅当该值为0时，该项结果有效
+    #[serde(deserialize_with = "number_from_str")]
+    pub errcode: i32,
+    /// 建议，有risky、pass、review三种值
+    pub suggest: String,
+    /// 命中标签枚举值
+    #[serde(deserialize_with = "number_from_str")]
+    pub label: i32,
+    /// 0-100，代表置信度，越高代表越有可能属于当前返回的标签（label）
+    #[serde(default, deserialize_with = "option_number_from_str")]
+    pub prob: Option<i32>,
+}
+
+/// 音视频内容安全识别的异步检测结果
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MediaCheckEvent {
+    /// 小程序的appid
+    pub appid: String,
+    /// 任务id
+    pub trace_id: String,
+    /// 可用于区分接口版本
+    #[serde(default, deserialize_with = "option_number_from_str")]
+    pub version: Option<i32>,
+    /// 1.0 版本接口的检测结果，0：暂未检测到风险，1：风险
+    #[serde(default, deserialize_with = "option_number_from_str")]
+    pub isrisky: Option<i32>,
+    /// 1.0 版本接口的附加信息
+    #[serde(default)]
+    pub extra_info_json: Option<String>,
+    /// 1.0 版本接口的错误码，0 表示检测成功
+    #[serde(default, deserialize_with = "option_number_from_str")]
+    pub status_code: Option<i32>,
+    /// 2.0 版本接口的综合结果
+    #[serde(default)]
+    pub result: Option<MediaCheckResult>,
+    /// 2.0 版本接口的详细检测结果
+    #[serde(default, deserialize_with = "one_or_many")]
+    pub detail: Vec<MediaCheckDetail>,
+}
+
+/// 用户操作订阅通知弹窗
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct SubscribeMsgPopup {
+    /// 模板id
+    pub template_id: String,
+    /// 订阅结果（accept接收；reject拒收）
+    pub subscribe_status_string: String,
+    /// 弹框场景，0代表在小程序页面内
+    #[serde(default, deserialize_with = "option_number_from_str")]
+    pub popup_scene: Option<i32>,
+}
+
+/// 用户管理订阅通知
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct SubscribeMsgChange {
+    /// 模板id
+    pub template_id: String,
+    /// 订阅结果（reject拒收）
+    pub subscribe_status_string: String,
+}
+
+/// 发送订阅通知的结果
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct SubscribeMsgSent {
+    /// 模板id
+    pub template_id: String,
+    /// 消息id
+    #[serde(rename = "MsgID")]
+    pub msg_id: String,
+    /// 推送结果状态码（0表示成功）
+    #[serde(deserialize_with = "number_from_str")]
+    pub error_code: i32,
+    /// 推送结果状态码对应的含义
+    pub error_status: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct SubscribeMsgList<T> {
+    #[serde(
+        rename = "List",
+        deserialize_with = "one_or_many",
+        bound(deserialize = "T: Deserialize<'de>")
+    )]
+    list: Vec<T>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct ExpressPathAction {
+    /// 轨迹节点 Unix 时间戳
+    #[serde(deserialize_with = "number_from_str")]
+    pub action_time: i64,
+    /// 轨迹节点类型
+    #[serde(deserialize_with = "number_from_str")]
+    pub action_type: i32,
+    /// 轨迹节点详情
+    pub action_msg: String,
+}
+
+/// 运单轨迹更新事件
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct ExpressPathEvent {
+    /// 快递公司ID
+    #[serde(rename = "DeliveryID")]
+    pub delivery_id: String,
+    /// 运单ID
+    pub way_bill_id: String,
+    /// 订单ID
+    #[serde(default)]
+    pub order_id: Option<String>,
+    /// 轨迹版本号（整型）
+    #[serde(deserialize_with = "number_from_str")]
+    pub version: i32,
+    /// 轨迹节点数（整型）
+    #[serde(deserialize_with = "number_from_str")]
+    pub count: i32,
+    /// 轨迹列表
+    #[serde(default, deserialize_with = "one_or_many")]
+    pub actions: Vec<ExpressPathAction>,
+}
+
+/// 直播间状态变更事件
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct LiveStatusEvent {
+    /// 直播间ID
+    #[serde(
+        alias = "room_id",
+        alias = "roomid",
+        deserialize_with = "number_from_str"
+    )]
+    pub room_id: i64,
+    /// 直播间状态。101：直播中，102：未开始，103已结束，104禁播，105：暂停，106：异常，107：已过期
+    #[serde(alias = "live_status", deserialize_with = "number_from_str")]
+    pub live_status: i32,
+}
+
+#[derive(Debug)]
+pub enum EventMessage {
+    UserEnterTempSession(UserEnterTempSessionEvent),
+    MediaCheck(MediaCheckEvent),
+    SubscribeMsgPopup(Vec<SubscribeMsgPopup>),
+    SubscribeMsgChange(Vec<SubscribeMsgChange>),
+    SubscribeMsgSent(Vec<SubscribeMsgSent>),
+    AddExpressPath(ExpressPathEvent),
+    /// 直播间状态变更
+    LiveStatusChange(LiveStatusEvent),
+    /// 未处理的事件，保留转换为 JSON 后的消息原文
+    UnhandledEvent(String, Value),
+}
+
+impl EventMessage {
+    fn from_json(event_type: &str, value: Value) -> SdkResult<Self> {
+        let event = match event_type {
+            EVENT_USER_ENTER_TEMPSESSION => {
+                EventMessage::UserEnterTempSession(serde_json::from_value(value)?)
+            }
+            EVENT_WXA_MEDIA_CHECK => EventMessage::MediaCheck(serde_json::from_value(value)?),
+            EVENT_SUBSCRIBE_MSG_POPUP => {
+                let list = value
+                    .get("SubscribeMsgPopupEvent")
+                    .cloned()
+                    .unwrap_or(Value::Null);
+                EventMessage::SubscribeMsgPopup(
+                    serde_json::from_value::<SubscribeMsgList<_>>(list)?.list,
+                )
+            }
+            EVENT_SUBSCRIBE_MSG_CHANGE => {
+                let list = value
+                    .get("SubscribeMsgChangeEvent")
+                    .cloned()
+                    .unwrap_or(Value::Null);
+                EventMessage::SubscribeMsgChange(
+                    serde_json::from_value::<SubscribeMsgList<_>>(list)?.list,
+                )
+            }
+            EVENT_SUBSCRIBE_MSG_SENT => {
+                let list = value
+                    .get("SubscribeMsgSentEvent")
+                    .cloned()
+                    .unwrap_or(Value::Null);
+                EventMessage::SubscribeMsgSent(
+                    serde_json::from_value::<SubscribeMsgList<_>>(list)?.list,
+                )
+            }
+            EVENT_ADD_EXPRESS_PATH => EventMessage::AddExpressPath(serde_json::from_value(value)?),
+            EVENT_LIVE_STATUS_CHANGE => {
+                EventMessage::LiveStatusChange(serde_json::from_value(value)?)
+            }
+            _ => EventMessage::UnhandledEvent(event_type.to_owned(), value),
+        };
+        Ok(event)
+    }
+
+    pub fn get_event_type(&self) -> &str {
+        match self {
+            EventMessage::UserEnterTempSession(_) => EVENT_USER_ENTER_TEMPSESSION,
+            EventMessage::MediaCheck(_) => EVENT_WXA_MEDIA_CHECK,
+            EventMessage::SubscribeMsgPopup(_) => EVENT_SUBSCRIBE_MSG_POPUP,
+            EventMessage::SubscribeMsgChange(_) => EVENT_SUBSCRIBE_MSG_CHANGE,
+            EventMessage::SubscribeMsgSent(_) => EVENT_SUBSCRIBE_MSG_SENT,
+            EventMessage::AddExpressPath(_) => EVENT_ADD_EXPRESS_PATH,
+            EventMessage::LiveStatusChange(_) => EVENT_LIVE_STATUS_CHANGE,
+            EventMessage::UnhandledEvent(event_type, _) => event_type,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum ReceivedMessage {
+    /// 未处理的消息类型，保留转换为 JSON 后的消息原文
+    UnhandledMessage(String),
+    Text(TextMessage),
+    Image(ImageMessage),
+    MiniProgramPage(MiniProgramPageMessage),
+    Event(EventMessage),
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct MessageHeader {
+    to_user_name: String,
+    from_user_name: String,
+    #[serde(deserialize_with = "number_from_str")]
+    create_time: u64,
+    msg_type: String,
+    #[serde(default)]
+    event: Option<String>,
+}
+
+#[derive(Debug)]
+pub struct ReceivedEvent {
+    pub from: String,
+
+    pub to: String,
+
+    pub create_time: u64,
+
+    pub msg_type: String,
+
+    pub body: ReceivedMessage,
+}
+
+impl ReceivedEvent {
+    /// 解析明文消息，自动识别 JSON 和 XML 格式
+    pub fn parse(input: &str) -> SdkResult<Self> {
+        let value = DataFormat::detect(input).to_json(input)?;
+        Self::from_json(value)
+    }
+
+    /// 从转换后的 JSON 解析消息
+    pub fn from_json(value: Value) -> SdkResult<Self> {
+        let header = serde_json::from_value::<MessageHeader>(value.clone())?;
+        let body = match header.msg_type.as_str() {
+            MSG_TEXT => ReceivedMessage::Text(serde_json::from_value(value)?),
+            MSG_IMAGE => ReceivedMessage::Image(serde_json::from_value(value)?),
+            MSG_MINIPROGRAMPAGE => ReceivedMessage::MiniProgramPage(serde_json::from_value(value)?),
+            MSG_EVENT => {
+                let event_type = header.event.as_deref().ok_or_else(|| {
+                    SdkError::InvalidParams("parse msg need `Event` params".to_owned())
+                })?;
+                ReceivedMessage::Event(EventMessage::from_json(event_type, value)?)
+            }
+            _ => ReceivedMessage::UnhandledMessage(value.to_string()),
+        };
+        Ok(ReceivedEvent {
+            from: header.from_user_name,
+            to: header.to_user_name,
+            create_time: header.create_time,
+            msg_type: header.msg_type,
+            body,
+        })
+    }
+}
+
+/// 获取安全模式或兼容模式下消息中的 `Encrypt` 密文
+pub fn get_encrypt(input: &str) -> SdkResult<Option<String>> {
+    let value = DataFormat::detect(input).to_json(input)?;
+    Ok(value
+        .get("Encrypt")
+        .and_then(|v| v.as_str())
+        .map(|s| s.to_owned()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_text() -> SdkResult<()> {
+        let xml = "<xml>
+   <ToUserName><![CDATA[toUser]]></ToUserName>
+   <FromUserName><![CDATA[fromUser]]></FromUserName>
+   <CreateTime>1482048670</CreateTime>
+   <MsgType><![CDATA[text]]></MsgType>
+   <Content><![CDATA[this is a test]]></Content>
+   <MsgId>1234567890123456</MsgId>
+</xml>";
+        let json = r#"{
+  "ToUserName": "toUser",
+  "FromUserName": "fromUser",
+  "CreateTime": 1482048670,
+  "MsgType": "text",
+  "Content": "this is a test",
+  "MsgId": 1234567890123456
+}"#;
+        for input in [xml, json] {
+            let event = ReceivedEvent::parse(input)?;
+            assert_eq!(event.create_time, 1482048670);
+            match event.body {
+                ReceivedMessage::Text(t) => {
+                    assert_eq!(t.content, "this is a test");
+                    assert_eq!(t.msg_id, 1234567890123456);
+                }
+                _ => panic!("should be a text message"),
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn parse_miniprogrampage() -> SdkResult<()> {
+        let json = r#"{
+  "ToUserName": "toUser",
+  "FromUserName": "fromUser",
+  "CreateTime": 1482048670,
+  "MsgType": "miniprogrampage",
+  "MsgId": 1234567890123456,
+  "Title":"title",
+  "AppId":"appid",
+  "PagePath":"path",
+  "ThumbUrl":"",
+  "ThumbMediaId":""
+}"#;
+        let event = ReceivedEvent::parse(json)?;
+        assert!(matches!(event.body, ReceivedMessage::MiniProgramPage(_)));
+        Ok(())
+    }
+
+    #[test]
+    fn parse_subscribe_msg_popup() -> SdkResult<()> {
+        let xml = "<xml>
+    <ToUserName><![CDATA[gh_123456789abc]]></ToUserName>
+    <FromUserName><![CDATA[otFpruAK8D-E6EfStSYonYSBZ8_4]]></FromUserName>
+    <CreateTime>1610969440</CreateTime>
+    <MsgType><![CDATA[event]]></MsgType>
+    <Event><![CDATA[subscribe_msg_popup_event]]></Event>
+    <SubscribeMsgPopupEvent>
+        <List>
+            <TemplateId><![CDATA[VRR0UEO9VJOLs0MHlU0OilqX6MVFDwH3_3gz3Oc0NIc]]></TemplateId>
+            <SubscribeStatusString><![CDATA[accept]]></SubscribeStatusString>
+            <PopupScene>2</PopupScene>
+        </List>
+        <List>
+            <TemplateId><![CDATA[9nLIlbOQZC5Y89AZteFEux3WCXRRRG5Wfzkpssu4bLI]]></TemplateId>
+            <SubscribeStatusString><![CDATA[reject]]></SubscribeStatusString>
+            <PopupScene>2</PopupScene>
+        </List>
+    </SubscribeMsgPopupEvent>
+</xml>";
+        let json = r#"{
+  "ToUserName": "gh_123456789abc",
+  "FromUserName": "o7esq5OI1Uej6Xixw1lA2H7XDVbc",
+  "CreateTime": "1620973045",
+  "MsgType": "event",
+  "Event": "subscribe_msg_popup_event",
+  "SubscribeMsgPopupEvent": {
+    "List": {
+      "TemplateId": "hD-ixGOhYmUfjOnI8MCzQMPshzGVeux_2vBgb0m2o",
+      "SubscribeStatusString": "accept",
+      "PopupScene": "0"
+    }
+  }
+}"#;
+        let event = ReceivedEvent::parse(xml)?;
+        match event.body {
+            ReceivedMessage::Event(EventMessage::SubscribeMsgPopup(list)) => {
+                assert_eq!(list.len(), 2);
+                assert_eq!(list[1].subscribe_status_string, "reject");
+            }
+            _ => panic!("should be a subscribe_msg_popup_event"),
+        }
+        let event = ReceivedEvent::parse(json)?;
+        match event.body {
+            ReceivedMessage::Event(EventMessage::SubscribeMsgPopup(list)) => {
+                assert_eq!(list.len(), 1);
+                assert_eq!(list[0].popup_scene, Some(0));
+            }
+            _ => panic!("should be a subscribe_msg_popup_event"),
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn parse_subscribe_msg_sent() -> SdkResult<()> {
+        let json = r#"{
+  "ToUserName": "gh_123456789abc",
+  "FromUserName": "o7esq5PHRGBQYmeNyfG064wEFVpQ",
+  "CreateTime": "1620963428",
+  "MsgType": "event",
+  "Event": "subscribe_msg_sent_event",
+  "SubscribeMsgSentEvent": {
+    "List": {
+      "TemplateId": "BEwX0BOT3MqK3Uc5oTU3CGBqzjpndk2jzXf7VBg",
+      "MsgID": "1864323726461255680",
+      "ErrorCode": "0",
+      "ErrorStatus": "success"
+    }
+  }
+}"#;
+        let event = ReceivedEvent::parse(json)?;
+        match event.body {
+            ReceivedMessage::Event(EventMessage::SubscribeMsgSent(list)) => {
+                assert_eq!(list[0].error_code, 0);
+            }
+            _ => panic!("should be a subscribe_msg_sent_event"),
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn parse_media_check() -> SdkResult<()> {
+        let json = r#"{
+   "ToUserName": "gh_38cc49f9733b",
+   "FromUserName": "oH1fu0FdHqpToe2T6gBj0WyB8iS1",
+   "CreateTime": 1626959646,
+   "MsgType": "event",
+   "Event": "wxa_media_check",
+   "appid": "wx8f16a5e6c7b1e12a",
+   "trace_id": "60f96f1d-3845297a-1976a3ae",
+   "version": 2,
+   "detail": [{
+        "strategy": "content_model",
+        "errcode": 0,
+        "suggest": "pass",
+        "label": 100,
+        "prob": 90
+   }],
+   "errcode": 0,
+   "errmsg": "ok",
+   "result": {
+        "suggest": "pass",
+        "label": 100
+   }
+}"#;
+        let event = ReceivedEvent::parse(json)?;
+        match event.body {
+            ReceivedMessage::Event(EventMessage::MediaCheck(e)) => {
+                assert_eq!(e.trace_id, "60f96f1d-3845297a-1976a3ae");
+                assert_eq!(e.result.unwrap().suggest, "pass");
+                assert_eq!(e.detail.len(), 1);
+            }
+            _ => panic!("should be a wxa_media_check event"),
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn parse_add_express_path() -> SdkResult<()> {
+        let xml = "<xml>
+  <ToUserName><![CDATA[toUser]]></ToUserName>
+  <FromUserName><![CDATA[fromUser]]></FromUserName>
+  <CreateTime>1546924844</CreateTime>
+  <MsgType><![CDATA[event]]></MsgType>
+  <Event><![CDATA[add_express_path]]></Event>
+  <DeliveryID><![CDATA[SF]]></DeliveryID>
+  <WayBillId><![CDATA[123456789]]></WayBillId>
+  <Version>3</Version>
+  <Count>3</Count>
+  <Actions>
+    <ActionTime>1546924840</ActionTime>
+    <ActionType>100001</ActionType>
+    <ActionMsg><![CDATA[小哥A揽件成功]]></ActionMsg>
+  </Actions>
+  <Actions>
+    <ActionTime>1546924841</ActionTime>
+    <ActionType>200001</ActionType>
+    <ActionMsg><![CDATA[到达广州集包地]]></ActionMsg>
+  </Actions>
+</xml>";
+        let event = ReceivedEvent::parse(xml)?;
+        match event.body {
+            ReceivedMessage::Event(EventMessage::AddExpressPath(e)) => {
+                assert_eq!(e.delivery_id, "SF");
+                assert_eq!(e.actions.len(), 2);
+                assert_eq!(e.actions[1].action_type, 200001);
+            }
+            _ => panic!("should be a add_express_path event"),
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn parse_live_status_change() -> SdkResult<()> {
+        let xml = "<xml>
+  <ToUserName><![CDATA[gh_123456789abc]]></ToUserName>
+  <FromUserName><![CDATA[fromUser]]></FromUserName>
+  <CreateTime>1620963428</CreateTime>
+  <MsgType><![CDATA[event]]></MsgType>
+  <Event><![CDATA[live_status_change]]></Event>
+  <RoomId>1001</RoomId>
+  <LiveStatus>101</LiveStatus>
+</xml>";
+        let json = r#"{
+  "ToUserName": "gh_123456789abc",
+  "FromUserName": "fromUser",
+  "CreateTime": 1620963428,
+  "MsgType": "event",
+  "Event": "live_status_change",
+  "room_id": 1001,
+  "live_status": 101
+}"#;
+        for input in [xml, json] {
+            match ReceivedEvent::parse(input)?.body {
+                ReceivedMessage::Event(EventMessage::LiveStatusChange(e)) => {
+                    assert_eq!(e.room_id, 1001);
+                    assert_eq!(e.live_status, 101);
+                }
+                _ => panic!("should be a live_status_change event"),
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn parse_unhandled_event() -> SdkResult<()> {
+        let json = r#"{
+  "ToUserName": "toUser",
+  "FromUserName": "fromUser",
+  "CreateTime": 1482048670,
+  "MsgType": "event",
+  "Event": "some_new_event",
+  "Foo": "bar"
+}"#;
+        let event = ReceivedEvent::parse(json)?;
+        match event.body {
+            ReceivedMessage::Event(EventMessage::UnhandledEvent(t, v)) => {
+                assert_eq!(t, "some_new_event");
+                assert_eq!(v["Foo"], "bar");
+            }
+            _ => panic!("should be a unhandled event"),
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_get_encrypt() -> SdkResult<()> {
+        let xml = "<xml><ToUserName><![CDATA[toUser]]></ToUserName><Encrypt><![CDATA[msg_encrypt]]></Encrypt></xml>";
+        let json = r#"{"ToUserName": "toUser", "Encrypt": "msg_encrypt"}"#;
+        assert_eq!(DataFormat::detect(xml), DataFormat::Xml);
+        assert_eq!(DataFormat::detect(json), DataFormat::Json);
+        assert_eq!(get_encrypt(xml)?.as_deref(), Some("msg_encrypt"));
+        assert_eq!(get_encrypt(json)?.as_deref(), Some("msg_encrypt"));
+        assert_eq!(get_encrypt(r#"{"ToUserName": "toUser"}"#)?, None);
+        Ok(())
+    }
+}
@@ -10,10 +10,13 @@ pub struct PhoneNumber {
 #[derive(Debug, Serialize, Deserialize)]
 pub struct PhoneInfo {
     /// 用户绑定的手机号（国外手机号会有区号）
+    #[serde(alias = "phoneNumber")]
     pub phone_number: String,
     /// 没有区号的手机号
+    #[serde(alias = "purePhoneNumber")]
     pub pure_phone_number: String,
     /// 区号
+    #[serde(alias = "countryCode")]
     pub country_code: String,
     /// 数据水印
     pub watermark: Watermark,
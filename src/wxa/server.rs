@@ -0,0 +1,242 @@
+//! 小程序消息推送服务端处理
+//!
+//! 与 [MpServer][crate::mp::server::MpServer] 相同，[WxaServer] 与 web 框架无关。
+//! 小程序的客服消息不支持被动回复，处理完成后统一回复 `success`。
+use std::collections::HashMap;
+
+use async_trait::async_trait;
+
+use crate::{
+    access_token::AccessTokenProvider,
+    error::SdkError,
+    mp::server::{check_signature, error_response, parse_query, ServerResponse, REPLY_SUCCESS},
+    SdkResult,
+};
+
+use super::{
+    event::{
+        EventMessage, ExpressPathEvent, ImageMessage, LiveStatusEvent, MediaCheckEvent,
+        MiniProgramPageMessage, ReceivedEvent, ReceivedMessage, SubscribeMsgChange,
+        SubscribeMsgPopup, SubscribeMsgSent, TextMessage, UserEnterTempSessionEvent,
+    },
+    WxaSdk,
+};
+
+#[cfg(feature = "actix")]
+pub mod actix;
+#[cfg(feature = "axum")]
+pub mod axum;
+
+/// 小程序推送消息处理，按消息类型和事件类型分发
+#[async_trait]
+pub trait WxaMessageHandler: Send + Sync {
+    /// 处理所有推送消息，默认按 [ReceivedMessage] 和 [EventMessage] 的类型分发到下面的方法
+    async fn on_message(&self, event: &ReceivedEvent) -> SdkResult<()> {
+        match &event.body {
+            ReceivedMessage::Text(m) => self.on_text(event, m).await,
+            ReceivedMessage::Image(m) => self.on_image(event, m).await,
+            ReceivedMessage::MiniProgramPage(m) => self.on_miniprogrampage(event, m).await,
+            ReceivedMessage::UnhandledMessage(_) => self.on_unhandled_message(event).await,
+            ReceivedMessage::Event(e) => match e {
+                EventMessage::UserEnterTempSession(m) => {
+                    self.on_user_enter_tempsession(event, m).await
+                }
+                EventMessage::MediaCheck(m) => self.on_media_check(event, m).await,
+                EventMessage::SubscribeMsgPopup(m) => self.on_subscribe_msg_popup(event, m).await,
+                EventMessage::SubscribeMsgChange(m) => self.on_subscribe_msg_change(event, m).await,
+                EventMessage::SubscribeMsgSent(m) => self.on_subscribe_msg_sent(event, m).await,
+                EventMessage::AddExpressPath(m) => self.on_add_express_path(event, m).await,
+                EventMessage::LiveStatusChange(m) => self.on_live_status_change(event, m).await,
+                EventMessage::UnhandledEvent(_, _) => self.on_unhandled_event(event).await,
+            },
+        }
+    }
+
+    /// 文本消息
+    async fn on_text(&self, _event: &ReceivedEvent, _msg: &TextMessage) -> SdkResult<()> {
+        Ok(())
+    }
+    /// 图片消息
+    async fn on_image(&self, _event: &ReceivedEvent, _msg: &ImageMessage) -> SdkResult<()> {
+        Ok(())
+    }
+    /// 小程序卡片消息
+    async fn on_miniprogrampage(
+        &self,
+        _event: &ReceivedEvent,
+        _msg: &MiniProgramPageMessage,
+    ) -> SdkResult<()> {
+        Ok(())
+    }
+    /// 未处理的消息类型
+    async fn on_unhandled_message(&self, _event: &ReceivedEvent) -> SdkResult<()> {
+        Ok(())
+    }
+    /// 进入客服会话事件
+    async fn on_user_enter_tempsession(
+        &self,
+        _event: &ReceivedEvent,
+        _msg: &UserEnterTempSessionEvent,
+    ) -> SdkResult<()> {
+        Ok(())
+    }
+    /// 音视频内容安全识别结果
+    async fn on_media_check(
+        &self,
+        _event: &ReceivedEvent,
+        _msg: &MediaCheckEvent,
+    ) -> SdkResult<()> {
+        Ok(())
+    }
+    /// 用户操作订阅通知弹窗
+    async fn on_subscribe_msg_popup(
+        &self,
+        _event: &ReceivedEvent,
+        _msg: &[SubscribeMsgPopup],
+    ) -> SdkResult<()> {
+        Ok(())
+    }
+    /// 用户管理订阅通知
+    async fn on_subscribe_msg_change(
+        &self,
+        _event: &ReceivedEvent,
+        _msg: &[SubscribeMsgChange],
+    ) -> SdkResult<()> {
+        Ok(())
+    }
+    /// 发送订阅通知的结果
+    async fn on_subscribe_msg_sent(
+        &self,
+        _event: &ReceivedEvent,
+        _msg: &[SubscribeMsgSent],
+    ) -> SdkResult<()> {
+        Ok(())
+    }
+    /// 运单轨迹更新
+    async fn on_add_express_path(
+        &self,
+        _event: &ReceivedEvent,
+        _msg: &ExpressPathEvent,
+    ) -> SdkResult<()> {
+        Ok(())
+    }
+    /// 直播间状态变更事件
+    async fn on_live_status_change(
+        &self,
+        _event: &ReceivedEvent,
+        _msg: &LiveStatusEvent,
+    ) -> SdkResult<()> {
+        Ok(())
+    }
+    /// 未处理的事件类型
+    async fn on_unhandled_event(&self, _event: &ReceivedEvent) -> SdkResult<()> {
+        Ok(())
+    }
+}
+
+/// 小程序消息推送服务端
+pub struct WxaServer<T: AccessTokenProvider, H: WxaMessageHandler> {
+    sdk: WxaSdk<T>,
+    handler: H,
+}
+
+impl<T: AccessTokenProvider, H: WxaMessageHandler> WxaServer<T, H> {
+    /// `sdk` 需要通过 [WxSdk::wxa_with_server_config][crate::WxSdk::wxa_with_server_config] 构造
+    pub fn new(sdk: WxaSdk<T>, handler: H) -> Self {
+        WxaServer { sdk, handler }
+    }
+
+    pub fn sdk(&self) -> &WxaSdk<T> {
+        &self.sdk
+    }
+
+    /// 处理微信服务器的请求，`GET` 为服务器地址验证，`POST` 为消息推送
+    pub async fn handle(&self, method: &str, query: &str, body: &str) -> ServerResponse {
+        let token = match self.sdk.server_config {
+            Some(ref config) => config.token.as_str(),
+            None => return ServerResponse::error(500, "needs server_config"),
+        };
+        let params = parse_query(query);
+        if let Err(e) = check_signature(token, &params) {
+            return error_response(e);
+        }
+        if method.eq_ignore_ascii_case("GET") {
+            match params.get("echostr") {
+                Some(echostr) => ServerResponse::ok(echostr.as_str()),
+                None => error_response(SdkError::InvalidParams("echostr".to_owned())),
+            }
+        } else if method.eq_ignore_ascii_case("POST") {
+            self.handle_message(params, body).await
+        } else {
+            ServerResponse::error(405, "method not allowed")
+        }
+    }
+
+    async fn handle_message(&self, params: HashMap<String, String>, body: &str) -> ServerResponse {
+        let event = match self.sdk.parse_received_msg(body, Some(params)) {
+            Ok(event) => event,
+            Err(e) => return error_response(e),
+        };
+        match self.handler.on_message(&event).await {
+            Ok(()) => ServerResponse::ok(REPLY_SUCCESS),
+            Err(e) => ServerResponse::error(500, e.to_string()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+    use crate::{
+        mp::{event::signature::Signature, EncodingMode, ServerConfig},
+        WxSdk,
+    };
+
+    #[derive(Default)]
+    struct Counter(AtomicUsize);
+
+    #[async_trait]
+    impl WxaMessageHandler for Counter {
+        async fn on_text(&self, _event: &ReceivedEvent, _msg: &TextMessage) -> SdkResult<()> {
+            self.0.fetch_add(1, Ordering::SeqCst);
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_handle() {
+        let sdk = WxSdk::new_with_default_token_client("wx5823bf96d3bd56c7", "app_secret")
+            .wxa_with_server_config(ServerConfig::new("token", EncodingMode::Plain));
+        let server = WxaServer::new(sdk, Counter::default());
+        let signature = Signature::generate_signature(vec![
+            "token".to_owned(),
+            "1409659813".to_owned(),
+            "1372623149".to_owned(),
+        ]);
+        let query = format!(
+            "signature={}&timestamp=1409659813&nonce=1372623149",
+            signature
+        );
+        let res = server
+            .handle("GET", &format!("{}&echostr=hello", query), "")
+            .await;
+        assert_eq!(res, ServerResponse::ok("hello"));
+
+        let json = r#"{
+  "ToUserName": "toUser",
+  "FromUserName": "fromUser",
+  "CreateTime": 1482048670,
+  "MsgType": "text",
+  "Content": "this is a test",
+  "MsgId": 1234567890123456
+}"#;
+        let res = server.handle("POST", &query, json).await;
+        assert_eq!(res, ServerResponse::ok(REPLY_SUCCESS));
+        assert_eq!(server.handler.0.load(Ordering::SeqCst), 1);
+
+        let res = server.handle("POST", "signature=wrong", json).await;
+        assert_eq!(res.status, 400);
+    }
+}
@@ -0,0 +1,103 @@
+//! actix-web 集成，提供挂载小程序消息推送地址的 [Resource]
+use std::sync::Arc;
+
+use actix_web::{
+    web::{self, Data},
+    HttpRequest, Resource,
+};
+
+use crate::{access_token::AccessTokenProvider, mp::server::ServerResponse};
+
+use super::{WxaMessageHandler, WxaServer};
+
+/// 在 `path` 上挂载 `GET`（服务器地址验证）和 `POST`（消息推送）路由
+pub fn wxa_service<T, H>(path: &str, server: Arc<WxaServer<T, H>>) -> Resource
+where
+    T: AccessTokenProvider + 'static,
+    H: WxaMessageHandler + 'static,
+{
+    web::resource(path)
+        .app_data(Data::from(server))
+        .route(web::get().to(handle::<T, H>))
+        .route(web::post().to(handle::<T, H>))
+}
+
+async fn handle<T, H>(
+    req: HttpRequest,
+    body: String,
+    server: Data<WxaServer<T, H>>,
+) -> ServerResponse
+where
+    T: AccessTokenProvider + 'static,
+    H: WxaMessageHandler + 'static,
+{
+    server
+        .handle(req.method().as_str(), req.query_string(), &body)
+        .await
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use actix_web::{http::StatusCode, test, App};
+    use async_trait::async_trait;
+
+    use super::*;
+    use crate::{
+        mp::{event::signature::Signature, EncodingMode, ServerConfig},
+        wxa::event::{ReceivedEvent, TextMessage},
+        SdkResult, WxSdk,
+    };
+
+    #[derive(Default)]
+    struct Counter(AtomicUsize);
+
+    #[async_trait]
+    impl WxaMessageHandler for Counter {
+        async fn on_text(&self, _event: &ReceivedEvent, _msg: &TextMessage) -> SdkResult<()> {
+            self.0.fetch_add(1, Ordering::SeqCst);
+            Ok(())
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_wxa_service() {
+        let sdk = WxSdk::new_with_default_token_client("app_id", "app_secret")
+            .wxa_with_server_config(ServerConfig::new("token", EncodingMode::Plain));
+        let server = Arc::new(WxaServer::new(sdk, Counter::default()));
+        let app = test::init_service(App::new().service(wxa_service("/wxa", server.clone()))).await;
+
+        let signature = Signature::generate_signature(vec![
+            "token".to_owned(),
+            "1409659813".to_owned(),
+            "1372623149".to_owned(),
+        ]);
+        let query = format!(
+            "signature={}&timestamp=1409659813&nonce=1372623149",
+            signature
+        );
+        let req = test::TestRequest::get()
+            .uri(&format!("/wxa?{}&echostr=hello", query))
+            .to_request();
+        let body = test::call_and_read_body(&app, req).await;
+        assert_eq!(&body[..], b"hello");
+
+        let json = r#"{"ToUserName":"toUser","FromUserName":"fromUser","CreateTime":1482048670,"MsgType":"text","Content":"this is a test","MsgId":1234567890123456}"#;
+        let req = test::TestRequest::post()
+            .uri(&format!("/wxa?{}", query))
+            .set_payload(json)
+            .to_request();
+        let body = test::call_and_read_body(&app, req).await;
+        assert_eq!(&body[..], b"success");
+        assert_eq!(server.handler.0.load(Ordering::SeqCst), 1);
+
+        let req = test::TestRequest::post()
+            .uri("/wxa?signature=wrong&timestamp=1&nonce=1")
+            .set_payload(json)
+            .to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::FORBIDDEN);
+        assert_eq!(server.handler.0.load(Ordering::SeqCst), 1);
+    }
+}
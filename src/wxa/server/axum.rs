@@ -0,0 +1,127 @@
+//! axum 集成，提供挂载小程序消息推送地址的 [Router]
+use std::sync::Arc;
+
+use axum::{
+    extract::{RawQuery, State},
+    http::Method,
+    routing::get,
+    Router,
+};
+
+use crate::{access_token::AccessTokenProvider, mp::server::ServerResponse};
+
+use super::{WxaMessageHandler, WxaServer};
+
+/// 在 `path` 上挂载 `GET`（服务器地址验证）和 `POST`（消息推送）路由
+pub fn wxa_router<T, H>(path: &str, server: Arc<WxaServer<T, H>>) -> Router
+where
+    T: AccessTokenProvider + 'static,
+    H: WxaMessageHandler + 'static,
+{
+    Router::new()
+        .route(path, get(handle::<T, H>).post(handle::<T, H>))
+        .with_state(server)
+}
+
+async fn handle<T, H>(
+    State(server): State<Arc<WxaServer<T, H>>>,
+    method: Method,
+    RawQuery(query): RawQuery,
+    body: String,
+) -> ServerResponse
+where
+    T: AccessTokenProvider + 'static,
+    H: WxaMessageHandler + 'static,
+{
+    server
+        .handle(method.as_str(), query.as_deref().unwrap_or_default(), &body)
+        .await
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use async_trait::async_trait;
+    use axum::body::Body;
+    use axum::http::{Request, StatusCode};
+    use tower::ServiceExt;
+
+    use super::*;
+    use crate::{
+        mp::{event::signature::Signature, EncodingMode, ServerConfig},
+        wxa::event::{ReceivedEvent, TextMessage},
+        SdkResult, WxSdk,
+    };
+
+    #[derive(Default)]
+    struct Counter(AtomicUsize);
+
+    #[async_trait]
+    impl WxaMessageHandler for Counter {
+        async fn on_text(&self, _event: &ReceivedEvent, _msg: &TextMessage) -> SdkResult<()> {
+            self.0.fetch_add(1, Ordering::SeqCst);
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_wxa_router() {
+        let sdk = WxSdk::new_with_default_token_client("app_id", "app_secret")
+            .wxa_with_server_config(ServerConfig::new("token", EncodingMode::Plain));
+        let server = Arc::new(WxaServer::new(sdk, Counter::default()));
+        let app = wxa_router("/wxa", server.clone());
+
+        let signature = Signature::generate_signature(vec![
+            "token".to_owned(),
+            "1409659813".to_owned(),
+            "1372623149".to_owned(),
+        ]);
+        let query = format!(
+            "signature={}&timestamp=1409659813&nonce=1372623149",
+            signature
+        );
+        let res = app
+            .clone()
+            .oneshot(
+                Request::get(format!("/wxa?{}&echostr=hello", query))
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(res.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert_eq!(&body[..], b"hello");
+
+        let json = r#"{"ToUserName":"toUser","FromUserName":"fromUser","CreateTime":1482048670,"MsgType":"text","Content":"this is a test","MsgId":1234567890123456}"#;
+        let res = app
+            .clone()
+            .oneshot(
+                Request::post(format!("/wxa?{}", query))
+                    .body(Body::from(json))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(res.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert_eq!(&body[..], b"success");
+        assert_eq!(server.handler.0.load(Ordering::SeqCst), 1);
+
+        let res = app
+            .oneshot(
+                Request::post("/wxa?signature=wrong&timestamp=1&nonce=1")
+                    .body(Body::from(json))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::FORBIDDEN);
+        assert_eq!(server.handler.0.load(Ordering::SeqCst), 1);
+    }
+}
@@ -5,6 +5,7 @@ mod tests {
     use reqwest::blocking::get;
     use wx_sdk::{
         access_token::AccessTokenProvider,
+        mp::event::{crypto::encrypt_message, signature::Signature},
         mp::reply::{Reply, Text},
         mp::{EncodingMode, ServerConfig},
         wechat::WxSdk,
@@ -71,6 +72,84 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_compat_mode() -> SdkResult<()> {
+        let sdk = get_sdk().mp(ServerConfig::new(
+            "QDG6eK",
+            EncodingMode::Compat("jWmYm7qr5nMoAUwZRjGtBxmz3KA1tkAj3ykkR6q2B2C".to_owned()),
+        ));
+        let mut url = HashMap::new();
+        url.insert("timestamp".to_owned(), "1409659813".to_owned());
+        url.insert("nonce".to_owned(), "1372623149".to_owned());
+        let reply = Reply::Text(Text {
+            content: "你好".to_owned(),
+        });
+        let reply_xml = sdk.reply_to_xml(
+            reply,
+            "oia2TjjewbmiOUlr6X-1crbLOvLw",
+            "wx5823bf96d3bd56c7",
+            Some(url.clone()),
+        )?;
+        let doc = exile::parse(reply_xml.clone()).unwrap();
+        assert_eq!(doc.root().child("Content").unwrap().text().unwrap(), "你好");
+        assert!(doc.root().child("Encrypt").is_some());
+
+        let text = "<xml><ToUserName><![CDATA[wx5823bf96d3bd56c7]]></ToUserName><FromUserName><![CDATA[oia2TjjewbmiOUlr6X-1crbLOvLw]]></FromUserName><CreateTime>1409659813</CreateTime><MsgType><![CDATA[text]]></MsgType><Content><![CDATA[hello]]></Content><MsgId>6054768590064713728</MsgId></xml>";
+        let encrypt_msg = encrypt_message(
+            text,
+            "jWmYm7qr5nMoAUwZRjGtBxmz3KA1tkAj3ykkR6q2B2C",
+            "wx5823bf96d3bd56c7",
+        )?;
+        let msg = text.replace(
+            "</xml>",
+            &format!("<Encrypt><![CDATA[{}]]></Encrypt></xml>", encrypt_msg),
+        );
+        let msg_signature = Signature::generate_signature(vec![
+            "QDG6eK".to_owned(),
+            "1409659813".to_owned(),
+            "1372623149".to_owned(),
+            encrypt_msg,
+        ]);
+
+        // 兼容模式需要校验明文签名
+        assert!(sdk.parse_received_msg(&msg, Some(url.clone())).is_err());
+        url.insert(
+            "signature".to_owned(),
+            Signature::generate_signature(vec![
+                "QDG6eK".to_owned(),
+                "1409659813".to_owned(),
+                "1372623149".to_owned(),
+            ]),
+        );
+        url.insert("msg_signature".to_owned(), msg_signature);
+        let event = sdk.parse_received_msg(&msg, Some(url.clone()))?;
+        assert_eq!(event.msg_type, "text");
+        assert_eq!(event.to, "wx5823bf96d3bd56c7");
+
+        url.insert("msg_signature".to_owned(), "wrong".to_owned());
+        assert!(sdk.parse_received_msg(&msg, Some(url)).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_scan_event() -> SdkResult<()> {
+        use wx_sdk::mp::event::{
+            msg_event::event_scan::{MenuScanEvent, ScanEvent},
+            ReceivedMessageParser,
+        };
+
+        let xml = "<xml><Event><![CDATA[SCAN]]></Event><EventKey><![CDATA[SCENE_VALUE]]></EventKey><Ticket><![CDATA[TICKET]]></Ticket></xml>";
+        let doc = roxmltree::Document::parse(xml)?;
+        let event = ScanEvent::from_xml(&doc.root())?;
+        assert_eq!(event.ticket, "TICKET");
+
+        let xml = "<xml><Event><![CDATA[scancode_push]]></Event><EventKey><![CDATA[6]]></EventKey><ScanCodeInfo><ScanType><![CDATA[qrcode]]></ScanType><ScanResult><![CDATA[1]]></ScanResult></ScanCodeInfo></xml>";
+        let doc = roxmltree::Document::parse(xml)?;
+        let event = MenuScanEvent::from_xml(&doc.root())?;
+        assert_eq!(event.scan_type, "qrcode");
+        Ok(())
+    }
+
     // #[test]
     // fn test_base_64() -> Result<(), Box<dyn std::error::Error>> {
     //     use base64ct::{Base64, Encoding};
@@ -0,0 +1,99 @@
+#![cfg(feature = "testing")]
+
+use std::sync::{Arc, Mutex};
+
+use wx_sdk::{
+    mp::{EncodingMode, ServerConfig},
+    testing::{MockResponse, MockServer, MOCK_ACCESS_TOKEN},
+    SdkResult,
+};
+
+#[tokio::test]
+async fn test_mp_user_info() -> SdkResult<()> {
+    let server = MockServer::start().await?;
+    server.mock(
+        "/cgi-bin/user/info",
+        MockResponse::json(&serde_json::json!({
+            "subscribe": 1,
+            "openid": "o6_bmjrPTlm6_2sgVt7hMZOPfL2M",
+            "nickname": "",
+            "language": "zh_CN",
+            "headimgurl": "",
+            "subscribe_time": 1382694957,
+            "remark": "",
+            "groupid": 0,
+            "tagid_list": [128, 2],
+            "subscribe_scene": "ADD_SCENE_QR_CODE",
+            "qr_scene": 98765,
+            "qr_scene_str": ""
+        })),
+    );
+
+    let sdk = server.sdk_builder("app_id", "app_secret").build()?;
+    let mp = sdk.mp(ServerConfig::new("token", EncodingMode::Plain));
+    let info = mp
+        .user()
+        .info("o6_bmjrPTlm6_2sgVt7hMZOPfL2M", "zh_CN")
+        .await?;
+    assert_eq!(info.tagid_list, vec![128, 2]);
+
+    let requests = server.requests_to("/cgi-bin/user/info");
+    assert_eq!(requests.len(), 1);
+    assert_eq!(requests[0].access_token(), Some(MOCK_ACCESS_TOKEN));
+    assert_eq!(requests[0].query["openid"], "o6_bmjrPTlm6_2sgVt7hMZOPfL2M");
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_mp_api_error() -> SdkResult<()> {
+    let server = MockServer::start().await?;
+    server.mock(
+        "/cgi-bin/user/info/updateremark",
+        MockResponse::error(40003, "invalid openid rid: 6123abcd-1234abcd-12345678"),
+    );
+
+    let sdk = server.sdk_builder("app_id", "app_secret").build()?;
+    let mp = sdk.mp(ServerConfig::new("token", EncodingMode::Plain));
+    let err = mp
+        .user()
+        .info_updateremark("openid", "remark")
+        .await
+        .unwrap_err();
+    assert_eq!(err.wx_error_code().map(|c| c.code()), Some(40003));
+
+    let body: serde_json::Value =
+        server.requests_to("/cgi-bin/user/info/updateremark")[0].json()?;
+    assert_eq!(body["remark"], "remark");
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_mp_sns_oauth() -> SdkResult<()> {
+    let server = MockServer::start().await?;
+    server.mock(
+        "/sns/oauth2/access_token",
+        MockResponse::json(&serde_json::json!({
+            "access_token": "USER_ACCESS_TOKEN",
+            "expires_in": 7200,
+            "refresh_token": "REFRESH_TOKEN",
+            "openid": "OPENID",
+            "scope": "snsapi_userinfo"
+        })),
+    );
+
+    let calls = Arc::new(Mutex::new(Vec::new()));
+    let recorded = calls.clone();
+    let sdk = server
+        .sdk_builder("app_id", "app_secret")
+        .on_api_call(move |call| recorded.lock().unwrap().push(call.path.clone()))
+        .build()?;
+    let mp = sdk.mp(ServerConfig::new("token", EncodingMode::Plain));
+    mp.sns().oauth_access_token("CODE".to_owned()).await?;
+
+    // reported to the hook, without the access_token of the app
+    assert_eq!(*calls.lock().unwrap(), vec!["/sns/oauth2/access_token"]);
+    let requests = server.requests_to("/sns/oauth2/access_token");
+    assert_eq!(requests[0].access_token(), None);
+    assert_eq!(requests[0].query["code"], "CODE");
+    Ok(())
+}